@@ -0,0 +1,197 @@
+use crate::{
+    compression::Compression,
+    frame::{Frame, FRAME_OVERHEAD},
+    Error, Result,
+};
+use std::collections::{BTreeMap, VecDeque};
+
+pub type ChannelId = [u8; 16];
+
+/// Upper bound of a decompressed channel, larger channels are rejected when decoding.
+pub const MAX_CHANNEL_SIZE: usize = 10_000_000;
+
+/// Compressed sequence of encoded L2 blocks, split into frames to be submitted.
+///
+/// Before compression the blocks are laid out as `length (u32 BE) ++ block ++ ...`.
+pub struct ChannelOut {
+    pub id: ChannelId,
+    data: Vec<u8>,
+}
+
+impl ChannelOut {
+    pub fn new(blocks: &[Vec<u8>], compression: Compression) -> Result<Self> {
+        let mut raw = vec![];
+        for block in blocks {
+            raw.extend_from_slice(&(block.len() as u32).to_be_bytes());
+            raw.extend_from_slice(block);
+        }
+        Ok(Self {
+            id: rand::random(),
+            data: compression.compress(&raw)?,
+        })
+    }
+
+    /// Compressed size of the channel.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Splits the channel into frames of at most `max_frame_size` encoded bytes.
+    pub fn frames(&self, max_frame_size: usize) -> Result<Vec<Frame>> {
+        let chunk_size = max_frame_size.saturating_sub(FRAME_OVERHEAD).max(1);
+        let chunks = self.data.chunks(chunk_size).collect::<Vec<_>>();
+        if chunks.len() > u16::MAX as usize + 1 {
+            return Err(Error::TooManyFrames(chunks.len()));
+        }
+        let last = chunks.len() - 1;
+        Ok(chunks
+            .into_iter()
+            .enumerate()
+            .map(|(number, data)| Frame {
+                channel_id: self.id,
+                number: number as u16,
+                data: data.to_vec(),
+                is_last: number == last,
+            })
+            .collect())
+    }
+}
+
+/// Decodes the blocks of a channel from its compressed data, e.g. the data of its frames in
+/// order.
+pub fn decode_channel(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let raw = Compression::decompress(data, MAX_CHANNEL_SIZE)?;
+    let mut cursor = raw.as_slice();
+    let mut blocks = vec![];
+    while !cursor.is_empty() {
+        let (len, rest) = cursor
+            .split_first_chunk::<4>()
+            .ok_or(Error::MalformedChannel(
+                "block length truncated".to_string(),
+            ))?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(Error::MalformedChannel("block truncated".to_string()));
+        }
+        let (block, rest) = rest.split_at(len);
+        blocks.push(block.to_vec());
+        cursor = rest;
+    }
+    Ok(blocks)
+}
+
+struct PendingChannel {
+    id: ChannelId,
+    /// Inclusion index of the submission holding the first frame seen.
+    opened_at: u64,
+    frames: BTreeMap<u16, Vec<u8>>,
+    last: Option<u16>,
+    size: usize,
+}
+
+impl PendingChannel {
+    fn is_complete(&self) -> bool {
+        self.last
+            .is_some_and(|last| self.frames.len() == last as usize + 1)
+    }
+}
+
+/// Assembles channels from frames read in inclusion order. Channels are read in the order their
+/// first frame was included, a channel not completed within `timeout` submissions is dropped.
+pub struct ChannelBank {
+    channels: VecDeque<PendingChannel>,
+    timeout: u64,
+}
+
+impl ChannelBank {
+    pub fn new(timeout: u64) -> Self {
+        Self {
+            channels: VecDeque::new(),
+            timeout,
+        }
+    }
+
+    /// Adds a frame of the submission at `inclusion`, invalid frames are dropped.
+    pub fn add_frame(&mut self, frame: Frame, inclusion: u64) {
+        let index = match self
+            .channels
+            .iter()
+            .position(|channel| channel.id == frame.channel_id)
+        {
+            Some(index) => index,
+            None => {
+                self.channels.push_back(PendingChannel {
+                    id: frame.channel_id,
+                    opened_at: inclusion,
+                    frames: BTreeMap::new(),
+                    last: None,
+                    size: 0,
+                });
+                self.channels.len() - 1
+            }
+        };
+        let channel = &mut self.channels[index];
+        if channel.frames.contains_key(&frame.number) {
+            warn!("drop duplicated frame {}", frame.number);
+            return;
+        }
+        let beyond_last = match channel.last {
+            Some(last) => frame.number > last,
+            None => frame.is_last && channel.frames.keys().any(|n| *n > frame.number),
+        };
+        if beyond_last || (frame.is_last && channel.last.is_some()) {
+            warn!("drop frame {} beyond the last frame", frame.number);
+            return;
+        }
+        if frame.is_last {
+            channel.last = Some(frame.number);
+        }
+        channel.size += frame.data.len();
+        channel.frames.insert(frame.number, frame.data);
+        if channel.size > MAX_CHANNEL_SIZE {
+            warn!(
+                "drop channel {} larger than the limit",
+                hex::encode(channel.id)
+            );
+            self.channels.remove(index);
+        }
+    }
+
+    /// Drops the channels that timed out by the submission at `inclusion`.
+    pub fn prune(&mut self, inclusion: u64) {
+        self.channels.retain(|channel| {
+            let timed_out = inclusion > channel.opened_at + self.timeout;
+            if timed_out {
+                warn!("drop incomplete channel {}", hex::encode(channel.id));
+            }
+            !timed_out
+        });
+    }
+
+    /// Compressed data of the first channel, once it is complete.
+    pub fn read(&mut self) -> Option<(ChannelId, Vec<u8>)> {
+        if !self.channels.front()?.is_complete() {
+            return None;
+        }
+        let channel = self.channels.pop_front()?;
+        Some((channel.id, channel.frames.into_values().flatten().collect()))
+    }
+
+    /// Inclusion index of the first frame of the oldest channel waiting for frames.
+    pub fn oldest_inclusion(&self) -> Option<u64> {
+        self.channels.front().map(|channel| channel.opened_at)
+    }
+
+    /// Channels waiting for frames.
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{Error, Result};
+use std::io::{Read, Write};
+
+const ZSTD: u8 = 0;
+const BROTLI: u8 = 1;
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+/// Compression of the channel data, the algorithm is written as the first byte of the
+/// compressed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Zstd { level: i32 },
+    Brotli { quality: u32 },
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::Zstd { level: 3 }
+    }
+}
+
+impl Compression {
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match *self {
+            Self::Zstd { level } => {
+                let mut out = vec![ZSTD];
+                zstd::stream::copy_encode(data, &mut out, level)?;
+                Ok(out)
+            }
+            Self::Brotli { quality } => {
+                let mut out = vec![BROTLI];
+                let mut writer = brotli::CompressorWriter::new(
+                    &mut out,
+                    BROTLI_BUFFER_SIZE,
+                    quality,
+                    BROTLI_WINDOW_BITS,
+                );
+                writer.write_all(data)?;
+                drop(writer);
+                Ok(out)
+            }
+        }
+    }
+
+    /// Decompresses data of any supported algorithm, failing if it inflates beyond `max_size`.
+    pub fn decompress(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        let (tag, data) = data
+            .split_first()
+            .ok_or(Error::MalformedChannel("empty channel data".to_string()))?;
+        let reader: Box<dyn Read + '_> = match *tag {
+            ZSTD => Box::new(zstd::stream::read::Decoder::new(data)?),
+            BROTLI => Box::new(brotli::Decompressor::new(data, BROTLI_BUFFER_SIZE)),
+            tag => return Err(Error::UnknownCompression(tag)),
+        };
+        let mut out = vec![];
+        reader.take(max_size as u64 + 1).read_to_end(&mut out)?;
+        if out.len() > max_size {
+            return Err(Error::ChannelTooLarge(max_size));
+        }
+        Ok(out)
+    }
+}
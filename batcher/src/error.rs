@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("Unsupported submission version: {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Unknown compression: {0}")]
+    UnknownCompression(u8),
+
+    #[error("Malformed frame: {0}")]
+    MalformedFrame(String),
+
+    #[error("Malformed channel: {0}")]
+    MalformedChannel(String),
+
+    #[error("Channel larger than {0} bytes")]
+    ChannelTooLarge(usize),
+
+    #[error("Too many frames in channel: {0}")]
+    TooManyFrames(usize),
+
+    #[error("DA submission failed: {0}")]
+    SubmitFailed(String),
+
+    #[error("DA read failed: {0}")]
+    ReadFailed(String),
+}
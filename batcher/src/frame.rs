@@ -0,0 +1,97 @@
+use crate::{channel::ChannelId, Error, Result};
+
+/// Version byte leading every DA submission.
+pub const SUBMISSION_VERSION: u8 = 0;
+
+/// Bytes of a frame besides its data: channel id, frame number, data length and last flag.
+pub const FRAME_OVERHEAD: usize = 16 + 2 + 4 + 1;
+
+/// Upper bound of the data of a single frame, larger frames are rejected when decoding.
+pub const MAX_FRAME_DATA_SIZE: usize = 1_000_000;
+
+/// Piece of a channel's compressed data:
+///
+/// `channel_id (16) ++ frame_number (u16 BE) ++ data_length (u32 BE) ++ data ++ is_last (u8)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub channel_id: ChannelId,
+    pub number: u16,
+    pub data: Vec<u8>,
+    pub is_last: bool,
+}
+
+impl Frame {
+    pub fn encoded_len(&self) -> usize {
+        FRAME_OVERHEAD + self.data.len()
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.channel_id);
+        buf.extend_from_slice(&self.number.to_be_bytes());
+        buf.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.data);
+        buf.push(self.is_last as u8);
+    }
+
+    pub fn decode(cursor: &mut &[u8]) -> Result<Self> {
+        let channel_id = take(cursor, "channel id")?;
+        let number = u16::from_be_bytes(take(cursor, "frame number")?);
+        let len = u32::from_be_bytes(take(cursor, "frame data length")?) as usize;
+        if len > MAX_FRAME_DATA_SIZE {
+            return Err(Error::MalformedFrame(format!("frame data of {len} bytes")));
+        }
+        if cursor.len() < len {
+            return Err(Error::MalformedFrame("frame data truncated".to_string()));
+        }
+        let (data, rest) = cursor.split_at(len);
+        *cursor = rest;
+        let is_last = match take(cursor, "last flag")? {
+            [0] => false,
+            [1] => true,
+            [flag] => return Err(Error::MalformedFrame(format!("invalid last flag {flag}"))),
+        };
+        Ok(Self {
+            channel_id,
+            number,
+            data: data.to_vec(),
+            is_last,
+        })
+    }
+}
+
+/// Encodes the frames of one DA submission: `version ++ frame ++ frame ...`.
+pub fn encode_submission(frames: &[Frame]) -> Vec<u8> {
+    let mut buf = vec![SUBMISSION_VERSION];
+    for frame in frames {
+        frame.encode(&mut buf);
+    }
+    buf
+}
+
+/// Decodes every frame of a DA submission, a single malformed frame rejects all of them.
+pub fn decode_submission(data: &[u8]) -> Result<Vec<Frame>> {
+    let (version, mut cursor) = data
+        .split_first()
+        .ok_or(Error::MalformedFrame("empty submission".to_string()))?;
+    if *version != SUBMISSION_VERSION {
+        return Err(Error::UnsupportedVersion(*version));
+    }
+    let mut frames = vec![];
+    while !cursor.is_empty() {
+        frames.push(Frame::decode(&mut cursor)?);
+    }
+    if frames.is_empty() {
+        return Err(Error::MalformedFrame(
+            "submission without frames".to_string(),
+        ));
+    }
+    Ok(frames)
+}
+
+fn take<const N: usize>(cursor: &mut &[u8], field: &str) -> Result<[u8; N]> {
+    let (head, tail) = cursor
+        .split_first_chunk::<N>()
+        .ok_or_else(|| Error::MalformedFrame(format!("{field} truncated")))?;
+    *cursor = tail;
+    Ok(*head)
+}
@@ -0,0 +1,132 @@
+//! Batches encoded L2 blocks into compressed channels and submits them to a DA backend.
+//!
+//! A channel holds the blocks of one flush, compressed together. It is split into frames, and
+//! every frame is posted as its own submission: `version ++ frame`. Readers assemble the frames
+//! back into channels with a [`ChannelBank`].
+
+use std::time::{Duration, Instant};
+
+pub mod channel;
+pub mod compression;
+pub mod error;
+pub mod frame;
+pub mod sink;
+pub mod source;
+#[cfg(test)]
+mod tests;
+
+pub use {
+    channel::{ChannelBank, ChannelId, ChannelOut},
+    compression::Compression,
+    error::{Error, Result},
+    frame::Frame,
+    sink::{DaSink, FileSink},
+    source::{DaSource, FileSource},
+};
+
+#[macro_use]
+extern crate log;
+
+pub const DEFAULT_MAX_CHANNEL_SIZE: usize = 1_000_000;
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 120_000;
+pub const DEFAULT_MAX_CHANNEL_DURATION: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone)]
+pub struct BatcherConfig {
+    pub compression: Compression,
+    /// Uncompressed size of the pending blocks that flushes a channel.
+    pub max_channel_size: usize,
+    /// Encoded size limit of a frame, channels are split into frames of this size.
+    pub max_frame_size: usize,
+    /// Age of the oldest pending block that flushes a channel.
+    pub max_channel_duration: Duration,
+}
+
+impl Default for BatcherConfig {
+    fn default() -> Self {
+        Self {
+            compression: Compression::default(),
+            max_channel_size: DEFAULT_MAX_CHANNEL_SIZE,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_channel_duration: DEFAULT_MAX_CHANNEL_DURATION,
+        }
+    }
+}
+
+pub struct Batcher<S: DaSink> {
+    config: BatcherConfig,
+    sink: S,
+    pending: Vec<Vec<u8>>,
+    pending_size: usize,
+    opened_at: Option<Instant>,
+}
+
+impl<S: DaSink> Batcher<S> {
+    pub fn new(config: BatcherConfig, sink: S) -> Self {
+        Self {
+            config,
+            sink,
+            pending: vec![],
+            pending_size: 0,
+            opened_at: None,
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Blocks added since the last flush.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Adds an encoded block, flushing the channel once it reaches the size limit.
+    pub async fn add_block(&mut self, block: Vec<u8>) -> Result<Option<ChannelId>> {
+        self.pending_size += block.len();
+        self.pending.push(block);
+        self.opened_at.get_or_insert_with(Instant::now);
+        if self.pending_size >= self.config.max_channel_size {
+            return self.flush().await;
+        }
+        Ok(None)
+    }
+
+    /// Flushes the channel if its oldest block waited longer than the channel duration.
+    pub async fn tick(&mut self) -> Result<Option<ChannelId>> {
+        match self.opened_at {
+            Some(opened_at) if opened_at.elapsed() >= self.config.max_channel_duration => {
+                self.flush().await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Submits the pending blocks as a channel, `None` if there is nothing to submit. The
+    /// blocks are kept pending if a submission fails and go into a new channel on the next
+    /// flush, the frames already submitted are left incomplete.
+    pub async fn flush(&mut self) -> Result<Option<ChannelId>> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let channel = ChannelOut::new(&self.pending, self.config.compression)?;
+        let frames = channel.frames(self.config.max_frame_size)?;
+        for frame in frames.iter() {
+            self.sink
+                .submit(frame::encode_submission(std::slice::from_ref(frame)))
+                .await?;
+        }
+        debug!(
+            "submitted channel {} with {} blocks, {} bytes in {} frames",
+            hex::encode(channel.id),
+            self.pending.len(),
+            channel.len(),
+            frames.len()
+        );
+
+        self.pending.clear();
+        self.pending_size = 0;
+        self.opened_at = None;
+        Ok(Some(channel.id))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::Result;
+use async_trait::async_trait;
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Extension of the files written by [`FileSink`].
+pub const SUBMISSION_FILE_EXTENSION: &str = "batch";
+
+/// Destination of encoded DA submissions, e.g. an L1 blob or a DA layer.
+#[async_trait]
+pub trait DaSink: Send {
+    /// Submits one encoded submission, submissions must be included in the order they are
+    /// submitted.
+    async fn submit(&mut self, submission: Vec<u8>) -> Result<()>;
+}
+
+/// Writes every submission to its own file in a directory, named after its position in the
+/// inclusion order, e.g. `00000000000000000042.batch`.
+pub struct FileSink {
+    dir: PathBuf,
+    next_index: u64,
+}
+
+impl FileSink {
+    /// Opens the sink in `dir`, new submissions follow the ones already in it.
+    pub fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let next_index = submission_files(dir)?
+            .last()
+            .map_or(0, |(index, _)| index + 1);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            next_index,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+}
+
+#[async_trait]
+impl DaSink for FileSink {
+    async fn submit(&mut self, submission: Vec<u8>) -> Result<()> {
+        let path = submission_path(&self.dir, self.next_index);
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&submission)?;
+        file.sync_all()?;
+        fs::rename(tmp, path)?;
+        self.next_index += 1;
+        Ok(())
+    }
+}
+
+pub fn submission_path(dir: &Path, index: u64) -> PathBuf {
+    dir.join(format!("{index:020}.{SUBMISSION_FILE_EXTENSION}"))
+}
+
+/// Submission files in `dir` sorted by their index, other files are ignored.
+pub fn submission_files(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SUBMISSION_FILE_EXTENSION) {
+            continue;
+        }
+        if let Some(index) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            files.push((index, path));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
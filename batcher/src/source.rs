@@ -0,0 +1,56 @@
+use crate::{sink::submission_path, Result};
+use async_trait::async_trait;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Origin of DA submissions, the counterpart of [`crate::DaSink`].
+#[async_trait]
+pub trait DaSource: Send {
+    /// Next submission in inclusion order with its inclusion index, `None` until there is one.
+    async fn next_submission(&mut self) -> Result<Option<(u64, Vec<u8>)>>;
+
+    /// Inclusion index of the next submission to read.
+    fn next_index(&self) -> u64;
+
+    /// Continues reading at the submission of inclusion index `index`.
+    fn seek(&mut self, index: u64);
+}
+
+/// Reads the submissions written by [`crate::FileSink`] one after another.
+pub struct FileSource {
+    dir: PathBuf,
+    next_index: u64,
+}
+
+impl FileSource {
+    pub fn new(dir: &Path, start_index: u64) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            next_index: start_index,
+        }
+    }
+}
+
+#[async_trait]
+impl DaSource for FileSource {
+    async fn next_submission(&mut self) -> Result<Option<(u64, Vec<u8>)>> {
+        match fs::read(submission_path(&self.dir, self.next_index)) {
+            Ok(data) => {
+                self.next_index += 1;
+                Ok(Some((self.next_index - 1, data)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    fn seek(&mut self, index: u64) {
+        self.next_index = index;
+    }
+}
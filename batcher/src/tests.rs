@@ -0,0 +1,204 @@
+use anyhow::Result;
+use std::{fs, time::Duration};
+
+use crate::{
+    channel::decode_channel,
+    frame::{decode_submission, encode_submission},
+    sink::submission_files,
+    Batcher, BatcherConfig, ChannelBank, ChannelOut, Compression, DaSource, Error, FileSink,
+    FileSource, Frame,
+};
+
+/// Random blocks, so they do not compress away.
+fn blocks(count: usize, size: usize) -> Vec<Vec<u8>> {
+    (0..count)
+        .map(|_| (0..size).map(|_| rand::random()).collect())
+        .collect()
+}
+
+#[test]
+fn channel_round_trip_works() -> Result<()> {
+    let blocks = blocks(20, 500);
+    for compression in [
+        Compression::Zstd { level: 3 },
+        Compression::Brotli { quality: 5 },
+    ] {
+        let channel = ChannelOut::new(&blocks, compression)?;
+        // small frames split the channel
+        let frames = channel.frames(100)?;
+        assert!(frames.len() > 1);
+        assert!(frames.iter().all(|frame| frame.encoded_len() <= 100));
+        assert!(frames.last().unwrap().is_last);
+        assert!(frames[..frames.len() - 1].iter().all(|f| !f.is_last));
+
+        let submission = encode_submission(&frames);
+        let decoded = decode_submission(&submission)?;
+        assert_eq!(decoded, frames);
+
+        let data = decoded
+            .iter()
+            .flat_map(|frame| frame.data.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(decode_channel(&data)?, blocks);
+    }
+    Ok(())
+}
+
+#[test]
+fn malformed_submissions_are_rejected() -> Result<()> {
+    let frame = Frame {
+        channel_id: [1; 16],
+        number: 0,
+        data: vec![1, 2, 3],
+        is_last: true,
+    };
+    let submission = encode_submission(&[frame]);
+
+    let mut wrong_version = submission.clone();
+    wrong_version[0] = 1;
+    assert!(matches!(
+        decode_submission(&wrong_version),
+        Err(Error::UnsupportedVersion(1))
+    ));
+    assert!(matches!(
+        decode_submission(&submission[..submission.len() - 1]),
+        Err(Error::MalformedFrame(_))
+    ));
+    let mut wrong_flag = submission.clone();
+    *wrong_flag.last_mut().unwrap() = 2;
+    assert!(matches!(
+        decode_submission(&wrong_flag),
+        Err(Error::MalformedFrame(_))
+    ));
+    assert!(decode_submission(&[0]).is_err());
+
+    assert!(matches!(
+        decode_channel(&[9, 1, 2]),
+        Err(Error::UnknownCompression(9))
+    ));
+    // a block longer than the channel
+    let mut raw = 100u32.to_be_bytes().to_vec();
+    raw.push(1);
+    let data = Compression::default().compress(&raw)?;
+    assert!(matches!(
+        decode_channel(&data),
+        Err(Error::MalformedChannel(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn batcher_flushes_by_size_and_time() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let config = BatcherConfig {
+        max_channel_size: 1000,
+        max_frame_size: 300,
+        max_channel_duration: Duration::from_millis(50),
+        ..Default::default()
+    };
+    let mut batcher = Batcher::new(config.clone(), FileSink::new(dir.path())?);
+
+    // nothing pending, nothing flushed
+    assert!(batcher.flush().await?.is_none());
+
+    let blocks = blocks(3, 400);
+    assert!(batcher.add_block(blocks[0].clone()).await?.is_none());
+    assert!(batcher.add_block(blocks[1].clone()).await?.is_none());
+    assert!(batcher.tick().await?.is_none());
+    let first = batcher.add_block(blocks[2].clone()).await?.unwrap();
+    assert_eq!(batcher.pending(), 0);
+
+    batcher.add_block(vec![42]).await?;
+    tokio::time::sleep(config.max_channel_duration).await;
+    let second = batcher.tick().await?.unwrap();
+    assert_ne!(first, second);
+
+    // submissions are ordered files, reopening continues after them
+    let files = submission_files(dir.path())?;
+    let mut channels: Vec<(_, Vec<u8>)> = vec![];
+    for (_, path) in &files {
+        for frame in decode_submission(&fs::read(path)?)? {
+            match channels.last_mut() {
+                Some((id, data)) if *id == frame.channel_id => data.extend(frame.data),
+                _ => channels.push((frame.channel_id, frame.data)),
+            }
+        }
+    }
+    assert_eq!(channels.len(), 2);
+    assert_eq!(channels[0].0, first);
+    assert_eq!(decode_channel(&channels[0].1)?, blocks);
+    assert_eq!(decode_channel(&channels[1].1)?, vec![vec![42]]);
+
+    let sink = FileSink::new(dir.path())?;
+    assert_eq!(sink.next_index(), files.len() as u64);
+    Ok(())
+}
+
+#[test]
+fn channel_bank_works() -> Result<()> {
+    let blocks = blocks(10, 300);
+    let first = ChannelOut::new(&blocks[..5], Compression::default())?.frames(80)?;
+    let second = ChannelOut::new(&blocks[5..], Compression::default())?.frames(80)?;
+    assert!(first.len() > 2 && second.len() > 2);
+
+    let mut bank = ChannelBank::new(10);
+    // out of order and duplicated frames, the second channel waits for the first one
+    for frame in second.iter().rev() {
+        bank.add_frame(frame.clone(), 0);
+    }
+    bank.add_frame(first[1].clone(), 1);
+    bank.add_frame(first[1].clone(), 1);
+    assert!(bank.read().is_none());
+    for frame in first.iter().skip(2).chain(&first[..1]) {
+        bank.add_frame(frame.clone(), 2);
+    }
+    let (id, data) = bank.read().unwrap();
+    assert_eq!(id, first[0].channel_id);
+    assert_eq!(decode_channel(&data)?, blocks[..5]);
+    let (_, data) = bank.read().unwrap();
+    assert_eq!(decode_channel(&data)?, blocks[5..]);
+    assert!(bank.is_empty());
+
+    // a last frame before a later frame is dropped, the channel never completes
+    let frame = |number: u16, is_last| Frame {
+        channel_id: [7; 16],
+        number,
+        data: vec![number as u8],
+        is_last,
+    };
+    bank.add_frame(frame(1, false), 3);
+    bank.add_frame(frame(0, true), 3);
+    assert!(bank.read().is_none());
+    assert_eq!(bank.oldest_inclusion(), Some(3));
+
+    bank.prune(13);
+    assert_eq!(bank.len(), 1);
+    bank.prune(14);
+    assert!(bank.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn file_source_reads_in_order() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut batcher = Batcher::new(BatcherConfig::default(), FileSink::new(dir.path())?);
+    let mut source = FileSource::new(dir.path(), 0);
+    assert!(source.next_submission().await?.is_none());
+
+    batcher.add_block(vec![1]).await?;
+    batcher.flush().await?;
+    batcher.add_block(vec![2]).await?;
+    batcher.flush().await?;
+    for (index, block) in [(0, 1), (1, 2)] {
+        let (inclusion, data) = source.next_submission().await?.unwrap();
+        assert_eq!(inclusion, index);
+        let frames = decode_submission(&data)?;
+        assert_eq!(decode_channel(&frames[0].data)?, vec![vec![block]]);
+    }
+    assert!(source.next_submission().await?.is_none());
+    assert_eq!(source.next_index(), 2);
+
+    source.seek(1);
+    assert_eq!(source.next_submission().await?.unwrap().0, 1);
+    Ok(())
+}
@@ -1,36 +1,312 @@
-use crate::l1::attribute::PayloadAttributeImpl;
-use igloo_interface::derive::DaDerive;
-use std::sync::Arc;
-use tokio::sync::{mpsc::Receiver, RwLock};
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-#[derive(Clone, Default)]
+use anyhow::{bail, ensure, Result};
+use async_trait::async_trait;
+use igloo_batcher::{channel::decode_channel, frame::decode_submission, ChannelBank, DaSource};
+use igloo_interface::{derive::DaDerive, l1::Epoch};
+use igloo_verifier::rollup::RollupParams;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    l1::{attribute::PayloadAttributeImpl, L1Height},
+    l2::head::L2BlockRef,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Submissions a channel may span before it is dropped.
+pub const DEFAULT_CHANNEL_TIMEOUT: u64 = 32;
+/// L1 blocks an attribute's epoch may advance past the previous one.
+pub const DEFAULT_MAX_EPOCH_GAP: L1Height = 1;
+
+#[derive(Debug, Clone)]
+pub struct DaDeriveConfig {
+    pub channel_timeout: u64,
+    pub max_epoch_gap: L1Height,
+    pub rollup: RollupParams,
+}
+
+impl Default for DaDeriveConfig {
+    fn default() -> Self {
+        Self {
+            channel_timeout: DEFAULT_CHANNEL_TIMEOUT,
+            max_epoch_gap: DEFAULT_MAX_EPOCH_GAP,
+            rollup: RollupParams::default(),
+        }
+    }
+}
+
+/// Position of the last derived attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct DerivedHead {
+    epoch_hash: [u8; 32],
+    epoch_height: L1Height,
+    sequence_number: u64,
+}
+
+impl From<&PayloadAttributeImpl> for DerivedHead {
+    fn from(attribute: &PayloadAttributeImpl) -> Self {
+        Self {
+            epoch_hash: attribute.epoch.block_hash(),
+            epoch_height: attribute.epoch.block_height(),
+            sequence_number: attribute.sequence_number,
+        }
+    }
+}
+
+/// Position of a DA derivation to resume from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaProgress {
+    /// Submission to read first, the oldest one holding frames of a channel not read yet.
+    pub next_index: u64,
+    head: Option<DerivedHead>,
+}
+
+/// Derives attributes from the batches posted to a DA source, in the order the batches were
+/// included. A channel holding a malformed attribute, or one out of sequence with the attributes
+/// derived before it, is rejected as a whole.
+///
+/// The DA sources have no L1 inclusion of their own, a submission counts as included at the L1
+/// height set when it is read, see [`DaDeriveImpl::set_l1_height`].
 pub struct DaDeriveImpl {
-    cached: Arc<RwLock<Vec<PayloadAttributeImpl>>>,
+    source: Box<dyn DaSource>,
+    config: DaDeriveConfig,
+    bank: ChannelBank,
+    head: Option<DerivedHead>,
+    ready: VecDeque<PayloadAttributeImpl>,
+    /// L1 height the submissions read from now on are included at.
+    l1_height: Option<L1Height>,
 }
 
 impl DaDerive for DaDeriveImpl {
     type Item = PayloadAttributeImpl;
 
     async fn next(&mut self) -> Option<Self::Item> {
-        self.cached.write().await.pop()
+        loop {
+            if let Some(attribute) = self.ready.pop_front() {
+                return Some(attribute);
+            }
+            match self.source.next_submission().await {
+                Ok(Some((inclusion, data))) => self.add_submission(inclusion, &data),
+                Ok(None) => return None,
+                Err(e) => {
+                    warn!("failed to read DA submission: {}", e);
+                    return None;
+                }
+            }
+        }
     }
 }
 
 impl DaDeriveImpl {
-    pub fn run(&self, receiver: Receiver<Vec<PayloadAttributeImpl>>) {
-        tokio::spawn(Self::try_update(self.cached.clone(), receiver));
+    pub fn new(source: Box<dyn DaSource>, config: DaDeriveConfig) -> Self {
+        Self {
+            source,
+            bank: ChannelBank::new(config.channel_timeout),
+            config,
+            head: None,
+            ready: VecDeque::new(),
+            l1_height: None,
+        }
     }
 
-    pub async fn try_update(
-        cached: Arc<RwLock<Vec<PayloadAttributeImpl>>>,
-        mut receiver: Receiver<Vec<PayloadAttributeImpl>>,
-    ) {
-        loop {
-            let payloads = receiver.recv().await;
+    /// Sets the L1 height of the submissions read from now on, their attributes have to be
+    /// within the sequencing window of their epoch at it. Nothing is checked before it is set.
+    pub fn set_l1_height(&mut self, l1_height: L1Height) {
+        self.l1_height = Some(l1_height);
+    }
+
+    /// Position to resume from, once `next` returned `None`.
+    pub fn progress(&self) -> DaProgress {
+        DaProgress {
+            next_index: self
+                .bank
+                .oldest_inclusion()
+                .unwrap_or(self.source.next_index()),
+            head: self.head,
+        }
+    }
 
-            if let Some(payloads) = payloads {
-                cached.write().await.extend(payloads);
+    /// Continues after `block`, the safe head moved back by an L1 reorg: the attributes after it
+    /// are derived again from the submissions read from now on. `None` restarts from the first
+    /// attribute.
+    pub fn reset_head(&mut self, block: Option<&L2BlockRef>) {
+        self.ready.clear();
+        self.head = block.map(|block| DerivedHead {
+            epoch_hash: block.epoch.block_hash(),
+            epoch_height: block.epoch.block_height(),
+            sequence_number: block.sequence_number,
+        });
+    }
+
+    /// Resumes from `progress`, the channels of the submissions before it are not read again.
+    pub fn resume(&mut self, progress: &DaProgress) {
+        self.source.seek(progress.next_index);
+        self.bank = ChannelBank::new(self.config.channel_timeout);
+        self.ready.clear();
+        self.head = progress.head;
+    }
+
+    fn add_submission(&mut self, inclusion: u64, data: &[u8]) {
+        self.bank.prune(inclusion);
+        match decode_submission(data) {
+            Ok(frames) => frames
+                .into_iter()
+                .for_each(|frame| self.bank.add_frame(frame, inclusion)),
+            Err(e) => warn!("drop malformed DA submission {}: {}", inclusion, e),
+        }
+        while let Some((id, data)) = self.bank.read() {
+            if let Err(e) = self.add_channel(&data) {
+                warn!("reject channel {}: {}", hex::encode(id), e);
+            }
+        }
+    }
+
+    fn add_channel(&mut self, data: &[u8]) -> Result<()> {
+        let mut head = self.head;
+        let mut attributes = vec![];
+        for block in decode_channel(data)? {
+            let attribute = PayloadAttributeImpl::decode(&block)?;
+            if self.check_next(head, &attribute)? {
+                head = Some((&attribute).into());
+                attributes.push(attribute);
             }
         }
+        self.head = head;
+        self.ready.extend(attributes);
+        Ok(())
+    }
+
+    /// Whether `attribute` follows `head`, `false` for attributes derived already. Fails if the
+    /// attribute skips sequence numbers or epochs, is on another epoch of the same height, drifts
+    /// too far from its epoch, or is included outside the sequencing window of its epoch.
+    fn check_next(
+        &self,
+        head: Option<DerivedHead>,
+        attribute: &PayloadAttributeImpl,
+    ) -> Result<bool> {
+        let next = DerivedHead::from(attribute);
+        self.config
+            .rollup
+            .check_drift(attribute.epoch.timestamp(), next.sequence_number)?;
+        if let Some(l1_height) = self.l1_height {
+            self.config
+                .rollup
+                .check_window(next.epoch_height, l1_height)?;
+        }
+        let Some(head) = head else {
+            ensure!(
+                next.sequence_number == 0,
+                "first attribute at sequence number {}",
+                next.sequence_number
+            );
+            return Ok(true);
+        };
+        if next.epoch_height < head.epoch_height {
+            return Ok(false);
+        }
+        if next.epoch_height == head.epoch_height {
+            ensure!(
+                next.epoch_hash == head.epoch_hash,
+                "epoch {} does not match the derived one",
+                next.epoch_height
+            );
+            if next.sequence_number <= head.sequence_number {
+                return Ok(false);
+            }
+            ensure!(
+                Some(next.sequence_number) == head.sequence_number.checked_add(1),
+                "sequence number {} does not follow {}",
+                next.sequence_number,
+                head.sequence_number
+            );
+            return Ok(true);
+        }
+        if next.epoch_height - head.epoch_height > self.config.max_epoch_gap {
+            bail!(
+                "epoch {} out of the window after epoch {}",
+                next.epoch_height,
+                head.epoch_height
+            );
+        }
+        ensure!(
+            next.sequence_number == 0,
+            "new epoch {} at sequence number {}",
+            next.epoch_height,
+            next.sequence_number
+        );
+        Ok(true)
+    }
+}
+
+/// Reads submissions from a local HTTP stand-in of a DA layer, serving the JSON-RPC method
+/// `da_getSubmission(index)` with the hex encoded submission or `null`.
+pub struct HttpSource {
+    client: reqwest::Client,
+    url: String,
+    next_index: u64,
+    next_id: AtomicU64,
+}
+
+impl HttpSource {
+    pub fn new(url: String, start_index: u64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            next_index: start_index,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    async fn get_submission(&self, index: u64) -> Result<Option<Vec<u8>>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": "da_getSubmission",
+            "params": [index],
+        });
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            bail!("da_getSubmission failed: {}", error);
+        }
+        match response["result"].as_str() {
+            Some(data) => Ok(Some(hex::decode(data.trim_start_matches("0x"))?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl DaSource for HttpSource {
+    async fn next_submission(&mut self) -> igloo_batcher::Result<Option<(u64, Vec<u8>)>> {
+        let submission = self
+            .get_submission(self.next_index)
+            .await
+            .map_err(|e| igloo_batcher::Error::ReadFailed(e.to_string()))?;
+        Ok(submission.map(|data| {
+            self.next_index += 1;
+            (self.next_index - 1, data)
+        }))
+    }
+
+    fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    fn seek(&mut self, index: u64) {
+        self.next_index = index;
     }
 }
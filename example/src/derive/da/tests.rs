@@ -0,0 +1,207 @@
+use std::{fs, net::SocketAddr, path::PathBuf, sync::Arc};
+
+use anyhow::Result;
+use igloo_batcher::{
+    sink::submission_path, Batcher, BatcherConfig, DaSink, DaSource, FileSink, FileSource,
+};
+use igloo_interface::{derive::DaDerive, l1::Epoch};
+use igloo_verifier::rollup::RollupParams;
+use jsonrpc_core::{IoHandler, Params, Value};
+use jsonrpc_http_server::ServerBuilder;
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{DaDeriveConfig, DaDeriveImpl, HttpSource};
+use crate::{
+    l1::{attribute::PayloadAttributeImpl, head::L1HeadImpl},
+    l2::tx::L2Transaction,
+};
+
+fn attribute_on(hash: u8, height: u64, sequence_number: u64) -> PayloadAttributeImpl {
+    let mut attribute = PayloadAttributeImpl::try_from(L1HeadImpl {
+        hash: [hash; 32],
+        parent_hash: [hash.wrapping_sub(1); 32],
+        height,
+        timestamp: height * 12,
+    })
+    .unwrap();
+    attribute.sequence_number = sequence_number;
+    attribute.transactions = Arc::new(vec![L2Transaction {
+        from: Pubkey::new_unique(),
+        to: Pubkey::new_unique(),
+        amount: height * 100 + sequence_number,
+        calldata: vec![sequence_number as u8; 3],
+    }]);
+    attribute
+}
+
+fn attribute(height: u64, sequence_number: u64) -> PayloadAttributeImpl {
+    attribute_on(height as u8, height, sequence_number)
+}
+
+async fn submit_channel(
+    batcher: &mut Batcher<FileSink>,
+    attributes: &[PayloadAttributeImpl],
+) -> Result<()> {
+    for attribute in attributes {
+        batcher.add_block(attribute.encode()).await?;
+    }
+    batcher.flush().await?;
+    Ok(())
+}
+
+async fn drain(derive: &mut DaDeriveImpl) -> Vec<(u64, u64)> {
+    let mut derived = vec![];
+    while let Some(attribute) = derive.next().await {
+        derived.push((attribute.epoch.block_height(), attribute.sequence_number));
+    }
+    derived
+}
+
+fn new_derive(source: impl DaSource + 'static) -> DaDeriveImpl {
+    DaDeriveImpl::new(Box::new(source), DaDeriveConfig::default())
+}
+
+#[test]
+fn attribute_codec_works() -> Result<()> {
+    let attribute = attribute(7, 3);
+    let decoded = PayloadAttributeImpl::decode(&attribute.encode())?;
+    assert_eq!(decoded.epoch.block_hash(), attribute.epoch.block_hash());
+    assert_eq!(decoded.epoch.block_height(), 7);
+    assert_eq!(decoded.epoch.timestamp(), 7 * 12);
+    assert_eq!(decoded.sequence_number, 3);
+    assert_eq!(decoded.transactions[0].from, attribute.transactions[0].from);
+    assert_eq!(
+        decoded.transactions[0].amount,
+        attribute.transactions[0].amount
+    );
+    assert_eq!(
+        decoded.transactions[0].calldata,
+        attribute.transactions[0].calldata
+    );
+
+    let encoded = attribute.encode();
+    assert!(PayloadAttributeImpl::decode(&encoded[..encoded.len() - 1]).is_err());
+    assert!(PayloadAttributeImpl::decode(&[encoded.clone(), vec![0]].concat()).is_err());
+    Ok(())
+}
+
+#[test]
+fn da_derive_validates_batches() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let da_path = dir.path().to_path_buf();
+    let server = start_mock_da(da_path.clone());
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    runtime.block_on(async {
+        let config = BatcherConfig {
+            // small frames spread channels over several submissions
+            max_frame_size: 64,
+            ..Default::default()
+        };
+        let mut batcher = Batcher::new(config.clone(), FileSink::new(&da_path)?);
+        let mut file_derive = new_derive(FileSource::new(&da_path, 0));
+        let mut http_derive =
+            new_derive(HttpSource::new(format!("http://{}", server.address()), 0));
+        assert!(file_derive.next().await.is_none());
+
+        submit_channel(
+            &mut batcher,
+            &[attribute(1, 0), attribute(1, 1), attribute(2, 0)],
+        )
+        .await?;
+        // derived attributes are skipped
+        submit_channel(&mut batcher, &[attribute(1, 1), attribute(2, 1)]).await?;
+        // sequence number gap
+        submit_channel(&mut batcher, &[attribute(2, 2), attribute(2, 4)]).await?;
+        // another epoch at the same height
+        submit_channel(&mut batcher, &[attribute_on(9, 2, 2)]).await?;
+        // malformed submission, the batcher is reopened after it
+        FileSink::new(&da_path)?.submit(vec![0, 1, 2, 3]).await?;
+        let mut batcher = Batcher::new(config, FileSink::new(&da_path)?);
+        // epoch out of the window
+        submit_channel(&mut batcher, &[attribute(5, 0)]).await?;
+        submit_channel(&mut batcher, &[attribute(2, 2), attribute(3, 0)]).await?;
+
+        let expected = vec![(1, 0), (1, 1), (2, 0), (2, 1), (2, 2), (3, 0)];
+        assert_eq!(drain(&mut file_derive).await, expected);
+        assert_eq!(drain(&mut http_derive).await, expected);
+
+        // later submissions are picked up
+        submit_channel(&mut batcher, &[attribute(3, 1)]).await?;
+        assert_eq!(drain(&mut file_derive).await, vec![(3, 1)]);
+        anyhow::Ok(())
+    })?;
+
+    server.close();
+    Ok(())
+}
+
+#[tokio::test]
+async fn da_derive_rejects_drifting_batches() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut batcher = Batcher::new(BatcherConfig::default(), FileSink::new(dir.path())?);
+    let mut derive = DaDeriveImpl::new(
+        Box::new(FileSource::new(dir.path(), 0)),
+        DaDeriveConfig {
+            rollup: RollupParams {
+                block_time: 2,
+                max_sequencer_drift: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    // sequence number 2 is 4s ahead of its epoch
+    submit_channel(
+        &mut batcher,
+        &[attribute(1, 0), attribute(1, 1), attribute(1, 2)],
+    )
+    .await?;
+    submit_channel(&mut batcher, &[attribute(1, 0), attribute(1, 1)]).await?;
+    assert_eq!(drain(&mut derive).await, vec![(1, 0), (1, 1)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn da_derive_rejects_batches_outside_the_seq_window() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut batcher = Batcher::new(BatcherConfig::default(), FileSink::new(dir.path())?);
+    let mut derive = DaDeriveImpl::new(
+        Box::new(FileSource::new(dir.path(), 0)),
+        DaDeriveConfig {
+            rollup: RollupParams {
+                seq_window_size: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    derive.set_l1_height(3);
+    submit_channel(&mut batcher, &[attribute(1, 0)]).await?;
+    assert_eq!(drain(&mut derive).await, vec![(1, 0)]);
+
+    // epoch 1 is out of the window once included at L1 height 4
+    derive.set_l1_height(4);
+    submit_channel(&mut batcher, &[attribute(1, 1)]).await?;
+    submit_channel(&mut batcher, &[attribute(2, 0)]).await?;
+    assert_eq!(drain(&mut derive).await, vec![(2, 0)]);
+    Ok(())
+}
+
+/// DA stand-in serving the submissions written to `dir`.
+fn start_mock_da(dir: PathBuf) -> jsonrpc_http_server::Server {
+    let mut io = IoHandler::new();
+    io.add_sync_method("da_getSubmission", move |params: Params| {
+        let (index,): (u64,) = params.parse()?;
+        Ok(match fs::read(submission_path(&dir, index)) {
+            Ok(data) => json!(format!("0x{}", hex::encode(data))),
+            Err(_) => Value::Null,
+        })
+    });
+    ServerBuilder::new(io)
+        .start_http(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .unwrap()
+}
@@ -1,10 +1,13 @@
 use igloo_interface::derive::InstantDerive;
 use tokio::sync::mpsc::{error::TryRecvError, Receiver};
 
-use crate::l1::{attribute::PayloadAttributeImpl, block::L1BlockInfoImpl};
+use crate::l1::{attribute::PayloadAttributeImpl, block::L1BlockInfoImpl, head::L1HeadImpl};
 
+/// Derives the blocks sent over a channel. The sender never reorgs, so every received block is
+/// final.
 pub struct InstantDeriveImpl {
     receiver: Receiver<L1BlockInfoImpl>,
+    latest: Option<L1HeadImpl>,
 }
 
 impl InstantDerive for InstantDeriveImpl {
@@ -14,17 +17,27 @@ impl InstantDerive for InstantDeriveImpl {
 
     async fn get_new_block(&mut self) -> anyhow::Result<Option<Self::L1Info>> {
         match self.receiver.try_recv() {
-            Ok(info) => Ok(Some(info)),
+            Ok(info) => {
+                self.latest = Some(info.l1_head.clone());
+                Ok(Some(info))
+            }
             Err(err) => match err {
                 TryRecvError::Empty => Ok(None),
                 TryRecvError::Disconnected => Err(anyhow::anyhow!("Receiver disconnected")),
             },
         }
     }
+
+    async fn get_finalized(&mut self) -> anyhow::Result<Option<L1HeadImpl>> {
+        Ok(self.latest.clone())
+    }
 }
 
 impl InstantDeriveImpl {
     pub fn new(receiver: Receiver<L1BlockInfoImpl>) -> Self {
-        Self { receiver }
+        Self {
+            receiver,
+            latest: None,
+        }
     }
 }
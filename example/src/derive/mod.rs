@@ -1,2 +1,3 @@
 pub mod da;
 pub mod instant;
+pub mod rpc;
@@ -0,0 +1,334 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use igloo_interface::derive::InstantDerive;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+
+use crate::l1::{
+    attribute::PayloadAttributeImpl,
+    block::L1BlockInfoImpl,
+    head::L1HeadImpl,
+    portal::{decode_deposit, deposit_event_topic},
+    tx::DepositTx,
+    L1Hash, L1Height,
+};
+
+#[cfg(test)]
+mod tests;
+
+pub const DEFAULT_CONFIRMATIONS: u64 = 0;
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(6);
+pub const DEFAULT_REORG_DEPTH: usize = 64;
+pub const DEFAULT_MAX_BLOCKS_PER_POLL: u64 = 32;
+
+#[derive(Debug, Clone)]
+pub struct L1RpcConfig {
+    pub url: String,
+    /// Address of the portal contract emitting the deposit events.
+    pub portal_address: [u8; 20],
+    /// First L1 block to derive from.
+    pub start_height: L1Height,
+    /// Blocks the latest L1 head must be ahead of a block before it is derived.
+    pub confirmations: u64,
+    pub poll_interval: Duration,
+    /// L1 heads kept to follow reorgs, deeper reorgs fail the derivation.
+    pub reorg_depth: usize,
+    pub max_blocks_per_poll: u64,
+}
+
+impl L1RpcConfig {
+    pub fn new(url: String, portal_address: [u8; 20], start_height: L1Height) -> Self {
+        Self {
+            url,
+            portal_address,
+            start_height,
+            confirmations: DEFAULT_CONFIRMATIONS,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            reorg_depth: DEFAULT_REORG_DEPTH,
+            max_blocks_per_poll: DEFAULT_MAX_BLOCKS_PER_POLL,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcBlock {
+    number: String,
+    hash: String,
+    parent_hash: String,
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct RpcLog {
+    topics: Vec<String>,
+    data: String,
+    #[serde(default)]
+    removed: bool,
+}
+
+/// Minimal client of the Ethereum JSON-RPC methods the derivation needs.
+pub struct L1RpcClient {
+    client: reqwest::Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+impl L1RpcClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let mut response: Value = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            bail!("{} failed: {}", method, error);
+        }
+        Ok(serde_json::from_value(response["result"].take())?)
+    }
+
+    pub async fn block_number(&self) -> Result<L1Height> {
+        parse_quantity(&self.call::<String>("eth_blockNumber", json!([])).await?)
+    }
+
+    pub async fn head_by_number(&self, height: L1Height) -> Result<Option<L1HeadImpl>> {
+        let head = self.head_by_tag(format!("{height:#x}")).await?;
+        if let Some(head) = head.as_ref() {
+            ensure!(
+                head.height == height,
+                "requested L1 block {} but got {}",
+                height,
+                head.height
+            );
+        }
+        Ok(head)
+    }
+
+    /// Latest L1 block the consensus layer considers finalized.
+    pub async fn finalized_head(&self) -> Result<Option<L1HeadImpl>> {
+        self.head_by_tag("finalized".to_string()).await
+    }
+
+    async fn head_by_tag(&self, tag: String) -> Result<Option<L1HeadImpl>> {
+        let block: Option<RpcBlock> = self
+            .call("eth_getBlockByNumber", json!([tag, false]))
+            .await?;
+        let Some(block) = block else {
+            return Ok(None);
+        };
+        Ok(Some(L1HeadImpl {
+            hash: parse_hash(&block.hash)?,
+            parent_hash: parse_hash(&block.parent_hash)?,
+            height: parse_quantity(&block.number)?,
+            timestamp: parse_quantity(&block.timestamp)?,
+        }))
+    }
+
+    /// Logs of `address` with the given first topic, queried by block hash so a reorg can not
+    /// mix logs of another block in.
+    async fn logs(
+        &self,
+        block_hash: &L1Hash,
+        address: &[u8; 20],
+        topic0: &[u8; 32],
+    ) -> Result<Vec<RpcLog>> {
+        self.call(
+            "eth_getLogs",
+            json!([{
+                "blockHash": format!("0x{}", hex::encode(block_hash)),
+                "address": format!("0x{}", hex::encode(address)),
+                "topics": [format!("0x{}", hex::encode(topic0))],
+            }]),
+        )
+        .await
+    }
+}
+
+/// Derives L1 blocks from an Ethereum JSON-RPC endpoint, with the deposits emitted by the portal
+/// contract. Reorgs are detected by the parent hash of each new block; the blocks of the new
+/// canonical chain are derived again from the fork point.
+pub struct RpcInstantDerive {
+    client: L1RpcClient,
+    config: L1RpcConfig,
+    /// Recently derived heads, the last one is the parent of `next_height`.
+    heads: VecDeque<L1HeadImpl>,
+    /// Heads popped by the reorg being followed, reset once a block builds on the kept heads.
+    rewound: usize,
+    next_height: L1Height,
+    pending: VecDeque<L1BlockInfoImpl>,
+    finalized: Option<L1HeadImpl>,
+    last_poll: Option<Instant>,
+}
+
+impl InstantDerive for RpcInstantDerive {
+    type P = PayloadAttributeImpl;
+    type L1Info = L1BlockInfoImpl;
+    type Error = anyhow::Error;
+
+    async fn get_new_block(&mut self) -> Result<Option<Self::L1Info>> {
+        let poll_due = self
+            .last_poll
+            .is_none_or(|last| last.elapsed() >= self.config.poll_interval);
+        if self.pending.is_empty() && poll_due {
+            self.last_poll = Some(Instant::now());
+            self.poll().await?;
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    async fn get_finalized(&mut self) -> Result<Option<L1HeadImpl>> {
+        Ok(self.finalized.clone())
+    }
+
+    fn resume_after(&mut self, head: L1HeadImpl) {
+        self.next_height = head.height + 1;
+        self.pending.clear();
+        self.heads = VecDeque::from([head]);
+        self.rewound = 0;
+    }
+}
+
+impl RpcInstantDerive {
+    pub fn new(config: L1RpcConfig) -> Self {
+        Self {
+            client: L1RpcClient::new(config.url.clone()),
+            next_height: config.start_height,
+            config,
+            heads: VecDeque::new(),
+            rewound: 0,
+            pending: VecDeque::new(),
+            finalized: None,
+            last_poll: None,
+        }
+    }
+
+    async fn poll(&mut self) -> Result<()> {
+        if let Some(finalized) = self.client.finalized_head().await? {
+            self.finalized = Some(finalized);
+        }
+        let target = self
+            .client
+            .block_number()
+            .await?
+            .saturating_sub(self.config.confirmations);
+        let mut fetched = 0;
+        while self.next_height <= target && fetched < self.config.max_blocks_per_poll {
+            let Some(head) = self.client.head_by_number(self.next_height).await? else {
+                break;
+            };
+            fetched += 1;
+
+            if let Some(parent) = self.heads.back() {
+                if head.parent_hash != parent.hash {
+                    warn!(
+                        "L1 reorg detected at height {}, block {} replaced",
+                        head.height,
+                        hex::encode(parent.hash)
+                    );
+                    self.heads.pop_back();
+                    self.rewound += 1;
+                    self.next_height -= 1;
+                    self.pending
+                        .retain(|info| info.l1_head.height < self.next_height);
+                    if self.heads.is_empty() {
+                        if self.rewound >= self.config.reorg_depth {
+                            bail!(
+                                "L1 reorg deeper than {} blocks at height {}",
+                                self.config.reorg_depth,
+                                head.height
+                            );
+                        }
+                        // fewer heads were kept since the start or the resume, the walk back
+                        // goes on from the canonical parent
+                        if let Some(parent) = self.next_height.checked_sub(1) {
+                            self.heads.extend(self.client.head_by_number(parent).await?);
+                        }
+                    }
+                    continue;
+                }
+            }
+            self.rewound = 0;
+
+            let deposit_txs = self.deposits(&head).await?;
+            self.heads.push_back(head.clone());
+            if self.heads.len() > self.config.reorg_depth {
+                self.heads.pop_front();
+            }
+            self.pending.push_back(L1BlockInfoImpl {
+                deposit_txs,
+                batch: None,
+                l1_head: head,
+            });
+            self.next_height += 1;
+        }
+        Ok(())
+    }
+
+    async fn deposits(&self, head: &L1HeadImpl) -> Result<Vec<DepositTx>> {
+        let logs = self
+            .client
+            .logs(
+                &head.hash,
+                &self.config.portal_address,
+                &deposit_event_topic(),
+            )
+            .await?;
+        let mut deposits = vec![];
+        for log in logs.into_iter().filter(|log| !log.removed) {
+            let topics = log
+                .topics
+                .iter()
+                .map(|topic| parse_hash(topic))
+                .collect::<Result<Vec<_>>>()?;
+            match decode_deposit(&topics, &parse_bytes(&log.data)?) {
+                Ok(deposit) => deposits.push(deposit),
+                Err(e) => warn!("skip malformed deposit in L1 block {}: {}", head.height, e),
+            }
+        }
+        Ok(deposits)
+    }
+}
+
+fn parse_quantity(value: &str) -> Result<u64> {
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or(anyhow!("invalid quantity {value}"))?;
+    Ok(u64::from_str_radix(digits, 16)?)
+}
+
+fn parse_bytes(value: &str) -> Result<Vec<u8>> {
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or(anyhow!("invalid data {value}"))?;
+    Ok(hex::decode(digits)?)
+}
+
+fn parse_hash(value: &str) -> Result<[u8; 32]> {
+    parse_bytes(value)?
+        .try_into()
+        .map_err(|_| anyhow!("invalid hash {value}"))
+}
@@ -0,0 +1,219 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use igloo_interface::derive::InstantDerive;
+use jsonrpc_core::{IoHandler, Params, Value};
+use jsonrpc_http_server::{Server, ServerBuilder};
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{L1RpcConfig, RpcInstantDerive};
+use crate::l1::{
+    block::L1BlockInfoImpl, head::L1HeadImpl, portal::encode_deposit, tx::DepositTx, L1Hash,
+};
+
+const PORTAL: [u8; 20] = [7; 20];
+/// Blocks the mock L1 head is ahead of its finalized block.
+const FINALITY_DEPTH: u64 = 2;
+
+#[derive(Clone)]
+struct MockBlock {
+    hash: L1Hash,
+    parent_hash: L1Hash,
+    /// Topics and data of the portal logs.
+    logs: Vec<(Vec<[u8; 32]>, Vec<u8>)>,
+}
+
+/// Chain of the mock L1, indexed by height.
+type MockChain = Arc<Mutex<Vec<MockBlock>>>;
+
+fn push_block(chain: &MockChain, fork: u8, logs: Vec<(Vec<[u8; 32]>, Vec<u8>)>) {
+    let mut chain = chain.lock().unwrap();
+    let parent_hash = chain.last().map(|b| b.hash).unwrap_or_default();
+    let mut hash = [fork; 32];
+    hash[..8].copy_from_slice(&(chain.len() as u64).to_le_bytes());
+    chain.push(MockBlock {
+        hash,
+        parent_hash,
+        logs,
+    });
+}
+
+fn hex_str(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn start_mock_l1(chain: MockChain) -> Server {
+    let mut io = IoHandler::new();
+    let chain_ = chain.clone();
+    io.add_sync_method("eth_blockNumber", move |_| {
+        Ok(json!(format!("{:#x}", chain_.lock().unwrap().len() - 1)))
+    });
+    let chain_ = chain.clone();
+    io.add_sync_method("eth_getBlockByNumber", move |params: Params| {
+        let (number, _full): (String, bool) = params.parse()?;
+        let chain = chain_.lock().unwrap();
+        let height = match number.as_str() {
+            "finalized" => (chain.len() as u64 - 1).saturating_sub(FINALITY_DEPTH),
+            _ => u64::from_str_radix(number.trim_start_matches("0x"), 16).unwrap(),
+        };
+        Ok(chain.get(height as usize).map_or(Value::Null, |block| {
+            json!({
+                "number": format!("{height:#x}"),
+                "hash": hex_str(&block.hash),
+                "parentHash": hex_str(&block.parent_hash),
+                "timestamp": format!("{:#x}", height * 12),
+            })
+        }))
+    });
+    io.add_sync_method("eth_getLogs", move |params: Params| {
+        let (filter,): (Value,) = params.parse()?;
+        assert_eq!(filter["address"], hex_str(&PORTAL));
+        let chain = chain.lock().unwrap();
+        let logs = chain
+            .iter()
+            .filter(|block| filter["blockHash"] == hex_str(&block.hash))
+            .flat_map(|block| &block.logs)
+            .filter(|(topics, _)| filter["topics"][0] == hex_str(&topics[0]))
+            .map(|(topics, data)| {
+                json!({
+                    "address": hex_str(&PORTAL),
+                    "topics": topics.iter().map(|t| hex_str(t)).collect::<Vec<_>>(),
+                    "data": hex_str(data),
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(json!(logs))
+    });
+
+    ServerBuilder::new(io)
+        .start_http(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .unwrap()
+}
+
+async fn drain(derive: &mut RpcInstantDerive) -> Result<Vec<L1BlockInfoImpl>> {
+    let mut blocks = vec![];
+    while let Some(block) = derive.get_new_block().await? {
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+fn heights(blocks: &[L1BlockInfoImpl]) -> Vec<u64> {
+    blocks.iter().map(|b| b.l1_head.height).collect()
+}
+
+#[test]
+fn rpc_instant_derive_works() -> Result<()> {
+    let chain = MockChain::default();
+    let deposit = DepositTx {
+        from: Pubkey::default(),
+        to: Pubkey::new_unique(),
+        amount: 1_000,
+        calldata: vec![1, 2, 3],
+    };
+    let (topics, data) = encode_deposit(&[9; 20], &deposit);
+    // a malformed deposit is skipped
+    let malformed = (topics.clone(), data[..40].to_vec());
+    for height in 0..=3 {
+        let logs = match height {
+            2 => vec![(topics.clone(), data.clone()), malformed.clone()],
+            _ => vec![],
+        };
+        push_block(&chain, 0, logs);
+    }
+    let server = start_mock_l1(chain.clone());
+
+    let mut config = L1RpcConfig::new(format!("http://{}", server.address()), PORTAL, 1);
+    config.poll_interval = Duration::ZERO;
+    config.reorg_depth = 3;
+    let mut derive = RpcInstantDerive::new(config);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let blocks = drain(&mut derive).await?;
+        assert_eq!(heights(&blocks), vec![1, 2, 3]);
+        assert_eq!(blocks[1].l1_head.parent_hash, blocks[0].l1_head.hash);
+        assert_eq!(blocks[1].deposit_txs.len(), 1);
+        let derived = &blocks[1].deposit_txs[0];
+        let mut from = [0; 32];
+        from[12..].copy_from_slice(&[9; 20]);
+        assert_eq!(derived.from, Pubkey::new_from_array(from));
+        assert_eq!(derived.to, deposit.to);
+        assert_eq!(derived.amount, deposit.amount);
+        assert_eq!(derived.calldata, deposit.calldata);
+        let finalized = derive.get_finalized().await?.unwrap();
+        assert_eq!(finalized.height, 1);
+        assert_eq!(finalized.hash, blocks[0].l1_head.hash);
+
+        // block 3 is replaced, the new chain is derived from the fork point
+        chain.lock().unwrap().pop();
+        push_block(&chain, 1, vec![]);
+        push_block(&chain, 1, vec![]);
+        let blocks = drain(&mut derive).await?;
+        assert_eq!(heights(&blocks), vec![3, 4]);
+        assert_eq!(blocks[0].l1_head.hash, chain.lock().unwrap()[3].hash);
+        assert_eq!(derive.get_finalized().await?.unwrap().height, 2);
+
+        // reorgs deeper than the kept heads fail
+        chain.lock().unwrap().truncate(1);
+        for _ in 0..5 {
+            push_block(&chain, 2, vec![]);
+        }
+        assert!(derive.get_new_block().await.is_err());
+        anyhow::Ok(())
+    })?;
+
+    server.close();
+    Ok(())
+}
+
+#[test]
+fn rpc_instant_derive_resumes() -> Result<()> {
+    let chain = MockChain::default();
+    for _ in 0..=4 {
+        push_block(&chain, 0, vec![]);
+    }
+    let server = start_mock_l1(chain.clone());
+
+    let mut config = L1RpcConfig::new(format!("http://{}", server.address()), PORTAL, 1);
+    config.poll_interval = Duration::ZERO;
+    let mut derive = RpcInstantDerive::new(config);
+    let block = chain.lock().unwrap()[2].clone();
+    derive.resume_after(L1HeadImpl {
+        hash: block.hash,
+        parent_hash: block.parent_hash,
+        height: 2,
+        timestamp: 24,
+    });
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let blocks = drain(&mut derive).await?;
+        assert_eq!(heights(&blocks), vec![3, 4]);
+        assert_eq!(blocks[0].l1_head.parent_hash, block.hash);
+
+        // the head resumed after is replaced, the walk back goes on below it
+        derive.resume_after(L1HeadImpl {
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            height: 2,
+            timestamp: 24,
+        });
+        chain.lock().unwrap().truncate(2);
+        for _ in 2..=4 {
+            push_block(&chain, 1, vec![]);
+        }
+        let blocks = drain(&mut derive).await?;
+        assert_eq!(heights(&blocks), vec![2, 3, 4]);
+        assert_eq!(blocks[0].l1_head.hash, chain.lock().unwrap()[2].hash);
+        anyhow::Ok(())
+    })?;
+
+    server.close();
+    Ok(())
+}
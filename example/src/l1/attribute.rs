@@ -1,10 +1,13 @@
+use anyhow::{anyhow, ensure};
+use serde_derive::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
 use std::sync::Arc;
 
 use super::{head::L1HeadImpl, L1Hash, L1Height, L1Timestamp};
 use crate::l2::tx::L2Transaction;
 use igloo_interface::l1::{Epoch, PayloadAttribute};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct EpochInfo {
     hash: L1Hash,
     height: L1Height,
@@ -45,13 +48,13 @@ impl TryFrom<L1HeadImpl> for EpochInfo {
 pub struct PayloadAttributeImpl {
     pub transactions: Arc<Vec<L2Transaction>>,
     pub epoch: EpochInfo,
-    pub sequence_number: u8,
+    pub sequence_number: u64,
 }
 
 impl PayloadAttribute for PayloadAttributeImpl {
     type Transaction = L2Transaction;
     type Epoch = EpochInfo;
-    type SequenceNumber = u8;
+    type SequenceNumber = u64;
 
     fn transactions(&self) -> std::sync::Arc<Vec<Self::Transaction>> {
         self.transactions.clone()
@@ -66,6 +69,72 @@ impl PayloadAttribute for PayloadAttributeImpl {
     }
 }
 
+impl PayloadAttributeImpl {
+    /// Encodes the attribute as a block of a DA batch:
+    ///
+    /// `epoch hash (32) ++ epoch height (u64 BE) ++ epoch timestamp (u64 BE) ++ sequence number
+    /// (u64 BE) ++ tx count (u32 BE) ++ (from (32) ++ to (32) ++ amount (u64 BE) ++ calldata length (u32 BE)
+    /// ++ calldata)*`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.epoch.hash);
+        buf.extend_from_slice(&self.epoch.height.to_be_bytes());
+        buf.extend_from_slice(&self.epoch.timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.sequence_number.to_be_bytes());
+        buf.extend_from_slice(&(self.transactions.len() as u32).to_be_bytes());
+        for tx in self.transactions.iter() {
+            buf.extend_from_slice(tx.from.as_ref());
+            buf.extend_from_slice(tx.to.as_ref());
+            buf.extend_from_slice(&tx.amount.to_be_bytes());
+            buf.extend_from_slice(&(tx.calldata.len() as u32).to_be_bytes());
+            buf.extend_from_slice(&tx.calldata);
+        }
+        buf
+    }
+
+    /// Decodes an attribute encoded by [`PayloadAttributeImpl::encode`].
+    pub fn decode(mut data: &[u8]) -> anyhow::Result<Self> {
+        let cursor = &mut data;
+        let epoch = EpochInfo {
+            hash: take(cursor)?,
+            height: u64::from_be_bytes(take(cursor)?),
+            timestamp: u64::from_be_bytes(take(cursor)?),
+        };
+        let sequence_number = u64::from_be_bytes(take(cursor)?);
+        let count = u32::from_be_bytes(take(cursor)?);
+        let mut transactions = vec![];
+        for _ in 0..count {
+            let from = Pubkey::new_from_array(take(cursor)?);
+            let to = Pubkey::new_from_array(take(cursor)?);
+            let amount = u64::from_be_bytes(take(cursor)?);
+            let len = u32::from_be_bytes(take(cursor)?) as usize;
+            ensure!(cursor.len() >= len, "calldata truncated");
+            let (calldata, rest) = cursor.split_at(len);
+            *cursor = rest;
+            transactions.push(L2Transaction {
+                from,
+                to,
+                amount,
+                calldata: calldata.to_vec(),
+            });
+        }
+        ensure!(cursor.is_empty(), "trailing bytes after attribute");
+        Ok(Self {
+            transactions: Arc::new(transactions),
+            epoch,
+            sequence_number,
+        })
+    }
+}
+
+fn take<const N: usize>(cursor: &mut &[u8]) -> anyhow::Result<[u8; N]> {
+    let (head, tail) = cursor
+        .split_first_chunk::<N>()
+        .ok_or(anyhow!("attribute truncated"))?;
+    *cursor = tail;
+    Ok(*head)
+}
+
 impl TryFrom<L1HeadImpl> for PayloadAttributeImpl {
     type Error = anyhow::Error;
 
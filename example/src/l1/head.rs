@@ -1,10 +1,12 @@
 use igloo_interface::l1::L1Head;
+use serde_derive::{Deserialize, Serialize};
 
 use super::{L1Hash, L1Height, L1Timestamp};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct L1HeadImpl {
     pub hash: L1Hash,
+    pub parent_hash: L1Hash,
     pub height: L1Height,
     pub timestamp: L1Timestamp,
 }
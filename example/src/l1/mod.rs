@@ -2,6 +2,7 @@ pub mod attribute;
 pub mod batch;
 pub mod block;
 pub mod head;
+pub mod portal;
 pub mod tx;
 
 pub type L1Hash = [u8; 32];
@@ -0,0 +1,93 @@
+//! Deposit events of the L1 portal contract:
+//!
+//! ```solidity
+//! event TransactionDeposited(address indexed from, bytes32 indexed to, uint256 indexed version, bytes opaqueData);
+//! ```
+//!
+//! Version 0 of `opaqueData` is `abi.encodePacked(uint256 amount, bytes calldata)`.
+
+use anyhow::{anyhow, ensure, Result};
+use solana_sdk::{keccak::hashv, pubkey::Pubkey};
+
+use super::tx::DepositTx;
+
+pub const DEPOSIT_EVENT_SIGNATURE: &str = "TransactionDeposited(address,bytes32,uint256,bytes)";
+pub const DEPOSIT_VERSION: [u8; 32] = [0; 32];
+
+/// `topic0` of the deposit event.
+pub fn deposit_event_topic() -> [u8; 32] {
+    hashv(&[DEPOSIT_EVENT_SIGNATURE.as_bytes()]).to_bytes()
+}
+
+/// Decodes a deposit event from its indexed topics and ABI encoded data. The L1 sender address
+/// is left padded with zeros into an L2 address.
+pub fn decode_deposit(topics: &[[u8; 32]], data: &[u8]) -> Result<DepositTx> {
+    let [topic0, from, to, version] = topics else {
+        return Err(anyhow!("expected 4 deposit topics, got {}", topics.len()));
+    };
+    ensure!(*topic0 == deposit_event_topic(), "not a deposit event");
+    ensure!(
+        *version == DEPOSIT_VERSION,
+        "unsupported deposit version 0x{}",
+        hex::encode(version)
+    );
+
+    let opaque_data = decode_abi_bytes(data)?;
+    ensure!(opaque_data.len() >= 32, "deposit data too short");
+    let (amount, calldata) = opaque_data.split_at(32);
+    ensure!(
+        amount[..24].iter().all(|b| *b == 0),
+        "deposit amount exceeds u64"
+    );
+
+    Ok(DepositTx {
+        from: Pubkey::new_from_array(*from),
+        to: Pubkey::new_from_array(*to),
+        amount: u64::from_be_bytes(amount[24..].try_into()?),
+        calldata: calldata.to_vec(),
+    })
+}
+
+/// Decodes event data holding a single dynamic `bytes` value.
+fn decode_abi_bytes(data: &[u8]) -> Result<&[u8]> {
+    let word = |offset: usize| -> Result<usize> {
+        let word = data
+            .get(offset..)
+            .and_then(|data| data.get(..32))
+            .ok_or(anyhow!("abi data too short"))?;
+        ensure!(word[..24].iter().all(|b| *b == 0), "abi word out of range");
+        Ok(u64::from_be_bytes(word[24..].try_into()?) as usize)
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    data.get(offset + 32..)
+        .and_then(|data| data.get(..len))
+        .ok_or(anyhow!("abi bytes out of bounds"))
+}
+
+#[cfg(test)]
+pub(crate) fn encode_deposit(from: &[u8; 20], deposit: &DepositTx) -> (Vec<[u8; 32]>, Vec<u8>) {
+    let mut from_topic = [0; 32];
+    from_topic[12..].copy_from_slice(from);
+
+    let mut opaque_data = [0; 24].to_vec();
+    opaque_data.extend_from_slice(&deposit.amount.to_be_bytes());
+    opaque_data.extend_from_slice(&deposit.calldata);
+
+    let mut data = [0; 32].to_vec();
+    data[31] = 32;
+    data.extend_from_slice(&[0; 24]);
+    data.extend_from_slice(&(opaque_data.len() as u64).to_be_bytes());
+    data.extend_from_slice(&opaque_data);
+    data.resize(data.len().div_ceil(32) * 32, 0);
+
+    (
+        vec![
+            deposit_event_topic(),
+            from_topic,
+            deposit.to.to_bytes(),
+            DEPOSIT_VERSION,
+        ],
+        data,
+    )
+}
@@ -1,65 +1,39 @@
 use crate::l1::attribute::PayloadAttributeImpl;
-use std::sync::Arc;
-use tokio::sync::{
-    mpsc::{Receiver, Sender},
-    RwLock,
-};
+use igloo_batcher::{BatcherConfig, FileSink};
+use std::{path::Path, time::Duration};
+use tokio::sync::mpsc::Receiver;
 
-type ThreadSafeBlocks = Arc<RwLock<Vec<PayloadAttributeImpl>>>;
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Submits the produced attributes to a file based DA store.
 pub struct Batcher {
-    cache: ThreadSafeBlocks,
-    next_batch: ThreadSafeBlocks,
-    da_sender: Sender<Vec<PayloadAttributeImpl>>,
+    inner: igloo_batcher::Batcher<FileSink>,
 }
 
 impl Batcher {
-    pub fn new(da_sender: Sender<Vec<PayloadAttributeImpl>>) -> Self {
-        Self {
-            cache: Arc::new(RwLock::new(Vec::new())),
-            next_batch: Arc::new(RwLock::new(Vec::new())),
-            da_sender,
-        }
-    }
-
-    pub fn run(&self, receiver: Receiver<PayloadAttributeImpl>) {
-        tokio::spawn(Self::receive_tx_loop(receiver, self.cache.clone()));
-        tokio::spawn(Self::send_batch_loop(
-            self.da_sender.clone(),
-            self.cache.clone(),
-            self.next_batch.clone(),
-        ));
+    pub fn new(config: BatcherConfig, da_path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            inner: igloo_batcher::Batcher::new(config, FileSink::new(da_path)?),
+        })
     }
 
-    async fn receive_tx_loop(
-        mut receiver: Receiver<PayloadAttributeImpl>,
-        cache: ThreadSafeBlocks,
-    ) {
-        loop {
-            let block = receiver.recv().await;
-            if let Some(block) = block {
-                cache.write().await.push(block);
-            }
-        }
+    pub fn run(self, receiver: Receiver<PayloadAttributeImpl>) {
+        tokio::spawn(self.batch_loop(receiver));
     }
 
-    async fn send_batch_loop(
-        da_sender: Sender<Vec<PayloadAttributeImpl>>,
-        cache: ThreadSafeBlocks,
-        next_batch: ThreadSafeBlocks,
-    ) {
+    async fn batch_loop(mut self, mut receiver: Receiver<PayloadAttributeImpl>) {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
         loop {
-            tokio::time::sleep(std::time::Duration::from_secs(20)).await;
-
-            let has_batch = { next_batch.read().await.len() > 0 };
-            if has_batch {
-                if let Err(e) = da_sender.send(next_batch.read().await.clone()).await {
-                    error!("Failed to send batch: {}", e);
-                }
+            let result = tokio::select! {
+                attribute = receiver.recv() => match attribute {
+                    Some(attribute) => self.inner.add_block(attribute.encode()).await,
+                    None => break,
+                },
+                _ = interval.tick() => self.inner.tick().await,
+            };
+            if let Err(e) = result {
+                error!("Failed to submit batch: {}", e);
             }
-
-            let batch = { cache.write().await.drain(..).collect::<Vec<_>>() };
-            *next_batch.write().await = batch;
         }
     }
 }
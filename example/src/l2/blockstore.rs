@@ -1,6 +1,6 @@
 use rand::Rng;
 use solana_ledger::{
-    blockstore::Blockstore,
+    blockstore::{Blockstore, PurgeType},
     shred::{ProcessShredsStats, ReedSolomonCache, Shredder},
 };
 use solana_sdk::{clock::Slot, hash::Hash, signature::Keypair};
@@ -22,6 +22,41 @@ impl SimpleStore {
         })
     }
 
+    /// Remove all shreds of slots from `from_slot` up to the highest slot.
+    pub(crate) fn purge_from(&self, from_slot: Slot) -> anyhow::Result<()> {
+        if let Some(highest) = self.inner.highest_slot()? {
+            if highest >= from_slot {
+                self.inner.purge_slots(from_slot, highest, PurgeType::Exact);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_root(&self, slot: Slot) -> anyhow::Result<()> {
+        if !self.inner.is_root(slot) {
+            self.inner.set_roots(std::iter::once(&slot))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn highest_slot(&self) -> anyhow::Result<Option<Slot>> {
+        Ok(self.inner.highest_slot()?)
+    }
+
+    pub(crate) fn max_root(&self) -> Slot {
+        self.inner.max_root()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn has_slot(&self, slot: Slot) -> bool {
+        self.inner.meta(slot).ok().flatten().is_some()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_root(&self, slot: Slot) -> bool {
+        self.inner.is_root(slot)
+    }
+
     pub(crate) fn write_entries(
         &self,
         start_slot: Slot,
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use solana_sdk::signature::Keypair;
 use std::{path::Path, sync::Arc};
 use tokio::sync::{mpsc::Sender, RwLock};
@@ -44,10 +44,12 @@ impl SvmEngine {
         })
     }
 
+    /// Produces an unsafe block from `attribute` and the pending transactions of the stream,
+    /// returns it along with the attribute it was produced from, which is posted to the batcher.
     pub async fn produce_block(
         &mut self,
         attribute: PayloadAttributeImpl,
-    ) -> anyhow::Result<BlockPayloadImpl> {
+    ) -> anyhow::Result<(PayloadAttributeImpl, BlockPayloadImpl)> {
         let mut transactions = (*attribute.transactions).clone();
         let extra_txs = {
             self.stream
@@ -69,12 +71,73 @@ impl SvmEngine {
             sequence_number: attribute.sequence_number,
         };
         let block = self.producer.produce(new_attribute.clone()).await?;
+        self.post_attribute(new_attribute.clone()).await;
+
+        Ok((new_attribute, block))
+    }
 
-        if let Err(e) = self.attribute_sender.send(new_attribute).await {
+    /// Sends `attribute` to the batcher to be posted to DA.
+    pub async fn post_attribute(&self, attribute: PayloadAttributeImpl) {
+        if let Err(e) = self.attribute_sender.send(attribute).await {
             error!("Failed to send attribute: {}", e);
         }
+    }
 
-        Ok(block)
+    /// Produces a block from the transactions of `attribute` only, e.g. one derived from DA.
+    pub async fn derive_block(
+        &mut self,
+        attribute: PayloadAttributeImpl,
+    ) -> anyhow::Result<BlockPayloadImpl> {
+        trace!("derive block with {} txs", attribute.transactions.len());
+        self.producer.produce(attribute).await
+    }
+
+    /// Restores the ledger after a restart from the heads of the blocks from the finalized one
+    /// up, in height order. Blockstore slots above the latest head were written after the heads
+    /// were saved, they are dropped.
+    pub async fn resume(&mut self, heads: Vec<L2HeadImpl>, finalized: L2Height) -> Result<()> {
+        let latest = heads.last().map_or(0, |head| head.block_height());
+        let blockstore = self.blockstore.write().await;
+        let root = blockstore.max_root();
+        ensure!(
+            root <= latest,
+            "blockstore root {} is above the latest block {}",
+            root,
+            latest
+        );
+        if root < finalized {
+            blockstore.set_root(finalized)?;
+        }
+        if let Some(highest) = blockstore.highest_slot()? {
+            if highest > latest {
+                warn!(
+                    "drop blockstore slots {} to {} above the latest block",
+                    latest + 1,
+                    highest
+                );
+                blockstore.purge_from(latest + 1)?;
+            }
+        }
+
+        let mut ledger = self.ledger.write().await;
+        for head in heads {
+            ledger.new_block(BlockImpl {
+                head,
+                entries: vec![],
+            });
+        }
+        ledger.finalized = finalized;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn ledger(&self) -> &SharedLedger {
+        &self.ledger
+    }
+
+    #[cfg(test)]
+    pub(crate) fn blockstore(&self) -> &SharedStore {
+        &self.blockstore
     }
 }
 
@@ -125,11 +188,28 @@ impl EngineApi<BlockImpl, L2HeadImpl> for SvmEngine {
         Ok(head)
     }
 
-    async fn reorg(&mut self, _reset_to: L2HeadImpl) -> Result<()> {
-        todo!()
+    async fn reorg(&mut self, reset_to: L2HeadImpl) -> Result<()> {
+        let orphaned = self.ledger.write().await.reorg(&reset_to)?;
+        if let Some(first) = orphaned.first() {
+            self.blockstore.write().await.purge_from(*first)?;
+        }
+        info!(
+            "reorg to height: {}, {} blocks dropped",
+            reset_to.block_height(),
+            orphaned.len()
+        );
+        Ok(())
     }
 
-    async fn finalize(&mut self, _block: L2HeadImpl) -> Result<()> {
-        todo!()
+    async fn finalize(&mut self, block: L2HeadImpl) -> Result<()> {
+        self.ledger.write().await.finalize(&block)?;
+        if block.block_height() > 0 {
+            self.blockstore
+                .write()
+                .await
+                .set_root(block.block_height())?;
+        }
+        debug!("finalize block at height: {}", block.block_height());
+        Ok(())
     }
 }
@@ -1,8 +1,12 @@
 use igloo_interface::l2::L2Head;
+use serde_derive::{Deserialize, Serialize};
+use solana_sdk::hash::{hash, Hash};
+
+use crate::l1::attribute::{EpochInfo, PayloadAttributeImpl};
 
 use super::{L2Hash, L2Height, L2Timestamp};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct L2HeadImpl {
     pub hash: L2Hash,
     pub height: L2Height,
@@ -26,3 +30,30 @@ impl L2Head for L2HeadImpl {
         self.timestamp
     }
 }
+
+/// L2 head tied to the attribute it was produced from.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct L2BlockRef {
+    pub head: L2HeadImpl,
+    /// L1 origin of the block.
+    pub epoch: EpochInfo,
+    pub sequence_number: u64,
+    /// Hash of the encoded attribute, including every transaction of the block.
+    pub attribute_hash: Hash,
+}
+
+impl L2BlockRef {
+    pub fn new(head: L2HeadImpl, attribute: &PayloadAttributeImpl) -> Self {
+        Self {
+            head,
+            epoch: attribute.epoch.clone(),
+            sequence_number: attribute.sequence_number,
+            attribute_hash: hash(&attribute.encode()),
+        }
+    }
+
+    /// Whether the block was produced from `attribute`.
+    pub fn matches(&self, attribute: &PayloadAttributeImpl) -> bool {
+        self.attribute_hash == hash(&attribute.encode())
+    }
+}
@@ -14,6 +14,7 @@ pub type SharedLedger = Arc<RwLock<MockLedger>>;
 pub struct MockLedger {
     pub blocks: BTreeMap<L2Height, BlockImpl>,
     pub hash_map: HashMap<L2Hash, L2Height>,
+    pub finalized: L2Height,
 }
 
 impl MockLedger {
@@ -28,4 +29,61 @@ impl MockLedger {
     pub fn latest_height(&self) -> L2Height {
         self.blocks.last_key_value().map(|(k, _)| *k).unwrap_or(0)
     }
+
+    /// Drop all blocks above `reset_to`, returns the heights of the dropped blocks.
+    pub fn reorg(&mut self, reset_to: &L2HeadImpl) -> anyhow::Result<Vec<L2Height>> {
+        let height = reset_to.block_height();
+        if height < self.finalized {
+            return Err(anyhow::anyhow!(
+                "can not reorg to {}, block {} is finalized",
+                height,
+                self.finalized
+            ));
+        }
+        self.check_head(reset_to)?;
+
+        let orphaned = self.blocks.split_off(&(height + 1));
+        self.hash_map.retain(|_, h| *h <= height);
+        if height > 0 {
+            // the orphaned blocks may have shadowed the hash of the new head
+            self.hash_map.entry(reset_to.block_hash()).or_insert(height);
+        }
+        Ok(orphaned.into_keys().collect())
+    }
+
+    pub fn finalize(&mut self, head: &L2HeadImpl) -> anyhow::Result<()> {
+        let height = head.block_height();
+        if height < self.finalized {
+            return Err(anyhow::anyhow!(
+                "block {} is below the finalized height {}",
+                height,
+                self.finalized
+            ));
+        }
+        self.check_head(head)?;
+
+        self.finalized = height;
+        Ok(())
+    }
+
+    fn check_head(&self, head: &L2HeadImpl) -> anyhow::Result<()> {
+        // height 0 is the genesis, it is never stored in the ledger
+        if head.block_height() == 0 {
+            return Ok(());
+        }
+
+        let block = self
+            .blocks
+            .get(&head.block_height())
+            .ok_or(anyhow::anyhow!("block {} not found", head.block_height()))?;
+        if block.head().block_hash() != head.block_hash() {
+            return Err(anyhow::anyhow!(
+                "block {} hash mismatch, expected {}, got {}",
+                head.block_height(),
+                block.head().block_hash(),
+                head.block_hash()
+            ));
+        }
+        Ok(())
+    }
 }
@@ -133,6 +133,11 @@ impl SvmProducer {
     }
 
     fn get_program_path(&self) -> String {
-        "svm/executor/tests/simple_transfer_program.so".to_string()
+        // resolve from the manifest dir so it does not depend on the working directory
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../svm/executor/tests/simple_transfer_program.so"
+        )
+        .to_string()
     }
 }
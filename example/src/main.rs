@@ -1,7 +1,18 @@
-use anyhow::Result;
-use derive::{da::DaDeriveImpl, instant::InstantDeriveImpl};
-use igloo_interface::{l2::Engine, runner::Runner};
-use l2::batcher::Batcher;
+use anyhow::{anyhow, Result};
+use derive::{
+    da::{DaDeriveConfig, DaDeriveImpl},
+    instant::InstantDeriveImpl,
+    rpc::{L1RpcConfig, RpcInstantDerive},
+};
+use igloo_batcher::{BatcherConfig, FileSource};
+use igloo_interface::{
+    derive::InstantDerive,
+    l2::{Engine, L2Head},
+    runner::Runner,
+};
+use igloo_verifier::rollup::RollupParams;
+use l1::{attribute::PayloadAttributeImpl, block::L1BlockInfoImpl};
+use l2::{batcher::Batcher, head::L2BlockRef};
 use mock::{chain::MockLayer1, stream::TxServer};
 use runner::SimpleRunner;
 use std::path::Path;
@@ -20,29 +31,69 @@ extern crate log;
 async fn main() -> Result<()> {
     env_logger::init();
 
-    let (instant_sender, instant_receiver) = channel(1024);
-    let instanct_driver = InstantDeriveImpl::new(instant_receiver);
-
-    let (da_sender, da_receiver) = channel(1);
-    let da_driver = DaDeriveImpl::default();
+    match l1_rpc_config()? {
+        Some(config) => run(RpcInstantDerive::new(config)).await,
+        None => {
+            let (instant_sender, instant_receiver) = channel(1024);
+            MockLayer1::new(1000, instant_sender).run();
+            run(InstantDeriveImpl::new(instant_receiver)).await
+        }
+    }
+}
 
+async fn run<I>(instant_driver: I) -> Result<()>
+where
+    I: InstantDerive<P = PayloadAttributeImpl, L1Info = L1BlockInfoImpl, Error = anyhow::Error>,
+{
     let (attribute_sender, attribute_receiver) = channel(1024);
-    let mut runner = SimpleRunner::new(Path::new("/tmp/igloo-example"), attribute_sender)?;
+    let base_path = Path::new("/tmp/igloo-example");
+    let da_path = base_path.join("da");
+    let params = RollupParams::default();
+    let mut runner = SimpleRunner::new(base_path, params.clone(), attribute_sender)?;
+    let da_driver = DaDeriveImpl::new(
+        Box::new(FileSource::new(&da_path, 0)),
+        DaDeriveConfig {
+            rollup: params,
+            ..Default::default()
+        },
+    );
 
-    runner.register_instant(instanct_driver);
-    runner.register_da(da_driver.clone());
+    runner.register_instant(instant_driver);
+    runner.register_da(da_driver);
+    runner.resume().await?;
 
-    MockLayer1::new(1000, instant_sender).run();
     TxServer::new(runner.get_engine().stream().clone()).run();
-    Batcher::new(da_sender).run(attribute_receiver);
-    da_driver.run(da_receiver);
+    Batcher::new(BatcherConfig::default(), &da_path)?.run(attribute_receiver);
 
     loop {
         if let Err(e) = runner.advance().await {
             // We should match the error type and panic accordingly in production code
             error!("Error: {}", e);
         }
+        let height = |head: Option<&L2BlockRef>| head.map_or(0, |b| b.head.block_height());
+        debug!(
+            "unsafe head: {}, safe head: {}, finalized head: {}",
+            height(runner.unsafe_head()),
+            height(runner.safe_head()),
+            height(runner.finalized_head())
+        );
 
         tokio::time::sleep(std::time::Duration::from_secs(2)).await;
     }
 }
+
+/// Derives from an L1 JSON-RPC endpoint if `L1_RPC_URL` is set, from the mock L1 otherwise.
+fn l1_rpc_config() -> Result<Option<L1RpcConfig>> {
+    let Ok(url) = std::env::var("L1_RPC_URL") else {
+        return Ok(None);
+    };
+    let portal_address = std::env::var("L1_PORTAL_ADDRESS")?;
+    let portal_address = hex::decode(portal_address.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow!("L1_PORTAL_ADDRESS must be 20 bytes"))?;
+    let start_height = match std::env::var("L1_START_HEIGHT") {
+        Ok(height) => height.parse()?,
+        Err(_) => 0,
+    };
+    Ok(Some(L1RpcConfig::new(url, portal_address, start_height)))
+}
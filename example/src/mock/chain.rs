@@ -46,6 +46,7 @@ impl MockLayer1 {
         L1HeadImpl {
             height,
             hash: Default::default(),
+            parent_hash: Default::default(),
             timestamp: Utc::now().timestamp() as u64,
         }
     }
@@ -1,32 +1,70 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use igloo_interface::{
     derive::{DaDerive, InstantDerive},
     l1::{Epoch, L1BlockInfo, L1Head},
     l2::{EngineApi, L2Head},
     runner::Runner,
 };
+use igloo_verifier::rollup::RollupParams;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
     derive::{da::DaDeriveImpl, instant::InstantDeriveImpl},
-    l1::{attribute::PayloadAttributeImpl, head::L1HeadImpl},
-    l2::{block::BlockPayloadImpl, engine::SvmEngine, head::L2HeadImpl},
+    l1::{
+        attribute::PayloadAttributeImpl, block::L1BlockInfoImpl, head::L1HeadImpl, L1Hash, L1Height,
+    },
+    l2::{block::BlockPayloadImpl, engine::SvmEngine, head::L2BlockRef, L2Height},
+    runner::checkpoint::{Checkpoint, CheckpointStore},
 };
 
-pub struct SimpleRunner {
+pub mod checkpoint;
+#[cfg(test)]
+mod tests;
+
+/// Runner over the instant derivation `I`, the mock L1 by default.
+///
+/// Blocks are produced from the instant derivation as unsafe blocks, become safe once the DA
+/// derivation confirms them, and are finalized once their L1 origin is finalized. A DA-derived
+/// block diverging from the unsafe one at its height reorgs the unsafe blocks away.
+///
+/// An L1 block replacing one derived before, e.g. after an L1 reorg detected by the instant
+/// derivation, drops every block whose L1 origin was replaced, safe ones included. Blocks are
+/// only finalized once their L1 origin is on the chain of the finalized L1 block.
+///
+/// Unsafe blocks follow the rollup parameters: one block per block time, no block further ahead
+/// of its L1 origin than the max sequencer drift, and unsafe blocks not confirmed within the
+/// sequencing window of their L1 origin are dropped.
+///
+/// The state is checkpointed after every advance, see [`SimpleRunner::resume`].
+pub struct SimpleRunner<I = InstantDeriveImpl> {
     engine: SvmEngine,
-    instant_derive: Option<InstantDeriveImpl>,
+    instant_derive: Option<I>,
     da_derive: Option<DaDeriveImpl>,
-    current_head: Option<L1HeadImpl>,
-    sequence_number: u8,
+    params: RollupParams,
+    checkpoints: CheckpointStore,
+    /// Last L1 block derived by the instant derivation.
+    l1_head: Option<L1HeadImpl>,
+    /// Hashes of the L1 blocks derived since the finalized one, by height.
+    l1_heads: BTreeMap<L1Height, L1Hash>,
+    /// Blocks above the finalized head, by height.
+    blocks: BTreeMap<L2Height, L2BlockRef>,
+    unsafe_head: Option<L2BlockRef>,
+    safe_head: Option<L2BlockRef>,
+    finalized_head: Option<L2BlockRef>,
+    /// Attributes of the unsafe blocks produced locally, by height.
+    pending_batches: BTreeMap<L2Height, PayloadAttributeImpl>,
 }
 
-impl Runner<SvmEngine, InstantDeriveImpl, DaDeriveImpl> for SimpleRunner {
+impl<I> Runner<SvmEngine, I, DaDeriveImpl> for SimpleRunner<I>
+where
+    I: InstantDerive<P = PayloadAttributeImpl, L1Info = L1BlockInfoImpl, Error = anyhow::Error>,
+{
     type Error = anyhow::Error;
+    type Head = L2BlockRef;
 
-    fn register_instant(&mut self, derive: InstantDeriveImpl) {
+    fn register_instant(&mut self, derive: I) {
         self.instant_derive = Some(derive);
     }
 
@@ -40,89 +78,370 @@ impl Runner<SvmEngine, InstantDeriveImpl, DaDeriveImpl> for SimpleRunner {
 
     async fn advance(&mut self) -> Result<(), Self::Error> {
         self.advance_safe().await?;
-        self.advance_unsafe().await
+        self.advance_unsafe().await?;
+        self.advance_finalized().await?;
+        self.save_checkpoint()
+    }
+
+    fn unsafe_head(&self) -> Option<&L2BlockRef> {
+        self.unsafe_head.as_ref()
+    }
+
+    fn safe_head(&self) -> Option<&L2BlockRef> {
+        self.safe_head.as_ref()
+    }
+
+    fn finalized_head(&self) -> Option<&L2BlockRef> {
+        self.finalized_head.as_ref()
     }
 }
 
-impl SimpleRunner {
+impl<I> SimpleRunner<I>
+where
+    I: InstantDerive<P = PayloadAttributeImpl, L1Info = L1BlockInfoImpl, Error = anyhow::Error>,
+{
     pub fn new(
         base_path: &Path,
+        params: RollupParams,
         attribute_sender: Sender<PayloadAttributeImpl>,
     ) -> anyhow::Result<Self> {
         Ok(Self {
             engine: SvmEngine::new(base_path, attribute_sender)?,
             instant_derive: None,
             da_derive: None,
-            current_head: None,
-            sequence_number: 0,
+            params,
+            checkpoints: CheckpointStore::new(&base_path.join("checkpoint.json")),
+            l1_head: None,
+            l1_heads: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            unsafe_head: None,
+            safe_head: None,
+            finalized_head: None,
+            pending_batches: BTreeMap::new(),
         })
     }
 
-    async fn advance_unsafe(&mut self) -> Result<()> {
-        let info = self.instant_derive()?.get_new_block().await?;
-        let block = if let Some(i) = info {
-            self.current_head = Some(i.l1_head().clone());
-            self.sequence_number = 0;
+    /// Resumes from the last checkpoint, if any, once the derivations are registered. The
+    /// engine ledger is restored from the checkpointed blocks and blocks written after the
+    /// checkpoint are dropped; the derivations continue after their checkpointed positions, and
+    /// the pending batches are posted again.
+    pub async fn resume(&mut self) -> Result<()> {
+        let Some(checkpoint) = self.checkpoints.load()? else {
+            return Ok(());
+        };
+        let finalized = checkpoint
+            .finalized_head
+            .as_ref()
+            .map_or(0, |block| block.head.block_height());
+        let heads = checkpoint
+            .finalized_head
+            .iter()
+            .chain(&checkpoint.blocks)
+            .map(|block| block.head.clone())
+            .collect();
+        self.engine.resume(heads, finalized).await?;
+
+        if let Some(l1_head) = checkpoint.l1_head.clone() {
+            self.instant_derive()?.resume_after(l1_head);
+        }
+        if let Some(progress) = checkpoint.da_progress.as_ref() {
+            self.da_derive()?.resume(progress);
+        }
+        for attribute in checkpoint.pending_batches.values() {
+            self.engine.post_attribute(attribute.clone()).await;
+        }
 
-            self.engine.produce_block(i.try_into()?).await?
-        } else if let Some(safe_head) = self.current_head.as_ref() {
-            self.sequence_number += 1;
+        let height = |block: &Option<L2BlockRef>| block.as_ref().map(|b| b.head.block_height());
+        info!(
+            "resume from unsafe head: {:?}, safe head: {:?}, finalized head: {:?}",
+            height(&checkpoint.unsafe_head),
+            height(&checkpoint.safe_head),
+            height(&checkpoint.finalized_head)
+        );
+        self.l1_head = checkpoint.l1_head;
+        self.l1_heads = checkpoint.l1_heads;
+        self.blocks = checkpoint
+            .blocks
+            .into_iter()
+            .map(|block| (block.head.block_height(), block))
+            .collect();
+        self.unsafe_head = checkpoint.unsafe_head;
+        self.safe_head = checkpoint.safe_head;
+        self.finalized_head = checkpoint.finalized_head;
+        self.pending_batches = checkpoint.pending_batches;
+        Ok(())
+    }
 
-            let mut attribute: PayloadAttributeImpl = safe_head.clone().try_into()?;
-            attribute.sequence_number = self.sequence_number;
-            self.engine.produce_block(attribute).await?
+    fn save_checkpoint(&self) -> Result<()> {
+        self.checkpoints.save(&Checkpoint {
+            l1_head: self.l1_head.clone(),
+            l1_heads: self.l1_heads.clone(),
+            unsafe_head: self.unsafe_head.clone(),
+            safe_head: self.safe_head.clone(),
+            finalized_head: self.finalized_head.clone(),
+            blocks: self.blocks.values().cloned().collect(),
+            da_progress: self.da_derive.as_ref().map(|derive| derive.progress()),
+            pending_batches: self.pending_batches.clone(),
+        })
+    }
+
+    async fn advance_unsafe(&mut self) -> Result<()> {
+        let info = self.instant_derive()?.get_new_block().await?;
+        let attribute = if let Some(i) = info {
+            self.follow_l1(i.l1_head()).await?;
+            self.enforce_seq_window(i.l1_head().block_height()).await?;
+            self.l1_head = Some(i.l1_head().clone());
+            i.try_into()?
+        } else if let Some(head) = self.unsafe_head.as_ref() {
+            let epoch = head.epoch.clone();
+            let sequence_number = head.sequence_number + 1;
+            if self
+                .params
+                .check_drift(epoch.timestamp(), sequence_number)
+                .is_err()
+            {
+                debug!(
+                    "max sequencer drift reached at L1 height {}, wait for the next L1 block",
+                    epoch.block_height()
+                );
+                return Ok(());
+            }
+            let timestamp = self.params.l2_timestamp(epoch.timestamp(), sequence_number);
+            if timestamp > chrono::Utc::now().timestamp() as u64 {
+                // the next block is not due yet
+                return Ok(());
+            }
+            PayloadAttributeImpl {
+                transactions: Default::default(),
+                epoch,
+                sequence_number,
+            }
         } else {
             return Ok(());
         };
 
-        self.new_block(block).await?;
+        let (attribute, block) = self.engine.produce_block(attribute).await?;
+        let block = self.new_block(block, &attribute).await?;
+        self.pending_batches
+            .insert(block.head.block_height(), attribute);
+        Ok(())
+    }
+
+    /// Records `head` as the L1 block at its height. A head replacing derived L1 blocks drops the
+    /// blocks derived from them.
+    async fn follow_l1(&mut self, head: &L1HeadImpl) -> Result<()> {
+        let replaced = self.l1_heads.split_off(&head.height);
+        if let Some(parent) = head
+            .height
+            .checked_sub(1)
+            .and_then(|height| self.l1_heads.get(&height))
+        {
+            if *parent != head.parent_hash {
+                bail!(
+                    "L1 block {} at height {} does not build on the derived one",
+                    hex::encode(head.hash),
+                    head.height
+                );
+            }
+        }
+        self.l1_heads.insert(head.height, head.hash);
+        if !replaced.is_empty() {
+            warn!("L1 reorg at height {}", head.height);
+            self.reorg_l1(head.height).await?;
+        }
+        Ok(())
+    }
+
+    /// Drops the blocks derived from the L1 blocks from `l1_height` on, which were replaced.
+    async fn reorg_l1(&mut self, l1_height: L1Height) -> Result<()> {
+        if self
+            .finalized_head
+            .as_ref()
+            .is_some_and(|block| block.epoch.block_height() >= l1_height)
+        {
+            bail!("L1 reorg at height {} below the finalized head", l1_height);
+        }
+        let reset_to = self
+            .blocks
+            .values()
+            .rev()
+            .find(|block| block.epoch.block_height() < l1_height)
+            .or(self.finalized_head.as_ref())
+            .cloned();
+        let reset_height = reset_to
+            .as_ref()
+            .map_or(0, |block| block.head.block_height());
+        let height =
+            |block: &Option<L2BlockRef>| block.as_ref().map_or(0, |b| b.head.block_height());
+        if height(&self.unsafe_head) <= reset_height {
+            return Ok(());
+        }
+        warn!(
+            "drop blocks from {} derived from replaced L1 blocks",
+            reset_height + 1
+        );
+        if height(&self.safe_head) > reset_height {
+            self.safe_head = reset_to.clone();
+            self.da_derive()?.reset_head(reset_to.as_ref());
+        }
+        self.reorg_to(reset_to).await
+    }
+
+    /// Drops the unsafe blocks not confirmed by DA within the sequencing window at `l1_height`.
+    async fn enforce_seq_window(&mut self, l1_height: L1Height) -> Result<()> {
+        let safe_height = self
+            .safe_head
+            .as_ref()
+            .map_or(0, |block| block.head.block_height());
+        let Some((height, block)) = self.blocks.range(safe_height + 1..).next() else {
+            return Ok(());
+        };
+        if let Err(e) = self
+            .params
+            .check_window(block.epoch.block_height(), l1_height)
+        {
+            warn!("drop unsafe blocks from {}: {}", height, e);
+            self.reorg_to_safe().await?;
+        }
         Ok(())
     }
 
+    /// Appends a block produced from `attribute` on top of the unsafe head.
     #[allow(clippy::unnecessary_fallible_conversions)]
-    async fn new_block(&mut self, block: BlockPayloadImpl) -> Result<L2HeadImpl> {
+    async fn new_block(
+        &mut self,
+        block: BlockPayloadImpl,
+        attribute: &PayloadAttributeImpl,
+    ) -> Result<L2BlockRef> {
         let new_head = self.engine.new_block(block.try_into()?).await?;
+        let block = L2BlockRef::new(new_head, attribute);
         info!(
             "new block at: {}, derive from: {}",
-            new_head.block_height(),
-            self.current_head
-                .as_ref()
-                .ok_or(anyhow::anyhow!("No current head"))?
-                .block_height()
+            block.head.block_height(),
+            block.epoch.block_height()
         );
-        Ok(new_head)
+
+        // blocks left above the new one were orphaned by a reorg of the engine
+        self.blocks.split_off(&block.head.block_height());
+        self.pending_batches.split_off(&block.head.block_height());
+        self.blocks.insert(block.head.block_height(), block.clone());
+        self.unsafe_head = Some(block.clone());
+        Ok(block)
     }
 
     async fn advance_safe(&mut self) -> Result<()> {
         trace!("begin of da derive");
+        if let Some(l1_height) = self.l1_head.as_ref().map(|head| head.block_height()) {
+            self.da_derive()?.set_l1_height(l1_height);
+        }
         while let Some(attribute) = self.da_derive()?.next().await {
-            if self.has_executed(&attribute) {
-                debug!(
-                    "skip executed attribute at L1 height {} sequence number {}",
+            let height = self
+                .safe_head
+                .as_ref()
+                .map_or(0, |block| block.head.block_height())
+                + 1;
+            let diverged = match self.blocks.get(&height) {
+                Some(block) if block.matches(&attribute) => {
+                    debug!("block {} confirmed by DA", height);
+                    self.safe_head = Some(block.clone());
+                    self.pending_batches.remove(&height);
+                    continue;
+                }
+                Some(_) => true,
+                None => false,
+            };
+            if diverged {
+                warn!(
+                    "block {} diverges from DA at L1 height {} sequence number {}",
+                    height,
                     attribute.epoch.block_height(),
                     attribute.sequence_number
                 );
-                continue;
+                self.reorg_to_safe().await?;
             }
 
-            let block = self.engine.produce_block(attribute).await?;
-            self.new_block(block).await?;
+            let block = self.engine.derive_block(attribute.clone()).await?;
+            self.safe_head = Some(self.new_block(block, &attribute).await?);
         }
         trace!("end of da derive");
         Ok(())
     }
 
-    fn has_executed(&self, attribute: &PayloadAttributeImpl) -> bool {
-        if let Some(head) = self.current_head.as_ref() {
-            if head.block_height() > attribute.epoch.block_height() {
-                return true;
+    /// Drops every unsafe block above the safe head.
+    async fn reorg_to_safe(&mut self) -> Result<()> {
+        self.reorg_to(self.safe_head.clone()).await
+    }
+
+    /// Drops every block above `block`, which becomes the unsafe head.
+    async fn reorg_to(&mut self, block: Option<L2BlockRef>) -> Result<()> {
+        let reset_to = block
+            .as_ref()
+            .map(|block| block.head.clone())
+            .unwrap_or_default();
+        self.engine.reorg(reset_to.clone()).await?;
+        self.blocks.split_off(&(reset_to.block_height() + 1));
+        self.pending_batches
+            .split_off(&(reset_to.block_height() + 1));
+        self.unsafe_head = block;
+        Ok(())
+    }
+
+    /// Finalizes the latest safe block whose L1 origin is on the chain of the finalized L1 block.
+    async fn advance_finalized(&mut self) -> Result<()> {
+        let Some(l1_finalized) = self.instant_derive()?.get_finalized().await? else {
+            return Ok(());
+        };
+        let Some(safe_height) = self.safe_head.as_ref().map(|b| b.head.block_height()) else {
+            return Ok(());
+        };
+        let Some((&derived_height, _)) = self.l1_heads.last_key_value() else {
+            return Ok(());
+        };
+        let l1_finalized_height = match self.l1_heads.get(&l1_finalized.height) {
+            Some(hash) if *hash == l1_finalized.hash => l1_finalized.height,
+            Some(_) => {
+                warn!(
+                    "L1 block {} finalized at height {} was not derived",
+                    hex::encode(l1_finalized.hash),
+                    l1_finalized.height
+                );
+                self.l1_heads.split_off(&l1_finalized.height);
+                return self.reorg_l1(l1_finalized.height).await;
             }
-            return self.sequence_number >= attribute.sequence_number;
-        }
-        false
+            // derived behind the finalized L1 block, the derived ones can not be replaced anymore
+            None if derived_height < l1_finalized.height => derived_height,
+            None => return Ok(()),
+        };
+        let Some(block) = self
+            .blocks
+            .range(..=safe_height)
+            .rev()
+            .find(|(_, block)| {
+                let epoch_height = block.epoch.block_height();
+                epoch_height <= l1_finalized_height
+                    && self
+                        .l1_heads
+                        .get(&epoch_height)
+                        .is_none_or(|hash| *hash == block.epoch.block_hash())
+            })
+            .map(|(_, block)| block.clone())
+        else {
+            return Ok(());
+        };
+        self.l1_heads = self.l1_heads.split_off(&l1_finalized_height);
+
+        self.engine.finalize(block.head.clone()).await?;
+        info!(
+            "finalize block at: {}, L1 finalized at: {}",
+            block.head.block_height(),
+            l1_finalized.block_height()
+        );
+        self.blocks = self.blocks.split_off(&(block.head.block_height() + 1));
+        self.finalized_head = Some(block);
+        Ok(())
     }
 
-    fn instant_derive(&mut self) -> Result<&mut InstantDeriveImpl> {
+    fn instant_derive(&mut self) -> Result<&mut I> {
         self.instant_derive
             .as_mut()
             .ok_or(anyhow::anyhow!("Instant derive not registered"))
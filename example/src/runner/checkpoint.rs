@@ -0,0 +1,101 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    derive::da::DaProgress,
+    l1::{attribute::PayloadAttributeImpl, head::L1HeadImpl, L1Hash, L1Height},
+    l2::{head::L2BlockRef, L2Height},
+};
+
+/// Runner state saved after every advance, so a restarted runner resumes where it stopped.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Last L1 block derived by the instant derivation.
+    pub l1_head: Option<L1HeadImpl>,
+    /// Hashes of the L1 blocks derived since the finalized one, by height.
+    #[serde(default)]
+    pub l1_heads: BTreeMap<L1Height, L1Hash>,
+    pub unsafe_head: Option<L2BlockRef>,
+    pub safe_head: Option<L2BlockRef>,
+    pub finalized_head: Option<L2BlockRef>,
+    /// Blocks above the finalized head, in height order.
+    pub blocks: Vec<L2BlockRef>,
+    pub da_progress: Option<DaProgress>,
+    /// Attributes of the unsafe blocks produced locally, posted to DA again on restart since the
+    /// batcher may not have submitted them.
+    #[serde(with = "hex_attributes")]
+    pub pending_batches: BTreeMap<L2Height, PayloadAttributeImpl>,
+}
+
+/// Keeps the latest checkpoint in a JSON file, replaced atomically on save.
+pub struct CheckpointStore {
+    path: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn load(&self) -> Result<Option<Checkpoint>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec(checkpoint)?)?;
+        file.sync_all()?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Attributes by height, stored in their hex encoded DA form.
+mod hex_attributes {
+    use std::collections::BTreeMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::{l1::attribute::PayloadAttributeImpl, l2::L2Height};
+
+    pub fn serialize<S: Serializer>(
+        attributes: &BTreeMap<L2Height, PayloadAttributeImpl>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            attributes
+                .iter()
+                .map(|(height, attribute)| (height, hex::encode(attribute.encode()))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<L2Height, PayloadAttributeImpl>, D::Error> {
+        BTreeMap::<L2Height, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(height, data)| {
+                let data = hex::decode(data).map_err(D::Error::custom)?;
+                let attribute = PayloadAttributeImpl::decode(&data).map_err(D::Error::custom)?;
+                Ok((height, attribute))
+            })
+            .collect()
+    }
+}
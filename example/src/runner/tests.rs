@@ -0,0 +1,476 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Result;
+use igloo_batcher::{Batcher, BatcherConfig, FileSink, FileSource};
+use igloo_interface::{
+    l1::Epoch,
+    l2::{stream::TransactionStream, Engine, EngineApi, L2Head},
+    runner::Runner,
+};
+use igloo_verifier::rollup::RollupParams;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use super::SimpleRunner;
+use crate::{
+    derive::{
+        da::{DaDeriveConfig, DaDeriveImpl},
+        instant::InstantDeriveImpl,
+    },
+    l1::{attribute::PayloadAttributeImpl, block::L1BlockInfoImpl, head::L1HeadImpl},
+    l2::{
+        head::{L2BlockRef, L2HeadImpl},
+        tx::L2Transaction,
+    },
+};
+
+struct TestContext {
+    runner: SimpleRunner,
+    l1_sender: Sender<L1BlockInfoImpl>,
+    // the engine sends every produced attribute, they are posted to DA on demand
+    attribute_receiver: Receiver<PayloadAttributeImpl>,
+    base_path: PathBuf,
+    da_path: PathBuf,
+}
+
+impl TestContext {
+    fn new() -> Result<Self> {
+        Self::with_params(RollupParams::default())
+    }
+
+    fn with_params(params: RollupParams) -> Result<Self> {
+        Self::open(tempfile::tempdir()?.into_path(), params)
+    }
+
+    fn open(base_path: PathBuf, params: RollupParams) -> Result<Self> {
+        let (l1_sender, l1_receiver) = channel(16);
+        let (attribute_sender, attribute_receiver) = channel(1024);
+
+        let mut runner = SimpleRunner::new(&base_path, params, attribute_sender)?;
+        runner.register_instant(InstantDeriveImpl::new(l1_receiver));
+        runner.register_da(DaDeriveImpl::new(
+            Box::new(FileSource::new(&base_path.join("da"), 0)),
+            DaDeriveConfig {
+                rollup: params.clone(),
+                ..Default::default()
+            },
+        ));
+
+        Ok(Self {
+            runner,
+            l1_sender,
+            attribute_receiver,
+            da_path: base_path.join("da"),
+            base_path,
+        })
+    }
+
+    async fn new_l1_block(&self, height: u64) -> Result<()> {
+        self.new_l1_block_with_hash(height, height as u8).await
+    }
+
+    /// Sends the L1 block at `height` with the hash `[hash; 32]`, e.g. to replace one sent before.
+    async fn new_l1_block_with_hash(&self, height: u64, hash: u8) -> Result<()> {
+        self.l1_sender
+            .send(L1BlockInfoImpl {
+                deposit_txs: vec![],
+                batch: None,
+                l1_head: L1HeadImpl {
+                    hash: [hash; 32],
+                    parent_hash: [height.saturating_sub(1) as u8; 32],
+                    height,
+                    timestamp: height * 12,
+                },
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Advance the runner with one pending transaction, so every block has shreds.
+    async fn advance_with_tx(&mut self) -> Result<()> {
+        self.runner
+            .get_engine()
+            .stream()
+            .write()
+            .await
+            .insert(L2Transaction {
+                from: Pubkey::new_unique(),
+                to: Pubkey::new_unique(),
+                amount: 10,
+                calldata: vec![],
+            })
+            .await?;
+        self.runner.advance().await
+    }
+
+    /// Post `attributes` to DA in one channel.
+    async fn post(&self, attributes: &[PayloadAttributeImpl]) -> Result<()> {
+        let mut batcher = Batcher::new(BatcherConfig::default(), FileSink::new(&self.da_path)?);
+        for attribute in attributes {
+            batcher.add_block(attribute.encode()).await?;
+        }
+        batcher.flush().await?;
+        Ok(())
+    }
+
+    /// Attributes of the blocks produced so far.
+    fn produced(&mut self) -> Vec<PayloadAttributeImpl> {
+        let mut attributes = vec![];
+        while let Ok(attribute) = self.attribute_receiver.try_recv() {
+            attributes.push(attribute);
+        }
+        attributes
+    }
+
+    fn heights(&self) -> (Option<u64>, Option<u64>, Option<u64>) {
+        let height = |head: Option<&L2BlockRef>| head.map(|b| b.head.block_height());
+        (
+            height(self.runner.unsafe_head()),
+            height(self.runner.safe_head()),
+            height(self.runner.finalized_head()),
+        )
+    }
+
+    async fn head(&mut self, height: u64) -> Result<Option<L2HeadImpl>> {
+        self.runner.engine.get_head(height).await
+    }
+
+    async fn latest_height(&self) -> u64 {
+        self.runner.engine.ledger().read().await.latest_height()
+    }
+
+    async fn has_slot(&self, slot: u64) -> bool {
+        self.runner.engine.blockstore().read().await.has_slot(slot)
+    }
+}
+
+#[tokio::test]
+async fn reorg_drops_orphaned_blocks() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..4 {
+        ctx.advance_with_tx().await?;
+    }
+    assert_eq!(ctx.latest_height().await, 4);
+    for height in 1..=4 {
+        assert!(ctx.has_slot(height).await);
+    }
+
+    let reset_to = ctx.head(2).await?.unwrap();
+    ctx.runner.engine.reorg(reset_to).await?;
+    assert_eq!(ctx.latest_height().await, 2);
+    assert!(ctx.head(3).await?.is_none());
+    assert!(ctx.head(4).await?.is_none());
+    assert!(ctx.has_slot(2).await);
+    assert!(!ctx.has_slot(3).await);
+    assert!(!ctx.has_slot(4).await);
+
+    // the runner continues on top of the new head
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.latest_height().await, 3);
+    assert!(ctx.has_slot(3).await);
+    assert!(!ctx.has_slot(4).await);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reorg_to_unknown_head_should_fail() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    ctx.advance_with_tx().await?;
+
+    let unknown = L2HeadImpl {
+        height: 5,
+        ..Default::default()
+    };
+    assert!(ctx.runner.engine.reorg(unknown).await.is_err());
+    assert_eq!(ctx.latest_height().await, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn finalized_blocks_can_not_be_reorged() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..3 {
+        ctx.advance_with_tx().await?;
+    }
+
+    let finalized = ctx.head(2).await?.unwrap();
+    ctx.runner.engine.finalize(finalized.clone()).await?;
+    assert_eq!(ctx.runner.engine.ledger().read().await.finalized, 2);
+    assert!(ctx.runner.engine.blockstore().read().await.is_root(2));
+
+    let below_finalized = ctx.head(1).await?.unwrap();
+    assert!(ctx
+        .runner
+        .engine
+        .reorg(below_finalized.clone())
+        .await
+        .is_err());
+    assert!(ctx.runner.engine.finalize(below_finalized).await.is_err());
+    assert_eq!(ctx.latest_height().await, 3);
+
+    // reorg onto the finalized head itself is still allowed
+    ctx.runner.engine.reorg(finalized.clone()).await?;
+    assert_eq!(ctx.latest_height().await, finalized.block_height());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn runner_reorgs_on_diverged_da_attribute() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..4 {
+        ctx.advance_with_tx().await?;
+    }
+    assert_eq!(ctx.heights(), (Some(4), None, None));
+    let mut orphaned = vec![];
+    for height in 2..=4 {
+        orphaned.push(ctx.head(height).await?.unwrap().block_hash());
+    }
+    let first = ctx.head(1).await?.unwrap();
+    let produced = ctx.produced();
+
+    // DA confirms block 1 and holds another block 2, advancing reorgs blocks 2 to 4 away
+    let mut diverged = produced[1].clone();
+    diverged.transactions = Arc::new(vec![]);
+    ctx.post(&[produced[0].clone(), diverged.clone()]).await?;
+    ctx.runner.advance().await?;
+
+    // the safe block 2 is derived from DA, then a new unsafe block 3 is produced on top of it
+    assert_eq!(ctx.heights(), (Some(3), Some(2), Some(2)));
+    assert!(ctx.runner.safe_head().unwrap().matches(&diverged));
+    assert_eq!(ctx.runner.unsafe_head().unwrap().sequence_number, 2);
+
+    {
+        let ledger = ctx.runner.engine.ledger().read().await;
+        assert_eq!(ledger.blocks.keys().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(ledger.finalized, 2);
+        for hash in &orphaned {
+            assert!(!ledger.hash_map.contains_key(hash));
+        }
+    }
+    assert_eq!(ctx.head(1).await?.unwrap().block_hash(), first.block_hash());
+    let safe = ctx.runner.safe_head().unwrap().head.clone();
+    assert_eq!(ctx.head(2).await?.unwrap().block_hash(), safe.block_hash());
+    assert!(ctx.head(4).await?.is_none());
+
+    for slot in 1..=3 {
+        assert!(ctx.has_slot(slot).await);
+    }
+    assert!(!ctx.has_slot(4).await);
+    assert!(ctx.runner.engine.blockstore().read().await.is_root(2));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn heads_follow_da_and_l1_finality() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..3 {
+        ctx.advance_with_tx().await?;
+    }
+    assert_eq!(ctx.heights(), (Some(3), None, None));
+
+    // blocks confirmed by DA become safe, the mock L1 finalizes every block at once
+    let produced = ctx.produced();
+    assert_eq!(produced.len(), 3);
+    ctx.post(&produced[..2]).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.heights(), (Some(4), Some(2), Some(2)));
+    assert_eq!(ctx.runner.engine.ledger().read().await.finalized, 2);
+    assert_eq!(ctx.latest_height().await, 4);
+
+    // unsafe blocks are not finalized before DA confirms them
+    ctx.new_l1_block(2).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.heights(), (Some(5), Some(2), Some(2)));
+    let produced = [&produced[2..], &ctx.produced()].concat();
+    ctx.post(&produced).await?;
+    ctx.runner.advance().await?;
+    let safe = ctx.runner.safe_head().unwrap();
+    assert_eq!(safe.head.block_height(), 5);
+    assert_eq!(safe.epoch.block_height(), 2);
+    assert_eq!(ctx.heights(), (Some(6), Some(5), Some(5)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn diverged_da_blocks_reorg_unsafe_blocks() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..4 {
+        ctx.advance_with_tx().await?;
+    }
+    let produced = ctx.produced();
+
+    // DA holds another second block
+    let mut diverged = produced[1].clone();
+    diverged.transactions = Arc::new(vec![]);
+    ctx.post(&[produced[0].clone(), diverged.clone()]).await?;
+    ctx.runner.advance().await?;
+
+    // block 2 is derived from DA again, the blocks above it were dropped
+    let (unsafe_height, safe_height, _) = ctx.heights();
+    assert_eq!(safe_height, Some(2));
+    assert!(ctx.runner.safe_head().unwrap().matches(&diverged));
+    assert!(!ctx.has_slot(4).await);
+    // the runner continues on top of the safe head
+    assert_eq!(unsafe_height, Some(3));
+    assert_eq!(ctx.latest_height().await, 3);
+    let unsafe_head = ctx.runner.unsafe_head().unwrap();
+    assert_eq!(unsafe_head.sequence_number, 2);
+    assert!(ctx.head(4).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn runner_resumes_from_checkpoint() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..3 {
+        ctx.advance_with_tx().await?;
+    }
+    let produced = ctx.produced();
+    ctx.post(&produced[..2]).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.heights(), (Some(4), Some(2), Some(2)));
+    let pending = [&produced[2..], &ctx.produced()].concat();
+
+    // a block written after the checkpoint is dropped on restart
+    ctx.runner
+        .get_engine()
+        .stream()
+        .write()
+        .await
+        .insert(L2Transaction {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount: 10,
+            calldata: vec![],
+        })
+        .await?;
+    ctx.runner.advance_unsafe().await?;
+    assert!(ctx.has_slot(5).await);
+
+    let base_path = ctx.base_path.clone();
+    drop(ctx);
+    let mut ctx = TestContext::open(base_path, RollupParams::default())?;
+    ctx.runner.resume().await?;
+    assert_eq!(ctx.heights(), (Some(4), Some(2), Some(2)));
+    assert_eq!(ctx.latest_height().await, 4);
+    assert_eq!(ctx.runner.engine.ledger().read().await.finalized, 2);
+    assert!(!ctx.has_slot(5).await);
+    assert!(ctx.has_slot(4).await);
+
+    // the unconfirmed batches are posted again
+    let reposted = ctx.produced();
+    assert_eq!(reposted.len(), 2);
+    for (reposted, pending) in reposted.iter().zip(&pending) {
+        assert_eq!(reposted.encode(), pending.encode());
+    }
+
+    // the DA derivation continues after the derived channel, no L1 block is final since the
+    // restart
+    ctx.post(&reposted).await?;
+    ctx.runner.advance().await?;
+    assert_eq!(ctx.heights(), (Some(5), Some(4), Some(2)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sequencer_drift_bounds_blocks_per_epoch() -> Result<()> {
+    let mut ctx = TestContext::with_params(RollupParams {
+        block_time: 2,
+        max_sequencer_drift: 4,
+        ..Default::default()
+    })?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..5 {
+        ctx.runner.advance().await?;
+    }
+    // sequence numbers 0 to 2 are within the drift
+    assert_eq!(ctx.latest_height().await, 3);
+    assert_eq!(ctx.runner.unsafe_head().unwrap().sequence_number, 2);
+
+    ctx.new_l1_block(2).await?;
+    ctx.runner.advance().await?;
+    let unsafe_head = ctx.runner.unsafe_head().unwrap();
+    assert_eq!(unsafe_head.head.block_height(), 4);
+    assert_eq!(unsafe_head.epoch.block_height(), 2);
+    assert_eq!(unsafe_head.sequence_number, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unconfirmed_blocks_outside_seq_window_are_dropped() -> Result<()> {
+    let mut ctx = TestContext::with_params(RollupParams {
+        seq_window_size: 1,
+        ..Default::default()
+    })?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..2 {
+        ctx.advance_with_tx().await?;
+    }
+    ctx.new_l1_block(2).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.latest_height().await, 3);
+
+    // blocks of epoch 1 are not confirmed by L1 block 3
+    ctx.new_l1_block(3).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.latest_height().await, 1);
+    assert!(!ctx.has_slot(2).await);
+    let unsafe_head = ctx.runner.unsafe_head().unwrap();
+    assert_eq!(unsafe_head.epoch.block_height(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn l1_reorg_drops_blocks_of_replaced_epochs() -> Result<()> {
+    let mut ctx = TestContext::new()?;
+    ctx.new_l1_block(1).await?;
+    for _ in 0..2 {
+        ctx.advance_with_tx().await?;
+    }
+    ctx.new_l1_block(2).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.heights(), (Some(3), None, None));
+    let produced = ctx.produced();
+    ctx.post(&produced).await?;
+
+    // block 3 is confirmed by DA, but its L1 origin is replaced before it is finalized
+    ctx.new_l1_block_with_hash(2, 0xee).await?;
+    ctx.advance_with_tx().await?;
+    assert_eq!(ctx.heights(), (Some(3), Some(2), Some(2)));
+    let unsafe_head = ctx.runner.unsafe_head().unwrap();
+    assert_eq!(unsafe_head.epoch.block_hash(), [0xee; 32]);
+    assert_eq!(unsafe_head.sequence_number, 0);
+    assert_eq!(ctx.latest_height().await, 3);
+
+    // the DA derivation continues after the new safe head
+    let produced = ctx.produced();
+    assert_eq!(produced.len(), 1);
+    ctx.post(&produced).await?;
+    ctx.runner.advance().await?;
+    let safe = ctx.runner.safe_head().unwrap();
+    assert_eq!(safe.head.block_height(), 3);
+    assert_eq!(safe.epoch.block_hash(), [0xee; 32]);
+    assert_eq!(ctx.heights(), (Some(4), Some(3), Some(3)));
+
+    // an L1 block not building on the derived ones fails the runner
+    ctx.new_l1_block(3).await?;
+    assert!(ctx.runner.advance().await.is_err());
+
+    Ok(())
+}
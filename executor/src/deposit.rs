@@ -0,0 +1,91 @@
+use crate::{processor::TransactionProcessor, Error, Executor, Result};
+use igloo_storage::blockstore::txs::CommitBatch;
+use igloo_verifier::settings::Settings;
+use solana_sdk::{
+    fee::FeeStructure, hash::Hash, instruction::AccountMeta, pubkey::Pubkey, signature::Keypair,
+    signer::Signer, system_instruction, transaction::Transaction,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Funds deposited on L1, credited to `to` from the bridge escrow before the transactions of a
+/// block. Deposits are not signed by the depositor and are charged no fee.
+///
+/// Deposits are transfers from the escrow signed by the sequencer, written first in the block
+/// entries, one deposit per entry, so a replica replaying the blockstore applies them like any
+/// other transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deposit {
+    /// Unique id of the deposit on L1, e.g. the hash of its L1 block hash and log index.
+    pub source_hash: Hash,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+}
+
+impl Deposit {
+    /// Transfer from the escrow carrying the deposit. The source hash is passed as an extra
+    /// account of the transfer, so that equal deposits are distinct transactions.
+    pub fn to_transaction(&self, escrow: &Keypair, recent_blockhash: Hash) -> Transaction {
+        let mut instruction = system_instruction::transfer(&escrow.pubkey(), &self.to, self.amount);
+        instruction.accounts.push(AccountMeta::new_readonly(
+            Pubkey::new_from_array(self.source_hash.to_bytes()),
+            false,
+        ));
+        Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&escrow.pubkey()),
+            &[escrow],
+            recent_blockhash,
+        )
+    }
+}
+
+impl Executor {
+    /// Account holding the bridged supply, deposits are paid out of it.
+    pub fn set_deposit_escrow(&mut self, escrow: Arc<Keypair>) {
+        self.deposit_escrow = Some(escrow);
+    }
+
+    /// Executes the deposits and commits them to the working bank ahead of the block, their
+    /// receipts are recorded in the transaction history. A failed deposit only fails its own
+    /// receipt, a deposit that cannot be executed at all fails the block.
+    pub(crate) fn process_deposits(&mut self, deposits: &[Deposit]) -> Result<()> {
+        let escrow = self
+            .deposit_escrow
+            .clone()
+            .ok_or(Error::DepositEscrowNotSet)?;
+        // deposits are paid by the bridge, they are charged no fee
+        let settings = Settings {
+            fee_structure: FeeStructure::new(0.0, 0.0, vec![(u64::MAX, 0.0)]),
+            ..self.validator_settings.clone()
+        };
+
+        // every deposit debits the escrow, so each one is executed and committed on its own
+        // and written to its own entry, before the next one
+        for deposit in deposits {
+            let storage = self.storage()?;
+            let bank = storage.current_bank();
+            let transactions = vec![storage.to_sanitized_transaction(
+                deposit
+                    .to_transaction(&escrow, bank.last_blockhash())
+                    .into(),
+                false,
+            )?];
+            let output = TransactionProcessor::new(bank, settings.clone())
+                .process(Cow::Borrowed(&transactions))?;
+            match output.execution_results[0].flattened_result() {
+                Ok(()) => {}
+                Err(e) if output.execution_results[0].was_executed() => {
+                    log::warn!("deposit {} failed: {}", deposit.source_hash, e)
+                }
+                Err(e) => return Err(Error::DepositNotExecuted(deposit.source_hash, e)),
+            }
+
+            self.storage_mut()?.commit_ahead(
+                output.into(),
+                CommitBatch::new(Cow::Borrowed(&transactions)),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use solana_sdk::{hash::Hash, transaction::TransactionError};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -10,6 +11,9 @@ pub enum Error {
     #[error("Fetch stream batch error: {0}")]
     FetchStreamBatchError(String),
 
+    #[error("Fetch feed slot error: {0}")]
+    FetchFeedSlotError(String),
+
     #[error(transparent)]
     StorageError(#[from] igloo_storage::Error),
 
@@ -18,4 +22,13 @@ pub enum Error {
 
     #[error("Storage query error: {0}")]
     StorageQueryError(String),
+
+    #[error("Deposit escrow is not set")]
+    DepositEscrowNotSet,
+
+    #[error("Deposit {0} not executed: {1}")]
+    DepositNotExecuted(Hash, TransactionError),
+
+    #[error("Block process error: {0}")]
+    BlockProcessError(String),
 }
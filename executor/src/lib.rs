@@ -1,6 +1,5 @@
-use crate::processor::TransactionProcessor;
+use crate::processor::{BlockProcessor, SequentialBlockProcessor};
 use async_trait::async_trait;
-use igloo_storage::execution::TransactionsResultWrapper;
 use igloo_storage::{
     blockstore::txs::CommitBatch, config::GlobalConfig, ledger::SlotInfo, RollupStorage,
 };
@@ -8,19 +7,27 @@ use igloo_verifier::{
     settings::{Settings, Switchs},
     BankVerifier,
 };
-use solana_sdk::{clock::Slot, fee::FeeStructure, transaction::SanitizedTransaction};
+use solana_sdk::{
+    clock::Slot, fee::FeeStructure, signature::Keypair, signer::Signer,
+    transaction::SanitizedTransaction,
+};
 use std::{
     borrow::Cow,
     fmt::{Debug, Display},
     path::Path,
+    sync::Arc,
 };
 
+pub mod deposit;
 pub mod error;
 pub mod processor;
+pub mod replica;
 #[cfg(test)]
 mod tests;
 
+pub use deposit::Deposit;
 pub use error::{Error, Result};
+pub use replica::{FileLedgerFeed, LedgerFeed, Replica, TcpLedgerFeed};
 
 #[async_trait]
 pub trait StreamOperator {
@@ -32,6 +39,8 @@ pub type SanitizedTransactions = Vec<SanitizedTransaction>;
 
 #[derive(Clone, Default)]
 pub struct BlockPayload {
+    /// Executed natively before `transactions`, see [`Deposit`].
+    pub deposits: Vec<Deposit>,
     pub transactions: Vec<SanitizedTransactions>,
 }
 
@@ -40,6 +49,8 @@ pub struct Executor {
     storage: Option<RollupStorage>,
     finalized: Slot,
     validator_settings: Settings,
+    block_processor: Option<Arc<dyn BlockProcessor>>,
+    deposit_escrow: Option<Arc<Keypair>>,
 }
 
 impl Debug for Executor {
@@ -47,6 +58,11 @@ impl Debug for Executor {
         f.debug_struct("Engine")
             .field("finalized", &self.finalized)
             .field("validator_settings", &self.validator_settings)
+            .field("parallel", &self.block_processor.is_some())
+            .field(
+                "deposit_escrow",
+                &self.deposit_escrow.as_ref().map(|escrow| escrow.pubkey()),
+            )
             .finish()
     }
 }
@@ -81,9 +97,17 @@ impl Executor {
             storage: Some(storage),
             finalized,
             validator_settings,
+            block_processor: None,
+            deposit_escrow: None,
         })
     }
 
+    /// Replace the sequential batch execution of `new_block` with the given
+    /// processor, e.g. a scheduler backed parallel one.
+    pub fn set_block_processor(&mut self, processor: Arc<dyn BlockProcessor>) {
+        self.block_processor = Some(processor);
+    }
+
     pub async fn close(self) -> Result<()> {
         self.storage.ok_or(Error::StorageIsNone)?.close().await?;
         Ok(())
@@ -107,20 +131,28 @@ impl Executor {
     }
 
     pub async fn new_block(&mut self, block: BlockPayload) -> Result<SlotInfo> {
+        if !block.deposits.is_empty() && self.deposit_escrow.is_none() {
+            return Err(Error::DepositEscrowNotSet);
+        }
         self.storage_mut()?.bump()?;
 
-        let processor = TransactionProcessor::new(
-            self.storage()?.current_bank(),
-            self.validator_settings.clone(),
-        );
-        let mut results = vec![];
-        let mut origin_txs = vec![];
-        for transactions in block.transactions.iter() {
-            let result: TransactionsResultWrapper =
-                processor.process(Cow::Borrowed(transactions))?.into();
-            results.push(result);
-            origin_txs.push(CommitBatch::new(Cow::Borrowed(transactions)));
+        // deposits always go first, committed before the transactions spending them execute
+        if !block.deposits.is_empty() {
+            self.process_deposits(&block.deposits)?;
         }
+
+        let bank = self.storage()?.current_bank();
+        let results = match self.block_processor.as_ref() {
+            Some(processor) => processor.process_block(bank, &self.validator_settings, &block)?,
+            None => {
+                SequentialBlockProcessor.process_block(bank, &self.validator_settings, &block)?
+            }
+        };
+        let origin_txs = block
+            .transactions
+            .iter()
+            .map(|transactions| CommitBatch::new(Cow::Borrowed(transactions)))
+            .collect();
         self.storage_mut()?.commit(results, origin_txs).await?;
 
         let current = self.storage()?.current_height();
@@ -154,16 +186,23 @@ impl Executor {
 impl BlockPayload {
     pub fn new(transactions: Vec<SanitizedTransaction>) -> Self {
         Self {
+            deposits: vec![],
             transactions: vec![transactions],
         }
     }
 
     pub fn new_with_batches(batches: Vec<SanitizedTransactions>) -> Self {
         Self {
+            deposits: vec![],
             transactions: batches,
         }
     }
 
+    pub fn with_deposits(mut self, deposits: Vec<Deposit>) -> Self {
+        self.deposits = deposits;
+        self
+    }
+
     /// Extends the block with stream
     pub async fn extend_with<S: StreamOperator>(&mut self, stream: &S) -> Result<()> {
         self.transactions.extend(
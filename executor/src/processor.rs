@@ -1,14 +1,50 @@
-use crate::Result;
+use crate::{BlockPayload, Result};
+use igloo_storage::execution::TransactionsResultWrapper;
 use igloo_verifier::settings::Settings;
 use igloo_verifier::BankVerifier;
 use solana_runtime::bank::Bank;
 use solana_sdk::transaction::SanitizedTransaction;
 use solana_svm::transaction_processor::{
-    LoadAndExecuteSanitizedTransactionsOutput, TransactionProcessingConfig,
-    TransactionProcessingEnvironment,
+    ExecutionRecordingConfig, LoadAndExecuteSanitizedTransactionsOutput,
+    TransactionProcessingConfig, TransactionProcessingEnvironment,
 };
 use std::{borrow::Cow, sync::Arc};
 
+/// Executes all batches of a block against a bank without committing them.
+///
+/// Implementations must return exactly one result per batch of
+/// `BlockPayload::transactions`, in the same order, so the results can be
+/// committed the same way as the sequential path.
+pub trait BlockProcessor: Send + Sync {
+    fn process_block(
+        &self,
+        bank: Arc<Bank>,
+        settings: &Settings,
+        block: &BlockPayload,
+    ) -> Result<Vec<TransactionsResultWrapper>>;
+}
+
+/// The default `BlockProcessor`, runs every batch one by one with a single
+/// `TransactionProcessor`.
+#[derive(Default)]
+pub struct SequentialBlockProcessor;
+
+impl BlockProcessor for SequentialBlockProcessor {
+    fn process_block(
+        &self,
+        bank: Arc<Bank>,
+        settings: &Settings,
+        block: &BlockPayload,
+    ) -> Result<Vec<TransactionsResultWrapper>> {
+        let processor = TransactionProcessor::new(bank, settings.clone());
+        block
+            .transactions
+            .iter()
+            .map(|transactions| Ok(processor.process(Cow::Borrowed(transactions))?.into()))
+            .collect()
+    }
+}
+
 pub struct TransactionProcessor {
     bank: Arc<Bank>,
     settings: Settings,
@@ -62,6 +98,12 @@ impl TransactionProcessor {
             compute_budget: self.bank.compute_budget(),
             limit_to_load_programs: false,
             transaction_account_lock_limit: Some(self.bank.get_transaction_account_lock_limit()),
+            // logs are kept in the transaction history and streamed to log subscribers
+            recording_config: ExecutionRecordingConfig {
+                enable_log_recording: true,
+                enable_return_data_recording: true,
+                enable_cpi_recording: false,
+            },
             ..Default::default()
         }
     }
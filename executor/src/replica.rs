@@ -0,0 +1,198 @@
+use crate::{Error, Result};
+use async_trait::async_trait;
+use igloo_storage::{
+    broadcast::{FeedFormat, Subscription},
+    config::GlobalConfig,
+    replica::{LeaderSlot, MAX_FRAME_LEN},
+    RollupStorage,
+};
+use igloo_verifier::{settings::Settings, BankVerifier};
+use solana_sdk::{clock::Slot, transaction::TransactionVerificationMode};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::Path,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Slots completed by the leader, e.g. streamed or read from a file.
+#[async_trait]
+pub trait LedgerFeed {
+    type Error: Display;
+    /// Next slot of the leader, `None` once the feed is over.
+    async fn next_slot(&mut self) -> std::result::Result<Option<LeaderSlot>, Self::Error>;
+}
+
+/// Reads the slots written by `LeaderSlot::write_to` into a file.
+pub struct FileLedgerFeed {
+    reader: BufReader<File>,
+}
+
+impl FileLedgerFeed {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+        })
+    }
+}
+
+#[async_trait]
+impl LedgerFeed for FileLedgerFeed {
+    type Error = io::Error;
+
+    async fn next_slot(&mut self) -> io::Result<Option<LeaderSlot>> {
+        LeaderSlot::read_from(&mut self.reader)
+    }
+}
+
+/// Reads the slots streamed by the broadcast service of the leader, see
+/// `StorageConfig::broadcast_addr`. The feed is over once the leader closes
+/// the connection, it is resumed by connecting again from `cursor`. A feed
+/// behind the slots purged by the leader fails with a `NotFound` error, the
+/// follower has to bootstrap from its snapshots then.
+pub struct TcpLedgerFeed {
+    stream: TcpStream,
+    cursor: Slot,
+}
+
+impl TcpLedgerFeed {
+    pub async fn connect(
+        addr: SocketAddr,
+        from_slot: Slot,
+        format: FeedFormat,
+    ) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(&Subscription { from_slot, format }.encode())
+            .await?;
+        Ok(Self {
+            stream,
+            cursor: from_slot,
+        })
+    }
+
+    /// Slot after the last one received, to resume the feed from.
+    pub fn cursor(&self) -> Slot {
+        self.cursor
+    }
+}
+
+#[async_trait]
+impl LedgerFeed for TcpLedgerFeed {
+    type Error = io::Error;
+
+    async fn next_slot(&mut self) -> io::Result<Option<LeaderSlot>> {
+        let len = match self.stream.read_u64_le().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid leader slot: frame of {len} bytes"),
+            ));
+        }
+        let mut buf = vec![0; len];
+        self.stream.read_exact(&mut buf).await?;
+        let leader_slot = LeaderSlot::decode(&buf)?;
+        self.cursor = leader_slot.slot() + 1;
+        Ok(Some(leader_slot))
+    }
+}
+
+/// Read-only follower of a leader. Replays the slots of a `LedgerFeed` on top
+/// of its own storage, e.g. to serve RPC, and never produces a block.
+pub struct Replica {
+    storage: RollupStorage,
+    validator_settings: Settings,
+}
+
+impl Replica {
+    /// The ledger path must hold the genesis of the leader.
+    pub fn new_with_config(config: GlobalConfig) -> Result<Self> {
+        let mut storage = RollupStorage::new(config)?;
+        storage.init()?;
+        Ok(Self::new(storage))
+    }
+
+    /// Follows the leader from an initialized storage, e.g. bootstrapped from
+    /// its snapshots.
+    pub fn new(storage: RollupStorage) -> Self {
+        Self {
+            storage,
+            validator_settings: Default::default(),
+        }
+    }
+
+    /// Stores a slot of the leader, then replays every full slot chaining to
+    /// the replayed ones, and roots the slot the leader finalized. Returns the
+    /// replayed slots.
+    pub fn ingest(&mut self, leader_slot: LeaderSlot) -> Result<Vec<Slot>> {
+        let root = leader_slot.root();
+        self.storage.insert_leader_slot(leader_slot)?;
+        let mut replayed = vec![];
+        while let Some(slot) = self.storage.next_replay_slot()? {
+            if let Err(e) = self.verify_slot(slot) {
+                log::warn!("slot {slot} of the leader failed verification: {e}");
+                self.storage
+                    .blockstore()
+                    .set_dead_slot(slot)
+                    .map_err(igloo_storage::Error::from)?;
+                return Err(e);
+            }
+            self.storage.replay_slot(slot)?;
+            replayed.push(slot);
+        }
+        self.storage.set_leader_root(root)?;
+        Ok(replayed)
+    }
+
+    /// Ingests the slots of `feed` until it is over, returns the root then.
+    pub async fn follow<F: LedgerFeed + Send>(&mut self, feed: &mut F) -> Result<Slot> {
+        while let Some(leader_slot) = feed
+            .next_slot()
+            .await
+            .map_err(|e| Error::FetchFeedSlotError(e.to_string()))?
+        {
+            self.ingest(leader_slot)?;
+        }
+        Ok(self.storage.get_root())
+    }
+
+    pub fn storage(&self) -> &RollupStorage {
+        &self.storage
+    }
+
+    /// Mutable storage, e.g. to start the RPC service on top of it.
+    pub fn storage_mut(&mut self) -> &mut RollupStorage {
+        &mut self.storage
+    }
+
+    pub async fn close(self) -> Result<()> {
+        self.storage.close().await?;
+        Ok(())
+    }
+
+    /// Checks the transactions of the slot against the bank of its parent.
+    fn verify_slot(&self, slot: Slot) -> Result<()> {
+        let entries = self
+            .storage
+            .blockstore()
+            .get_slot_entries(slot, 0)
+            .map_err(igloo_storage::Error::from)?;
+        let verifier = BankVerifier::new(
+            self.storage.replay_parent(slot)?,
+            self.validator_settings.clone(),
+        );
+        for entry in entries.iter() {
+            verifier.entry_sanity_check(entry, TransactionVerificationMode::FullVerification)?;
+        }
+        Ok(())
+    }
+}
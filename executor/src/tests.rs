@@ -1,13 +1,19 @@
 use anyhow::Result;
 use igloo_storage::{
+    broadcast::{read_leader_slot, FeedFormat},
     config::GlobalConfig,
     init::default::{DEFAULT_MINT_LAMPORTS, DEFAULT_VALIDATOR_LAMPORTS},
+    replica::LeaderSlot,
 };
 use solana_sdk::{
-    signature::Keypair, signer::Signer, system_transaction, transaction::SanitizedTransaction,
+    hash::Hash, signature::Keypair, signer::Signer, system_transaction,
+    transaction::SanitizedTransaction,
 };
+use std::{collections::HashSet, fs::File, time::Duration};
 
-use crate::{BlockPayload, Error, Executor};
+use crate::{
+    BlockPayload, Deposit, Error, Executor, FileLedgerFeed, LedgerFeed, Replica, TcpLedgerFeed,
+};
 
 #[tokio::test]
 async fn engine_basic_process_works() -> Result<()> {
@@ -48,6 +54,7 @@ async fn engine_basic_process_works() -> Result<()> {
         ),
     ];
     let block_payload = BlockPayload {
+        deposits: vec![],
         transactions: vec![raw_txs
             .clone()
             .into_iter()
@@ -107,6 +114,7 @@ async fn commit_empty_block_should_fail() -> Result<()> {
     let mut engine = Executor::new_for_test(&ledger_path)?;
 
     let block_payload = BlockPayload {
+        deposits: vec![],
         transactions: vec![],
     };
 
@@ -121,3 +129,280 @@ async fn commit_empty_block_should_fail() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn deposits_execute_first() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut engine = Executor::new_for_test(&ledger_path)?;
+    let keypairs = engine.storage()?.keypairs().clone();
+
+    // the mint holds the bridged supply
+    let escrow = keypairs.mint_keypair.as_ref().unwrap().clone();
+    let charlie = Keypair::new();
+    let dave = Keypair::new().pubkey();
+
+    const DEPOSIT: u64 = 10000000;
+    const TO_DAVE: u64 = 1000000;
+    let deposit = Deposit {
+        source_hash: Hash::new_unique(),
+        from: Keypair::new().pubkey(),
+        to: charlie.pubkey(),
+        amount: DEPOSIT,
+    };
+    let too_large = Deposit {
+        source_hash: Hash::new_unique(),
+        from: Keypair::new().pubkey(),
+        to: dave,
+        amount: DEFAULT_MINT_LAMPORTS + 1,
+    };
+
+    // charlie spends the deposit in the same block
+    let transfer = system_transaction::transfer(
+        &charlie,
+        &dave,
+        TO_DAVE,
+        engine.storage()?.current_bank().last_blockhash(),
+    );
+    let block_payload = BlockPayload::new(vec![SanitizedTransaction::from_transaction_for_tests(
+        transfer,
+    )])
+    .with_deposits(vec![deposit.clone(), too_large.clone()]);
+
+    let result = engine.new_block(block_payload.clone()).await;
+    assert!(matches!(result, Err(Error::DepositEscrowNotSet)));
+
+    engine.set_deposit_escrow(escrow.clone());
+    let info = engine.new_block(block_payload).await?;
+    // deposits are charged no fee
+    assert_eq!(
+        engine.storage()?.balance(&escrow.pubkey()),
+        DEFAULT_MINT_LAMPORTS - DEPOSIT
+    );
+    assert_eq!(engine.storage()?.balance(&dave), TO_DAVE);
+    assert_eq!(
+        engine.storage()?.balance(&charlie.pubkey()),
+        DEPOSIT - TO_DAVE
+    );
+
+    // deposits are the first entries of the block, their receipts are kept in the history
+    let blockstore = engine.storage()?.blockstore();
+    let entries = blockstore.get_slot_entries(info.head.slot, 0)?;
+    assert_eq!(entries[0].transactions.len(), 1);
+    assert_eq!(entries[1].transactions.len(), 1);
+    assert_eq!(entries[2].transactions.len(), 1);
+    let slots = HashSet::from([info.head.slot]);
+    let mut statuses = vec![];
+    for transaction in entries[..2].iter().flat_map(|entry| &entry.transactions) {
+        let status = loop {
+            if let Some((_, meta)) =
+                blockstore.get_transaction_status(transaction.signatures[0], &slots)?
+            {
+                break meta;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        statuses.push(status);
+    }
+    assert!(statuses[0].status.is_ok());
+    assert_eq!(statuses[0].post_balances[1], DEPOSIT);
+    assert!(statuses[1].status.is_err());
+
+    // a block of deposits only
+    let block_payload = BlockPayload::default().with_deposits(vec![Deposit {
+        source_hash: Hash::new_unique(),
+        from: Keypair::new().pubkey(),
+        to: dave,
+        amount: DEPOSIT,
+    }]);
+    engine.new_block(block_payload).await?;
+    assert_eq!(engine.storage()?.balance(&dave), TO_DAVE + DEPOSIT);
+
+    engine.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn replica_replays_deposits() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut engine = Executor::new_for_test(&ledger_path)?;
+    let keypairs = engine.storage()?.keypairs().clone();
+    let escrow = keypairs.mint_keypair.as_ref().unwrap().clone();
+    engine.set_deposit_escrow(escrow.clone());
+
+    const DEPOSIT: u64 = 10000000;
+    let charlie = Keypair::new().pubkey();
+    let deposit = Deposit {
+        source_hash: Hash::new_unique(),
+        from: Keypair::new().pubkey(),
+        to: charlie,
+        amount: DEPOSIT,
+    };
+    // the same deposit twice is still two transfers
+    let block_payload = BlockPayload::default().with_deposits(vec![
+        deposit.clone(),
+        Deposit {
+            source_hash: Hash::new_unique(),
+            ..deposit
+        },
+    ]);
+    engine.new_block(block_payload).await?;
+
+    let replica_path = tempfile::tempdir()?.into_path();
+    std::fs::copy(
+        ledger_path.join("genesis.bin"),
+        replica_path.join("genesis.bin"),
+    )?;
+    let mut config = GlobalConfig::new(&replica_path)?;
+    config.keypairs = keypairs;
+    let mut replica = Replica::new_with_config(config)?;
+    let blockstore = engine.storage()?.blockstore();
+    let leader_slot = read_leader_slot(blockstore, 1, 0, FeedFormat::Shreds)?;
+    assert_eq!(replica.ingest(leader_slot)?, vec![1]);
+
+    assert_eq!(replica.storage().balance(&charlie), 2 * DEPOSIT);
+    assert_eq!(
+        replica.storage().balance(&escrow.pubkey()),
+        DEFAULT_MINT_LAMPORTS - 2 * DEPOSIT
+    );
+    assert_eq!(
+        replica.storage().get_bank(1)?.hash(),
+        engine.storage()?.get_bank(1)?.hash()
+    );
+
+    replica.close().await?;
+    engine.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn replica_follows_leader() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut engine = Executor::new_for_test(&ledger_path)?;
+    let keypairs = engine.storage()?.keypairs().clone();
+    let alice = keypairs.mint_keypair.as_ref().unwrap().clone();
+    let charlie = Keypair::new().pubkey();
+
+    const TO_CHARLIE: u64 = 2000000;
+    let transfer = system_transaction::transfer(
+        &alice,
+        &charlie,
+        TO_CHARLIE,
+        engine.storage()?.current_bank().last_blockhash(),
+    );
+    engine
+        .new_block(BlockPayload::new(vec![
+            SanitizedTransaction::from_transaction_for_tests(transfer),
+        ]))
+        .await?;
+    engine.new_block(BlockPayload::new(vec![])).await?;
+    engine.finalize(1)?;
+
+    // the leader exports its slots to a file feed
+    let feed_path = ledger_path.join("leader.feed");
+    let mut feed = File::create(&feed_path)?;
+    let blockstore = engine.storage()?.blockstore();
+    for slot in 1..=2 {
+        read_leader_slot(blockstore, slot, 1, FeedFormat::Shreds)?.write_to(&mut feed)?;
+    }
+    drop(feed);
+
+    let replica_path = tempfile::tempdir()?.into_path();
+    std::fs::copy(
+        ledger_path.join("genesis.bin"),
+        replica_path.join("genesis.bin"),
+    )?;
+    let mut config = GlobalConfig::new(&replica_path)?;
+    config.keypairs = keypairs;
+    let mut replica = Replica::new_with_config(config)?;
+    let root = replica
+        .follow(&mut FileLedgerFeed::open(&feed_path)?)
+        .await?;
+    // slot 2 is replayed but only rooted once the leader finalizes it
+    assert_eq!(root, 1);
+    assert_eq!(replica.storage().current_height(), 2);
+    assert_eq!(replica.storage().balance(&charlie), TO_CHARLIE);
+    assert_eq!(
+        replica.storage().current_bank().hash(),
+        engine.storage()?.get_bank(2)?.hash()
+    );
+
+    // a slot with an invalid transaction is not replayed
+    let mut transfer = system_transaction::transfer(
+        &alice,
+        &charlie,
+        TO_CHARLIE,
+        engine.storage()?.current_bank().last_blockhash(),
+    );
+    transfer.signatures[0] = Default::default();
+    engine.storage_mut()?.bump()?;
+    let slot = engine.storage()?.current_height();
+    let parent = engine.storage()?.current_bank().parent_slot();
+    let mut entries = blockstore.get_slot_entries(2, 0)?;
+    entries[0].transactions = vec![transfer.into()];
+    let result = replica.ingest(LeaderSlot::Entries {
+        slot,
+        parent,
+        bank_hash: Hash::default(),
+        root: 1,
+        entries,
+    });
+    assert!(matches!(result, Err(Error::ValidatorError(_))));
+    assert!(replica.storage().blockstore().is_dead(slot));
+    assert_eq!(replica.storage().get_root(), 1);
+    assert_eq!(replica.storage().current_height(), 2);
+
+    replica.close().await?;
+    engine.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn replica_follows_leader_broadcast() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut config = GlobalConfig::new_temp(&ledger_path)?;
+    config.storage.broadcast_addr = Some("127.0.0.1:0".parse()?);
+    let mut engine = Executor::new_with_config(config)?;
+    let keypairs = engine.storage()?.keypairs().clone();
+    let broadcast_addr = engine.storage()?.broadcast_addr().unwrap();
+    for _ in 0..3 {
+        engine.new_block(BlockPayload::new(vec![])).await?;
+    }
+
+    let replica_path = tempfile::tempdir()?.into_path();
+    std::fs::copy(
+        ledger_path.join("genesis.bin"),
+        replica_path.join("genesis.bin"),
+    )?;
+    let mut config = GlobalConfig::new(&replica_path)?;
+    config.keypairs = keypairs;
+    let mut replica = Replica::new_with_config(config)?;
+
+    let mut feed = TcpLedgerFeed::connect(broadcast_addr, 1, FeedFormat::Shreds).await?;
+    let leader_slot = feed.next_slot().await?.unwrap();
+    assert_eq!(replica.ingest(leader_slot)?, vec![1]);
+    assert_eq!(feed.cursor(), 2);
+    assert_eq!(replica.storage().get_root(), 0);
+
+    // resumed from the cursor after a disconnection, the slots carry the
+    // root finalized by the leader
+    engine.finalize(3)?;
+    let mut feed =
+        TcpLedgerFeed::connect(broadcast_addr, feed.cursor(), FeedFormat::Entries).await?;
+    engine.new_block(BlockPayload::new(vec![])).await?;
+    for slot in 2..=4 {
+        assert_eq!(
+            replica.ingest(feed.next_slot().await?.unwrap())?,
+            vec![slot]
+        );
+    }
+    assert_eq!(
+        replica.storage().current_bank().hash(),
+        engine.storage()?.get_bank(4)?.hash()
+    );
+
+    // the feed is over once the leader closes
+    engine.close().await?;
+    assert_eq!(replica.follow(&mut feed).await?, 3);
+    replica.close().await?;
+    Ok(())
+}
@@ -10,6 +10,17 @@ pub trait InstantDerive {
     /// Try to derive a new block from the L1 block, return `None` if
     ///  there is no new block to derive.
     async fn get_new_block(&mut self) -> Result<Option<Self::L1Info>, Self::Error>;
+
+    /// Latest finalized L1 head, return `None` if the finality is not known yet.
+    async fn get_finalized(
+        &mut self,
+    ) -> Result<Option<<Self::L1Info as L1BlockInfo<Self::P>>::L1Head>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Continue deriving after `head`, e.g. the last L1 block derived before a restart.
+    /// Derivations that can not seek ignore it.
+    fn resume_after(&mut self, _head: <Self::L1Info as L1BlockInfo<Self::P>>::L1Head) {}
 }
 
 /// DaDerive is a trait that can be implemented by a struct to derive blocks
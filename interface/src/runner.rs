@@ -5,6 +5,8 @@ use crate::{
 
 pub trait Runner<E: Engine, ID: InstantDerive, DD: DaDerive> {
     type Error: std::fmt::Display;
+    /// L2 head along with the L1 origin it was derived from.
+    type Head;
 
     fn register_instant(&mut self, derive: ID);
 
@@ -13,4 +15,13 @@ pub trait Runner<E: Engine, ID: InstantDerive, DD: DaDerive> {
     fn get_engine(&self) -> &E;
 
     async fn advance(&mut self) -> Result<(), Self::Error>;
+
+    /// Latest block, produced from the instant derivation and not confirmed by DA yet.
+    fn unsafe_head(&self) -> Option<&Self::Head>;
+
+    /// Latest block confirmed by the DA derivation.
+    fn safe_head(&self) -> Option<&Self::Head>;
+
+    /// Latest safe block whose L1 origin is finalized, it can not be reorged anymore.
+    fn finalized_head(&self) -> Option<&Self::Head>;
 }
@@ -7,6 +7,9 @@ pub enum Error {
     #[error("Init json rpc error: {0}")]
     InitJsonRpc(String),
 
+    #[error("Init pubsub error: {0}")]
+    InitPubSub(String),
+
     #[error(transparent)]
     IglooStorage(#[from] igloo_storage::Error),
 }
@@ -1,7 +1,13 @@
 //! The `rpc` module implements the Solana RPC interface.
 
-use super::cache::LargestAccountsCache;
+use super::{
+    cache::LargestAccountsCache,
+    faucet::{FaucetConfig, LocalFaucet},
+    output_root,
+    send_cache::{SendTransactionCache, SendTransactionCacheStats, SendTransactionFilter},
+};
 use crate::{filter::filter_allows, parsed_token_accounts::*};
+use igloo_storage::state::{smt::hash_leaf, SparseMerkleTree, StateCommitment};
 use jsonrpc_core::ErrorCode;
 use solana_program::vote::state::VoteState;
 use solana_rpc_client_api::request::{
@@ -67,6 +73,7 @@ use {
         exit::Exit,
         feature_set,
         hash::Hash,
+        keccak,
         message::SanitizedMessage,
         pubkey::{Pubkey, PUBKEY_BYTES},
         signature::{Keypair, Signature, Signer},
@@ -101,7 +108,7 @@ use {
         str::FromStr,
         sync::{
             atomic::{AtomicBool, AtomicU64, Ordering},
-            Arc, RwLock,
+            Arc, Mutex, RwLock,
         },
         time::Duration,
     },
@@ -127,8 +134,10 @@ fn is_finalized(bank: &Bank, blockstore: &Blockstore, slot: Slot) -> bool {
 pub struct JsonRpcConfig {
     pub enable_rpc_transaction_history: bool,
     pub enable_extended_tx_metadata_storage: bool,
-    // TODO: should replace to new faucet implementation
+    /// External solana-faucet daemon, only used if no local faucet is enabled.
     pub faucet_addr: Option<SocketAddr>,
+    /// Enables the in-process faucet backed by the mint keypair.
+    pub faucet: Option<FaucetConfig>,
     pub rpc_bigtable_config: Option<RpcBigtableConfig>,
     pub max_multiple_accounts: Option<usize>,
     pub account_indexes: AccountSecondaryIndexes,
@@ -137,6 +146,8 @@ pub struct JsonRpcConfig {
     pub full_api: bool,
     pub rpc_scan_and_fix_roots: bool,
     pub max_request_body_size: Option<usize>,
+    /// Native program whose PDAs hold withdrawal requests, proven by `soon_getWithdrawalProof`.
+    pub withdrawal_program_id: Option<Pubkey>,
 }
 
 impl JsonRpcConfig {
@@ -174,22 +185,25 @@ impl Default for RpcBigtableConfig {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OutputAtBlockResp {
-    version: String,
-    output_root: String,
-    state_root: String,
-    withdrawal_root: String,
-    block_hash: String,
+    pub version: String,
+    pub output_root: String,
+    pub state_root: String,
+    pub withdrawal_root: String,
+    pub block_hash: String,
 }
 
 /// SoonGetProofResp follows the format of eth_getProof response, but with specific soon info.
 /// It contains follow fields:
-///     * the merkle root of all solana account state.
+///     * the sparse merkle root of all solana account state, kept by the storage.
 ///     * merkle proof for withdrawal native program address (calling WNP below).
 ///     * When one raise a withdrawal request, the WNP will derive a new pda account.
 ///       This api also generates root and proof of this pda in storage-hash way.
 ///       So api also contains:
 ///         * merkle root of WNP storage hash
 ///         * proof for pda address in WNP's storage root
+///
+/// `psr` and `pwr` are the leaves the proofs start from: the WNP leaf in the state tree and the
+/// pda leaf in the WNP storage tree, see `igloo_storage::state::smt::hash_leaf`.
 #[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct SoonGetWithdrawalProofResp {
@@ -213,7 +227,11 @@ pub struct JsonRpcRequestProcessor {
     bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
     largest_accounts_cache: Arc<RwLock<LargestAccountsCache>>,
     max_complete_transaction_status_slot: Arc<AtomicU64>,
+    confirmed_slot: Arc<AtomicU64>,
     prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+    faucet: Option<Arc<LocalFaucet>>,
+    send_transaction_cache: Arc<Mutex<SendTransactionCache>>,
+    state_commitment: Option<Arc<Mutex<StateCommitment>>>,
 }
 impl Metadata for JsonRpcRequestProcessor {}
 
@@ -244,10 +262,26 @@ impl JsonRpcRequestProcessor {
         Ok(bank)
     }
 
-    #[allow(deprecated)]
-    fn bank(&self, _commitment: Option<CommitmentConfig>) -> Arc<Bank> {
-        // NOTE: ingore commitment, always return the working bank
-        self.bank_forks.read().unwrap().working_bank()
+    fn bank(&self, commitment: Option<CommitmentConfig>) -> Arc<Bank> {
+        commitment_bank(
+            &self.bank_forks,
+            &self.confirmed_slot,
+            commitment.unwrap_or_default(),
+        )
+    }
+
+    fn confirmation_status(&self, slot: Slot) -> TransactionConfirmationStatus {
+        if slot <= self.bank_forks.read().unwrap().root() {
+            TransactionConfirmationStatus::Finalized
+        } else if slot <= self.confirmed_slot.load(Ordering::Relaxed) {
+            TransactionConfirmationStatus::Confirmed
+        } else {
+            TransactionConfirmationStatus::Processed
+        }
+    }
+
+    pub fn send_transaction_cache_stats(&self) -> SendTransactionCacheStats {
+        self.send_transaction_cache.lock().unwrap().stats()
     }
 
     fn genesis_creation_time(&self) -> UnixTimestamp {
@@ -266,7 +300,10 @@ impl JsonRpcRequestProcessor {
         bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
         largest_accounts_cache: Arc<RwLock<LargestAccountsCache>>,
         max_complete_transaction_status_slot: Arc<AtomicU64>,
+        confirmed_slot: Arc<AtomicU64>,
         prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+        faucet: Option<Arc<LocalFaucet>>,
+        state_commitment: Option<Arc<Mutex<StateCommitment>>>,
     ) -> Self {
         Self {
             config,
@@ -279,7 +316,11 @@ impl JsonRpcRequestProcessor {
             bigtable_ledger_storage,
             largest_accounts_cache,
             max_complete_transaction_status_slot,
+            confirmed_slot,
             prioritization_fee_cache,
+            faucet,
+            send_transaction_cache: Arc::new(Mutex::new(SendTransactionCache::default())),
+            state_commitment,
         }
     }
 
@@ -301,7 +342,11 @@ impl JsonRpcRequestProcessor {
             bigtable_ledger_storage: None,
             largest_accounts_cache: Arc::new(RwLock::new(LargestAccountsCache::new(30))),
             max_complete_transaction_status_slot: Arc::new(AtomicU64::default()),
+            confirmed_slot: Arc::new(AtomicU64::default()),
             prioritization_fee_cache: Arc::new(PrioritizationFeeCache::default()),
+            faucet: None,
+            send_transaction_cache: Arc::new(Mutex::new(SendTransactionCache::default())),
+            state_commitment: None,
         }
     }
 
@@ -720,7 +765,11 @@ impl JsonRpcRequestProcessor {
     }
 
     fn minimum_ledger_slot(&self) -> Result<Slot> {
-        match self.blockstore.slot_meta_iterator(0) {
+        // slots up to the lowest cleanup slot are purged, or being purged
+        match self
+            .blockstore
+            .slot_meta_iterator(self.blockstore.lowest_cleanup_slot())
+        {
             Ok(mut metas) => match metas.next() {
                 Some((slot, _meta)) => Ok(slot),
                 None => Err(Error::invalid_request()),
@@ -1225,7 +1274,7 @@ impl JsonRpcRequestProcessor {
         let search_transaction_history = config
             .map(|x| x.search_transaction_history)
             .unwrap_or(false);
-        let bank = self.bank(None);
+        let bank = self.bank(Some(CommitmentConfig::processed()));
 
         if search_transaction_history && !self.config.enable_rpc_transaction_history {
             return Err(RpcCustomError::TransactionHistoryNotAvailable.into());
@@ -1282,7 +1331,7 @@ impl JsonRpcRequestProcessor {
             status,
             confirmations: None,
             err,
-            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+            confirmation_status: Some(self.confirmation_status(slot)),
         })
     }
 
@@ -1300,7 +1349,7 @@ impl JsonRpcRequestProcessor {
         check_is_at_least_confirmed(commitment)?;
 
         if self.config.enable_rpc_transaction_history {
-            let confirmed_bank = self.bank(None);
+            let confirmed_bank = self.bank(Some(CommitmentConfig::confirmed()));
             let confirmed_transaction = if commitment.is_confirmed() {
                 let highest_confirmed_slot = confirmed_bank.slot();
                 self.blockstore
@@ -1397,7 +1446,7 @@ impl JsonRpcRequestProcessor {
                     .into_iter()
                     .map(|x| {
                         let mut item: RpcConfirmedTransactionStatusWithSignature = x.into();
-                        item.confirmation_status = Some(TransactionConfirmationStatus::Finalized);
+                        item.confirmation_status = Some(self.confirmation_status(item.slot));
                         if item.block_time.is_none() {
                             item.block_time = self
                                 .bank_forks
@@ -1915,6 +1964,105 @@ impl JsonRpcRequestProcessor {
             })
             .collect())
     }
+
+    /// Outputs are only defined for complete blocks still held by the bank forks, the state
+    /// root is read from the commitment kept by the storage.
+    fn output_bank(&self, slot: Slot) -> Result<(Arc<Bank>, keccak::Hash)> {
+        let state_commitment = self.state_commitment.as_ref().ok_or_else(|| Error {
+            code: ErrorCode::InvalidRequest,
+            message: "State commitment is not available".to_string(),
+            data: None,
+        })?;
+        let bank = self
+            .bank_forks
+            .read()
+            .unwrap()
+            .get(slot)
+            .filter(|bank| bank.is_frozen())
+            .ok_or(RpcCustomError::BlockNotAvailable { slot })?;
+        let state_root = state_commitment
+            .lock()
+            .unwrap()
+            .state_root(slot)
+            .ok_or(RpcCustomError::BlockNotAvailable { slot })?;
+        Ok((bank, state_root))
+    }
+
+    /// Withdrawal PDAs of the bank, empty if no withdrawal program is configured.
+    fn withdrawal_tree(&self, bank: &Bank) -> Result<SparseMerkleTree> {
+        let Some(withdrawal_program) = self.config.withdrawal_program_id else {
+            return Ok(SparseMerkleTree::default());
+        };
+        let withdrawals =
+            self.get_filtered_program_accounts(bank, &withdrawal_program, vec![], false)?;
+        Ok(output_root::withdrawal_tree(withdrawals))
+    }
+
+    pub fn output_at_block(&self, slot: Slot) -> Result<OutputAtBlockResp> {
+        let (bank, state_root) = self.output_bank(slot)?;
+        let withdrawal_root = self.withdrawal_tree(&bank)?.root();
+        let block_hash = bank.last_blockhash();
+        Ok(OutputAtBlockResp {
+            version: to_hex(&output_root::OUTPUT_VERSION),
+            output_root: to_hex(
+                output_root::output_root(&state_root, &withdrawal_root, &block_hash).as_ref(),
+            ),
+            state_root: to_hex(state_root.as_ref()),
+            withdrawal_root: to_hex(withdrawal_root.as_ref()),
+            block_hash: to_hex(block_hash.as_ref()),
+        })
+    }
+
+    pub fn get_withdrawal_proof(
+        &self,
+        pda: &Pubkey,
+        slot: Slot,
+    ) -> Result<SoonGetWithdrawalProofResp> {
+        let Some(withdrawal_program) = self.config.withdrawal_program_id else {
+            return Err(Error {
+                code: ErrorCode::InvalidRequest,
+                message: "Withdrawal program is not configured".to_string(),
+                data: None,
+            });
+        };
+        let (bank, state_root) = self.output_bank(slot)?;
+        let not_found =
+            || Error::invalid_params(format!("Withdrawal {pda} not found at slot {slot}"));
+        let withdrawals = self.withdrawal_tree(&bank)?;
+        let withdrawal_proof = withdrawals.proof(pda).ok_or_else(not_found)?;
+        let state_proof = self
+            .state_commitment
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .prove(&withdrawal_program, slot)
+            .map_err(|e| Error {
+                code: ErrorCode::InternalError,
+                message: format!("Failed to prove withdrawal program {withdrawal_program}: {e}"),
+                data: None,
+            })?
+            .ok_or_else(|| {
+                Error::invalid_params(format!(
+                    "Withdrawal program {withdrawal_program} not found at slot {slot}"
+                ))
+            })?;
+        let to_hex_vec = |hashes: &[keccak::Hash]| -> Vec<String> {
+            hashes.iter().map(|hash| to_hex(hash.as_ref())).collect()
+        };
+        Ok(SoonGetWithdrawalProofResp {
+            state_root: to_hex(state_root.as_ref()),
+            withdrawal_root: to_hex(withdrawals.root().as_ref()),
+            state_proof: to_hex_vec(&state_proof.siblings),
+            withdrawal_proof: to_hex_vec(&withdrawal_proof.siblings),
+            psr: to_hex(hash_leaf(&withdrawal_program, &state_proof.account_hash).as_ref()),
+            pwr: to_hex(hash_leaf(pda, &withdrawal_proof.account_hash).as_ref()),
+        })
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
 }
 
 fn err_transfer_fn_with_loc<E: std::error::Error>(
@@ -1927,7 +2075,7 @@ fn err_transfer_fn_with_loc<E: std::error::Error>(
     })
 }
 
-fn optimize_filters(filters: &mut [RpcFilterType]) {
+pub(crate) fn optimize_filters(filters: &mut [RpcFilterType]) {
     filters.iter_mut().for_each(|filter_type| {
         if let RpcFilterType::Memcmp(compare) = filter_type {
             if let Err(err) = compare.convert_to_raw_bytes() {
@@ -1954,7 +2102,7 @@ fn verify_transaction(
     Ok(())
 }
 
-fn verify_filter(input: &RpcFilterType) -> Result<()> {
+pub(crate) fn verify_filter(input: &RpcFilterType) -> Result<()> {
     input
         .verify()
         .map_err(|e| Error::invalid_params(format!("Invalid param: {e:?}")))
@@ -1972,7 +2120,7 @@ fn verify_hash(input: &str) -> Result<Hash> {
         .map_err(|e| Error::invalid_params(format!("Invalid param: {e:?}")))
 }
 
-fn verify_signature(input: &str) -> Result<Signature> {
+pub(crate) fn verify_signature(input: &str) -> Result<Signature> {
     input
         .parse()
         .map_err(|e| Error::invalid_params(format!("Invalid param: {e:?}")))
@@ -2014,6 +2162,29 @@ fn verify_and_parse_signatures_for_address_params(
     Ok((address, before, until, limit))
 }
 
+/// Maps the commitment onto rollup banks: `processed` is the working bank, `confirmed` the last
+/// slot confirmed by the storage and `finalized` the root of the bank forks.
+#[allow(deprecated)]
+pub(crate) fn commitment_bank(
+    bank_forks: &RwLock<BankForks>,
+    confirmed_slot: &AtomicU64,
+    commitment: CommitmentConfig,
+) -> Arc<Bank> {
+    let r_bank_forks = bank_forks.read().unwrap();
+    if commitment.is_finalized() {
+        r_bank_forks.root_bank()
+    } else if commitment.is_confirmed() {
+        // the confirmed bank may be pruned or reorged away meanwhile, fall back to the root
+        let slot = confirmed_slot.load(Ordering::Relaxed);
+        r_bank_forks
+            .get(slot)
+            .filter(|bank| bank.slot() > r_bank_forks.root())
+            .unwrap_or_else(|| r_bank_forks.root_bank())
+    } else {
+        r_bank_forks.working_bank()
+    }
+}
+
 pub(crate) fn check_is_at_least_confirmed(commitment: CommitmentConfig) -> Result<()> {
     if !commitment.is_at_least_confirmed() {
         return Err(Error::invalid_params(
@@ -2023,7 +2194,7 @@ pub(crate) fn check_is_at_least_confirmed(commitment: CommitmentConfig) -> Resul
     Ok(())
 }
 
-fn get_encoded_account(
+pub(crate) fn get_encoded_account(
     bank: &Bank,
     pubkey: &Pubkey,
     encoding: UiAccountEncoding,
@@ -2050,7 +2221,7 @@ fn get_encoded_account(
     }
 }
 
-fn encode_account<T: ReadableAccount>(
+pub(crate) fn encode_account<T: ReadableAccount>(
     account: &T,
     pubkey: &Pubkey,
     encoding: UiAccountEncoding,
@@ -2209,12 +2380,29 @@ fn _send_transaction(
     meta: JsonRpcRequestProcessor,
     signature: Signature,
     transaction: SanitizedTransaction,
-    _last_valid_block_height: u64,
-    _durable_nonce_info: Option<(Pubkey, Hash)>,
+    last_valid_block_height: u64,
+    durable_nonce_info: Option<(Pubkey, Hash)>,
 ) -> Result<String> {
-    // TODO: implement a transaction cache for RPC, to remove duplicated and invalid transactions
-    // let transaction_info =
-    //     TransactionInfo::new(transaction, last_valid_block_height, durable_nonce_info);
+    let bank = meta.bank(Some(CommitmentConfig::processed()));
+    let filtered = meta.send_transaction_cache.lock().unwrap().try_insert(
+        &bank,
+        signature,
+        last_valid_block_height,
+        durable_nonce_info,
+    );
+    match filtered {
+        Ok(()) => {}
+        // retries of a known signature are answered without queueing the transaction again
+        Err(SendTransactionFilter::Pending | SendTransactionFilter::AlreadyProcessed) => {
+            inc_new_counter_info!("rpc-send-tx_filtered-duplicate", 1);
+            return Ok(signature.to_string());
+        }
+        Err(err @ SendTransactionFilter::Expired) => {
+            inc_new_counter_info!("rpc-send-tx_filtered-expired", 1);
+            return Err(Error::invalid_params(err.to_string()));
+        }
+    }
+
     meta.tx_channel
         .0
         .send(transaction)
@@ -3092,6 +3280,17 @@ pub mod rpc_full {
             meta: Self::Metadata,
             pubkey_strs: Option<Vec<String>>,
         ) -> Result<Vec<RpcPrioritizationFee>>;
+
+        #[rpc(meta, name = "soon_outputAtBlock")]
+        fn output_at_block(&self, meta: Self::Metadata, slot: Slot) -> Result<OutputAtBlockResp>;
+
+        #[rpc(meta, name = "soon_getWithdrawalProof")]
+        fn get_withdrawal_proof(
+            &self,
+            meta: Self::Metadata,
+            pda_str: String,
+            slot: Slot,
+        ) -> Result<SoonGetWithdrawalProofResp>;
     }
 
     pub struct FullImpl;
@@ -3211,7 +3410,6 @@ pub mod rpc_full {
                 &config
             );
 
-            let faucet_addr = meta.config.faucet_addr.ok_or_else(Error::invalid_request)?;
             let pubkey = verify_pubkey(&pubkey_str)?;
 
             let config = config.unwrap_or_default();
@@ -3226,21 +3424,37 @@ pub mod rpc_full {
                 .get_blockhash_last_valid_block_height(&blockhash)
                 .unwrap_or(0);
 
-            let unsanitized_tx =
+            let unsanitized_tx = if let Some(faucet) = meta.faucet.as_ref() {
+                faucet
+                    .build_airdrop_transaction(&pubkey, lamports, blockhash)
+                    .map_err(|err| {
+                        info!("local faucet rejected airdrop: {}", err);
+                        Error::invalid_params(err.to_string())
+                    })?
+            } else {
+                let faucet_addr = meta.config.faucet_addr.ok_or_else(Error::invalid_request)?;
                 request_airdrop_transaction(&faucet_addr, &pubkey, lamports, blockhash).map_err(
                     |err| {
                         info!("request_airdrop_transaction failed: {:?}", err);
                         Error::internal_error()
                     },
-                )?;
-            let transaction = sanitize_transaction(
+                )?
+            };
+            let faucet = meta.faucet.clone();
+            let result = sanitize_transaction(
                 unsanitized_tx.into(),
                 bank.as_ref(),
                 bank.get_reserved_account_keys(),
-            )?;
-            let signature = *transaction.signature();
-
-            _send_transaction(meta, signature, transaction, last_valid_block_height, None)
+            )
+            .and_then(|transaction| {
+                let signature = *transaction.signature();
+                _send_transaction(meta, signature, transaction, last_valid_block_height, None)
+            });
+            if let (Err(_), Some(faucet)) = (&result, faucet) {
+                // the airdrop is not sent, it does not count against the faucet limits
+                faucet.refund(&pubkey, lamports);
+            }
+            result
         }
 
         fn send_transaction(
@@ -3661,6 +3875,25 @@ pub mod rpc_full {
                 .collect::<Result<Vec<_>>>()?;
             meta.get_recent_prioritization_fees(pubkeys)
         }
+
+        fn output_at_block(&self, meta: Self::Metadata, slot: Slot) -> Result<OutputAtBlockResp> {
+            debug!("output_at_block rpc request received: {:?}", slot);
+            meta.output_at_block(slot)
+        }
+
+        fn get_withdrawal_proof(
+            &self,
+            meta: Self::Metadata,
+            pda_str: String,
+            slot: Slot,
+        ) -> Result<SoonGetWithdrawalProofResp> {
+            debug!(
+                "get_withdrawal_proof rpc request received: {:?} {:?}",
+                pda_str, slot
+            );
+            let pda = verify_pubkey(&pda_str)?;
+            meta.get_withdrawal_proof(&pda, slot)
+        }
     }
 }
 
@@ -3975,6 +4208,8 @@ mod tests {
         blockstore: Arc<Blockstore>,
         bank_forks: Arc<RwLock<BankForks>>,
         max_complete_transaction_status_slot: Arc<AtomicU64>,
+        confirmed_slot: Arc<AtomicU64>,
+        state_commitment: Arc<Mutex<StateCommitment>>,
     }
 
     impl RpcHandler {
@@ -3998,6 +4233,15 @@ mod tests {
             let node_exit = create_node_exit(exit);
             // note that this means that slot 0 will always be considered complete
             let max_complete_transaction_status_slot = Arc::new(AtomicU64::new(0));
+            let confirmed_slot = Arc::new(AtomicU64::new(0));
+            let state_commitment = Arc::new(Mutex::new(
+                StateCommitment::open(
+                    &ledger_path.join("state"),
+                    &bank_forks.read().unwrap().root_bank(),
+                    16,
+                )
+                .unwrap(),
+            ));
 
             let meta = JsonRpcRequestProcessor::new(
                 config,
@@ -4010,7 +4254,10 @@ mod tests {
                 None,
                 Arc::new(RwLock::new(LargestAccountsCache::new(30))),
                 max_complete_transaction_status_slot.clone(),
+                confirmed_slot.clone(),
                 Arc::new(PrioritizationFeeCache::default()),
+                None,
+                Some(state_commitment.clone()),
             );
 
             let mut io = MetaIoHandler::default();
@@ -4026,6 +4273,8 @@ mod tests {
                 bank_forks,
                 blockstore,
                 max_complete_transaction_status_slot,
+                confirmed_slot,
+                state_commitment,
             }
         }
 
@@ -4272,6 +4521,71 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_rpc_bank_follows_commitment() {
+        let rpc = RpcHandler::start();
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let amount = rpc.working_bank().get_minimum_balance_for_rent_exemption(0);
+
+        // slot 0 is the root, slot 1 confirmed and slot 2 only processed
+        let bank1 = rpc.advance_bank_to_confirmed_slot(1);
+        let signature1 = bank1
+            .transfer(amount, &rpc.mint_keypair, &bob_pubkey)
+            .unwrap();
+        let bank2 = rpc.advance_bank_to_confirmed_slot(2);
+        let signature2 = bank2
+            .transfer(amount, &rpc.mint_keypair, &bob_pubkey)
+            .unwrap();
+        rpc.confirmed_slot.store(1, Ordering::Relaxed);
+
+        for (commitment, slot, balance) in [
+            ("finalized", 0, 0),
+            ("confirmed", 1, amount),
+            ("processed", 2, amount * 2),
+        ] {
+            let request = create_test_request(
+                "getBalance",
+                Some(json!([bob_pubkey.to_string(), {"commitment": commitment}])),
+            );
+            let result: RpcResponse<u64> = parse_success_result(rpc.handle_request_sync(request));
+            assert_eq!(result.context.slot, slot);
+            assert_eq!(result.value, balance);
+        }
+
+        // no commitment means finalized
+        let request = create_test_request("getBalance", Some(json!([bob_pubkey.to_string()])));
+        let result: RpcResponse<u64> = parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(result.context.slot, 0);
+
+        let request = create_test_request(
+            "getSignatureStatuses",
+            Some(json!([[signature1.to_string(), signature2.to_string()]])),
+        );
+        let result: RpcResponse<Vec<Option<TransactionStatus>>> =
+            parse_success_result(rpc.handle_request_sync(request));
+        let statuses = result
+            .value
+            .into_iter()
+            .map(|status| status.unwrap().confirmation_status.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                TransactionConfirmationStatus::Confirmed,
+                TransactionConfirmationStatus::Processed
+            ]
+        );
+
+        // a confirmed bank reorged away falls back to the root
+        rpc.confirmed_slot.store(3, Ordering::Relaxed);
+        let request = create_test_request(
+            "getBalance",
+            Some(json!([bob_pubkey.to_string(), {"commitment": "confirmed"}])),
+        );
+        let result: RpcResponse<u64> = parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(result.context.slot, 0);
+    }
+
     #[test]
     fn test_rpc_get_balance_via_client() {
         let genesis = create_genesis_config(20);
@@ -4396,6 +4710,28 @@ mod tests {
         assert_eq!(0, result);
     }
 
+    #[test]
+    fn test_rpc_ledger_slots_after_cleanup() {
+        let rpc = RpcHandler::start();
+        rpc.add_roots_to_blockstore(vec![0, 1, 3, 4, 8]);
+
+        // as done by the ledger cleanup of the storage
+        *rpc.blockstore.lowest_cleanup_slot.write().unwrap() = 2;
+        rpc.blockstore
+            .purge_slots(0, 2, solana_ledger::blockstore::PurgeType::Exact);
+
+        let request = create_test_request("minimumLedgerSlot", None);
+        let result: Slot = parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(result, 3);
+        // the parent of slot 3 is purged, its block is incomplete
+        let request = create_test_request("getFirstAvailableBlock", None);
+        let result: Slot = parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(result, 4);
+        let request = create_test_request("getBlocks", Some(json!([0u64])));
+        let result: Vec<Slot> = parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(result, vec![4, 8]);
+    }
+
     #[test]
     fn test_get_supply() {
         let rpc = RpcHandler::start();
@@ -5744,6 +6080,172 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_rpc_request_airdrop_with_local_faucet() {
+        let RpcHandler {
+            mut meta,
+            io,
+            mint_keypair,
+            ..
+        } = RpcHandler::start();
+        meta.faucet = Some(Arc::new(LocalFaucet::new(
+            Arc::new(mint_keypair.insecure_clone()),
+            FaucetConfig {
+                per_request_cap: Some(100),
+                ..FaucetConfig::default()
+            },
+        )));
+
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"requestAirdrop","params":["{bob_pubkey}", 50]}}"#
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let json: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        let signature: Signature = json["result"].as_str().unwrap().parse().unwrap();
+
+        let transaction = meta.tx_channel.1.try_recv().unwrap();
+        assert_eq!(*transaction.signature(), signature);
+        assert_eq!(transaction.message().fee_payer(), &mint_keypair.pubkey());
+
+        // above the per request cap
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"requestAirdrop","params":["{bob_pubkey}", 101]}}"#
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let json: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        assert_eq!(json["error"]["code"], ErrorCode::InvalidParams.code());
+        assert!(meta.tx_channel.1.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_rpc_rejected_airdrop_is_refunded() {
+        let mut rpc = RpcHandler::start();
+        rpc.meta.faucet = Some(Arc::new(LocalFaucet::new(
+            Arc::new(rpc.mint_keypair.insecure_clone()),
+            FaucetConfig {
+                per_request_cap: None,
+                per_address_cap: Some(100),
+                ..FaucetConfig::default()
+            },
+        )));
+        rpc.advance_bank_to_confirmed_slot(1);
+
+        // an unknown blockhash expires the airdrop before it is sent
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let request = create_test_request(
+            "requestAirdrop",
+            Some(json!([
+                bob_pubkey.to_string(),
+                100,
+                {"recentBlockhash": Hash::new_unique().to_string()}
+            ])),
+        );
+        let (code, _) = parse_failure_response(rpc.handle_request_sync(request));
+        assert_eq!(code, ErrorCode::InvalidParams.code());
+        assert!(rpc.meta.tx_channel.1.try_recv().is_err());
+
+        // the quota of the rejected airdrop is still available
+        let request =
+            create_test_request("requestAirdrop", Some(json!([bob_pubkey.to_string(), 100])));
+        let signature: Signature = parse_success_result::<String>(rpc.handle_request_sync(request))
+            .parse()
+            .unwrap();
+        let transaction = rpc.meta.tx_channel.1.try_recv().unwrap();
+        assert_eq!(*transaction.signature(), signature);
+    }
+
+    #[test]
+    fn test_rpc_output_at_block_and_withdrawal_proof() {
+        let withdrawal_program = Pubkey::new_unique();
+        let rpc = RpcHandler::start_with_config(JsonRpcConfig {
+            withdrawal_program_id: Some(withdrawal_program),
+            ..JsonRpcConfig::default()
+        });
+        let pda = Pubkey::new_unique();
+        let bank = rpc.advance_bank_to_confirmed_slot(1);
+        bank.store_account(
+            &withdrawal_program,
+            &AccountSharedData::new(1, 0, &solana_sdk::native_loader::id()),
+        );
+        bank.store_account(&pda, &AccountSharedData::new(10, 8, &withdrawal_program));
+
+        // the block is not complete yet
+        let request = create_test_request("soon_outputAtBlock", Some(json!([1])));
+        let response = parse_failure_response(rpc.handle_request_sync(request));
+        assert_eq!(
+            response,
+            (
+                JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+                "Block not available for slot 1".to_string()
+            )
+        );
+
+        // nor is its state committed by the storage
+        bank.freeze();
+        let request = create_test_request("soon_outputAtBlock", Some(json!([1])));
+        let (code, _) = parse_failure_response(rpc.handle_request_sync(request));
+        assert_eq!(code, JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE);
+
+        rpc.state_commitment.lock().unwrap().sync(&bank).unwrap();
+        let state_root = rpc.state_commitment.lock().unwrap().state_root(1).unwrap();
+        let withdrawals = output_root::withdrawal_tree([(pda, bank.get_account(&pda).unwrap())]);
+        let request = create_test_request("soon_outputAtBlock", Some(json!([1])));
+        let output: OutputAtBlockResp = parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(output.version, format!("0x{}", "00".repeat(32)));
+        assert_eq!(output.state_root, to_hex(state_root.as_ref()));
+        assert_eq!(output.withdrawal_root, to_hex(withdrawals.root().as_ref()));
+        assert_eq!(
+            output.output_root,
+            to_hex(
+                output_root::output_root(&state_root, &withdrawals.root(), &bank.last_blockhash())
+                    .as_ref()
+            )
+        );
+        assert_eq!(output.block_hash, to_hex(bank.last_blockhash().as_ref()));
+
+        let request =
+            create_test_request("soon_getWithdrawalProof", Some(json!([pda.to_string(), 1])));
+        let proof: SoonGetWithdrawalProofResp =
+            parse_success_result(rpc.handle_request_sync(request));
+        assert_eq!(proof.state_root, output.state_root);
+        assert_eq!(proof.withdrawal_root, output.withdrawal_root);
+        // the only withdrawal is the withdrawal root itself
+        assert!(proof.withdrawal_proof.is_empty());
+        assert_eq!(proof.pwr, proof.withdrawal_root);
+        let program_proof = rpc
+            .state_commitment
+            .lock()
+            .unwrap()
+            .prove(&withdrawal_program, 1)
+            .unwrap()
+            .unwrap();
+        assert!(program_proof.verify(&state_root));
+        assert!(!proof.state_proof.is_empty());
+        assert_eq!(
+            proof.state_proof,
+            program_proof
+                .siblings
+                .iter()
+                .map(|hash| to_hex(hash.as_ref()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            proof.psr,
+            to_hex(hash_leaf(&withdrawal_program, &program_proof.account_hash).as_ref())
+        );
+
+        // accounts not owned by the withdrawal program are not withdrawals
+        for pubkey in [Pubkey::new_unique(), withdrawal_program] {
+            let request = create_test_request(
+                "soon_getWithdrawalProof",
+                Some(json!([pubkey.to_string(), 1])),
+            );
+            let (code, _) = parse_failure_response(rpc.handle_request_sync(request));
+            assert_eq!(code, ErrorCode::InvalidParams.code());
+        }
+    }
+
     #[test]
     fn test_rpc_send_bad_tx() {
         let genesis = create_genesis_config(100);
@@ -5784,7 +6286,10 @@ mod tests {
             None,
             Arc::new(RwLock::new(LargestAccountsCache::new(30))),
             Arc::new(AtomicU64::default()),
+            Arc::new(AtomicU64::default()),
             Arc::new(PrioritizationFeeCache::default()),
+            None,
+            None,
         );
 
         let mut bad_transaction = system_transaction::transfer(
@@ -5873,6 +6378,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rpc_send_transaction_filters_duplicates() {
+        let RpcHandler {
+            meta,
+            io,
+            mint_keypair,
+            ..
+        } = RpcHandler::start();
+        let bank = meta.bank(Some(CommitmentConfig::processed()));
+
+        let transaction = system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            bank.get_minimum_balance_for_rent_exemption(0),
+            bank.last_blockhash(),
+        );
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"sendTransaction","params":["{}", {{"skipPreflight": true}}]}}"#,
+            bs58::encode(serialize(&transaction).unwrap()).into_string()
+        );
+        let expected = format!(
+            r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#,
+            transaction.signatures[0]
+        );
+
+        // wallet retries are answered with the same signature but queued once
+        for _ in 0..3 {
+            let res = io.handle_request_sync(&req, meta.clone());
+            assert_eq!(res, Some(expected.clone()));
+        }
+        assert!(meta.tx_channel.1.try_recv().is_ok());
+        assert!(meta.tx_channel.1.try_recv().is_err());
+        assert_eq!(meta.send_transaction_cache_stats().pending, 2);
+
+        // once landed, the signature is still filtered
+        bank.process_transaction(&transaction).unwrap();
+        let res = io.handle_request_sync(&req, meta.clone());
+        assert_eq!(res, Some(expected));
+        assert!(meta.tx_channel.1.try_recv().is_err());
+        assert_eq!(meta.send_transaction_cache_stats().filtered(), 3);
+    }
+
     #[test]
     fn test_rpc_verify_filter() {
         let filter = RpcFilterType::Memcmp(Memcmp::new(
@@ -0,0 +1,273 @@
+use {
+    solana_sdk::{
+        hash::Hash, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair,
+        signer::Signer, system_transaction, transaction::Transaction,
+    },
+    std::{
+        collections::{HashMap, HashSet},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    thiserror::Error,
+};
+
+pub const DEFAULT_TIME_SLICE: Duration = Duration::from_secs(60);
+pub const DEFAULT_PER_REQUEST_CAP: u64 = 10 * LAMPORTS_PER_SOL;
+pub const DEFAULT_PER_ADDRESS_CAP: u64 = 100 * LAMPORTS_PER_SOL;
+pub const DEFAULT_PER_TIME_CAP: u64 = 1_000 * LAMPORTS_PER_SOL;
+
+#[derive(Debug, Clone)]
+pub struct FaucetConfig {
+    /// Max lamports of a single airdrop.
+    pub per_request_cap: Option<u64>,
+    /// Max lamports a single address can receive within one time slice.
+    pub per_address_cap: Option<u64>,
+    /// Max lamports handed out to all addresses within one time slice.
+    pub per_time_cap: Option<u64>,
+    pub time_slice: Duration,
+    /// Only fund these addresses if set.
+    pub allowlist: Option<HashSet<Pubkey>>,
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        Self {
+            per_request_cap: Some(DEFAULT_PER_REQUEST_CAP),
+            per_address_cap: Some(DEFAULT_PER_ADDRESS_CAP),
+            per_time_cap: Some(DEFAULT_PER_TIME_CAP),
+            time_slice: DEFAULT_TIME_SLICE,
+            allowlist: None,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FaucetError {
+    #[error("address {0} is not allowed to request airdrops")]
+    NotAllowed(Pubkey),
+
+    #[error("airdrop of {requested} lamports exceeds the per request cap of {cap}")]
+    PerRequestCapExceeded { requested: u64, cap: u64 },
+
+    #[error("airdrops to {address} exceed the cap of {cap} lamports, retry later")]
+    PerAddressCapExceeded { address: Pubkey, cap: u64 },
+
+    #[error("faucet exceeds its cap of {cap} lamports, retry later")]
+    PerTimeCapExceeded { cap: u64 },
+}
+
+/// In-process faucet signing airdrops with the mint keypair, so `requestAirdrop` works without
+/// an external solana-faucet daemon.
+pub struct LocalFaucet {
+    keypair: Arc<Keypair>,
+    config: FaucetConfig,
+    state: Mutex<FaucetState>,
+}
+
+struct FaucetState {
+    slice_start: Instant,
+    total: u64,
+    per_address: HashMap<Pubkey, u64>,
+}
+
+impl LocalFaucet {
+    pub fn new(keypair: Arc<Keypair>, config: FaucetConfig) -> Self {
+        Self {
+            keypair,
+            config,
+            state: Mutex::new(FaucetState {
+                slice_start: Instant::now(),
+                total: 0,
+                per_address: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    /// Checks the limits, records the airdrop and builds the signed transfer. The airdrop is to
+    /// be refunded if the transfer is not accepted, see [`LocalFaucet::refund`].
+    pub fn build_airdrop_transaction(
+        &self,
+        to: &Pubkey,
+        lamports: u64,
+        blockhash: Hash,
+    ) -> Result<Transaction, FaucetError> {
+        self.record(to, lamports)?;
+        Ok(system_transaction::transfer(
+            &self.keypair,
+            to,
+            lamports,
+            blockhash,
+        ))
+    }
+
+    fn record(&self, to: &Pubkey, lamports: u64) -> Result<(), FaucetError> {
+        if let Some(allowlist) = &self.config.allowlist {
+            if !allowlist.contains(to) {
+                return Err(FaucetError::NotAllowed(*to));
+            }
+        }
+        if let Some(cap) = self.config.per_request_cap {
+            if lamports > cap {
+                return Err(FaucetError::PerRequestCapExceeded {
+                    requested: lamports,
+                    cap,
+                });
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.slice_start.elapsed() >= self.config.time_slice {
+            state.slice_start = Instant::now();
+            state.total = 0;
+            state.per_address.clear();
+        }
+
+        let address_total = state
+            .per_address
+            .get(to)
+            .copied()
+            .unwrap_or_default()
+            .saturating_add(lamports);
+        if let Some(cap) = self.config.per_address_cap {
+            if address_total > cap {
+                return Err(FaucetError::PerAddressCapExceeded { address: *to, cap });
+            }
+        }
+        let total = state.total.saturating_add(lamports);
+        if let Some(cap) = self.config.per_time_cap {
+            if total > cap {
+                return Err(FaucetError::PerTimeCapExceeded { cap });
+            }
+        }
+
+        state.total = total;
+        state.per_address.insert(*to, address_total);
+        Ok(())
+    }
+
+    /// Gives back the quota of an airdrop whose transfer was not accepted.
+    pub fn refund(&self, to: &Pubkey, lamports: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.total = state.total.saturating_sub(lamports);
+        if let Some(address_total) = state.per_address.get_mut(to) {
+            *address_total = address_total.saturating_sub(lamports);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_faucet(config: FaucetConfig) -> LocalFaucet {
+        LocalFaucet::new(Arc::new(Keypair::new()), config)
+    }
+
+    #[test]
+    fn test_faucet_per_request_cap() {
+        let faucet = new_faucet(FaucetConfig {
+            per_request_cap: Some(10),
+            ..FaucetConfig::default()
+        });
+        let to = Pubkey::new_unique();
+
+        let tx = faucet
+            .build_airdrop_transaction(&to, 10, Hash::default())
+            .unwrap();
+        assert_eq!(tx.message.account_keys[0], faucet.pubkey());
+        assert_eq!(
+            faucet.build_airdrop_transaction(&to, 11, Hash::default()),
+            Err(FaucetError::PerRequestCapExceeded {
+                requested: 11,
+                cap: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_faucet_rate_limits() {
+        let faucet = new_faucet(FaucetConfig {
+            per_request_cap: None,
+            per_address_cap: Some(10),
+            per_time_cap: Some(25),
+            ..FaucetConfig::default()
+        });
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let charlie = Pubkey::new_unique();
+
+        assert!(faucet.record(&alice, 6).is_ok());
+        assert_eq!(
+            faucet.record(&alice, 6),
+            Err(FaucetError::PerAddressCapExceeded {
+                address: alice,
+                cap: 10
+            })
+        );
+        assert!(faucet.record(&alice, 4).is_ok());
+        assert!(faucet.record(&bob, 10).is_ok());
+        assert_eq!(
+            faucet.record(&charlie, 6),
+            Err(FaucetError::PerTimeCapExceeded { cap: 25 })
+        );
+        assert!(faucet.record(&charlie, 5).is_ok());
+    }
+
+    #[test]
+    fn test_faucet_refund() {
+        let faucet = new_faucet(FaucetConfig {
+            per_request_cap: None,
+            per_address_cap: Some(10),
+            per_time_cap: Some(15),
+            ..FaucetConfig::default()
+        });
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+
+        assert!(faucet.record(&alice, 10).is_ok());
+        assert!(faucet.record(&alice, 1).is_err());
+        faucet.refund(&alice, 10);
+        assert!(faucet.record(&alice, 10).is_ok());
+        assert_eq!(
+            faucet.record(&bob, 6),
+            Err(FaucetError::PerTimeCapExceeded { cap: 15 })
+        );
+        faucet.refund(&alice, 4);
+        assert!(faucet.record(&bob, 6).is_ok());
+    }
+
+    #[test]
+    fn test_faucet_time_slice_resets_limits() {
+        let faucet = new_faucet(FaucetConfig {
+            per_address_cap: Some(10),
+            time_slice: Duration::from_millis(10),
+            ..FaucetConfig::default()
+        });
+        let to = Pubkey::new_unique();
+
+        assert!(faucet.record(&to, 10).is_ok());
+        assert!(faucet.record(&to, 1).is_err());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(faucet.record(&to, 10).is_ok());
+    }
+
+    #[test]
+    fn test_faucet_allowlist() {
+        let allowed = Pubkey::new_unique();
+        let faucet = new_faucet(FaucetConfig {
+            allowlist: Some(HashSet::from([allowed])),
+            ..FaucetConfig::default()
+        });
+        let other = Pubkey::new_unique();
+
+        assert!(faucet.record(&allowed, 1).is_ok());
+        assert_eq!(
+            faucet.record(&other, 1),
+            Err(FaucetError::NotAllowed(other))
+        );
+    }
+}
@@ -1,3 +1,6 @@
 mod cache;
 pub mod core;
+pub mod faucet;
+pub mod output_root;
+pub mod send_cache;
 pub mod service;
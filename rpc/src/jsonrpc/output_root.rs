@@ -0,0 +1,39 @@
+//! Output roots posted to L1:
+//! `keccak256(version ++ state_root ++ withdrawal_root ++ block_hash)`.
+//!
+//! The state root is the sparse Merkle root of every account kept by the storage, see
+//! `igloo_storage::state`. The withdrawal root is the root of a tree of the same kind over the
+//! PDAs owned by the withdrawal native program, so a withdrawal is proven against the withdrawal
+//! root and the withdrawal program against the state root.
+
+use igloo_storage::state::{hash_account, SparseMerkleTree};
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    hash::Hash as BlockHash,
+    keccak::{hashv, Hash},
+    pubkey::Pubkey,
+};
+
+pub const OUTPUT_VERSION: [u8; 32] = [0; 32];
+
+pub fn output_root(state_root: &Hash, withdrawal_root: &Hash, block_hash: &BlockHash) -> Hash {
+    hashv(&[
+        &OUTPUT_VERSION,
+        state_root.as_ref(),
+        withdrawal_root.as_ref(),
+        block_hash.as_ref(),
+    ])
+}
+
+/// Tree of the withdrawal PDAs, accounts without lamports are left out like in the state tree.
+pub fn withdrawal_tree(
+    withdrawals: impl IntoIterator<Item = (Pubkey, AccountSharedData)>,
+) -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::default();
+    for (pubkey, account) in withdrawals {
+        if account.lamports() > 0 {
+            tree.update(pubkey, Some(hash_account(&account)));
+        }
+    }
+    tree
+}
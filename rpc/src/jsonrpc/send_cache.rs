@@ -0,0 +1,225 @@
+use {
+    solana_runtime::bank::Bank,
+    solana_sdk::{clock::Slot, hash::Hash, nonce_account, pubkey::Pubkey, signature::Signature},
+    std::collections::HashMap,
+    thiserror::Error,
+};
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum SendTransactionFilter {
+    #[error("transaction is already pending")]
+    Pending,
+
+    #[error("transaction has already been processed")]
+    AlreadyProcessed,
+
+    #[error("transaction blockhash or durable nonce expired")]
+    Expired,
+}
+
+#[derive(Debug, Clone)]
+struct TransactionInfo {
+    last_valid_block_height: u64,
+    durable_nonce_info: Option<(Pubkey, Hash)>,
+}
+
+impl TransactionInfo {
+    fn is_expired(&self, bank: &Bank) -> bool {
+        if let Some((nonce_pubkey, durable_nonce)) = self.durable_nonce_info {
+            // nonce transactions stay valid until the nonce advances, the last valid block height
+            // is only a fallback timeout for them
+            let nonce_account = bank.get_account(&nonce_pubkey).unwrap_or_default();
+            if nonce_account::verify_nonce_account(&nonce_account, &durable_nonce).is_none() {
+                return true;
+            }
+        }
+        bank.block_height() > self.last_valid_block_height
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SendTransactionCacheStats {
+    pub pending: u64,
+    pub already_processed: u64,
+    pub expired: u64,
+}
+
+impl SendTransactionCacheStats {
+    pub fn filtered(&self) -> u64 {
+        self.pending + self.already_processed + self.expired
+    }
+}
+
+/// Transactions sent through RPC but not yet seen by the working bank, keyed by signature.
+/// Wallets retry the same signature until it lands, the cache makes sure only the first
+/// submission reaches the transaction stream.
+#[derive(Debug, Default)]
+pub struct SendTransactionCache {
+    pending: HashMap<Signature, TransactionInfo>,
+    last_pruned_slot: Option<Slot>,
+    stats: SendTransactionCacheStats,
+}
+
+impl SendTransactionCache {
+    /// Inserts the transaction if it should be forwarded, or returns the reason to filter it.
+    pub fn try_insert(
+        &mut self,
+        bank: &Bank,
+        signature: Signature,
+        last_valid_block_height: u64,
+        durable_nonce_info: Option<(Pubkey, Hash)>,
+    ) -> Result<(), SendTransactionFilter> {
+        self.prune(bank);
+
+        let info = TransactionInfo {
+            last_valid_block_height,
+            durable_nonce_info,
+        };
+        let filter = if self.pending.contains_key(&signature) {
+            Some(SendTransactionFilter::Pending)
+        } else if bank.get_signature_status_slot(&signature).is_some() {
+            Some(SendTransactionFilter::AlreadyProcessed)
+        } else if info.is_expired(bank) {
+            Some(SendTransactionFilter::Expired)
+        } else {
+            None
+        };
+
+        match filter {
+            Some(filter) => {
+                match filter {
+                    SendTransactionFilter::Pending => self.stats.pending += 1,
+                    SendTransactionFilter::AlreadyProcessed => self.stats.already_processed += 1,
+                    SendTransactionFilter::Expired => self.stats.expired += 1,
+                }
+                Err(filter)
+            }
+            None => {
+                self.pending.insert(signature, info);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn stats(&self) -> SendTransactionCacheStats {
+        self.stats
+    }
+
+    /// Drops the transactions landed or expired in `bank`, at most once per slot.
+    fn prune(&mut self, bank: &Bank) {
+        if self.last_pruned_slot == Some(bank.slot()) {
+            return;
+        }
+        self.last_pruned_slot = Some(bank.slot());
+        self.pending.retain(|signature, info| {
+            bank.get_signature_status_slot(signature).is_none() && !info.is_expired(bank)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_runtime::genesis_utils::create_genesis_config,
+        solana_sdk::{signature::Keypair, signer::Signer, system_transaction},
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_send_transaction_cache() {
+        let genesis = create_genesis_config(1_000_000_000);
+        let bank = Arc::new(Bank::new_for_tests(&genesis.genesis_config));
+        let mut cache = SendTransactionCache::default();
+        let last_valid_block_height = bank.block_height() + 2;
+
+        let signature = Signature::new_unique();
+        assert_eq!(
+            cache.try_insert(&bank, signature, last_valid_block_height, None),
+            Ok(())
+        );
+        assert_eq!(
+            cache.try_insert(&bank, signature, last_valid_block_height, None),
+            Err(SendTransactionFilter::Pending)
+        );
+        assert_eq!(cache.len(), 1);
+
+        // landed transactions are pruned and rejected afterwards
+        let tx = system_transaction::transfer(
+            &genesis.mint_keypair,
+            &Keypair::new().pubkey(),
+            bank.get_minimum_balance_for_rent_exemption(0),
+            bank.last_blockhash(),
+        );
+        assert_eq!(
+            cache.try_insert(&bank, tx.signatures[0], last_valid_block_height, None),
+            Ok(())
+        );
+        let bank1 = Bank::new_from_parent(bank.clone(), &Pubkey::default(), 1);
+        bank1.process_transaction(&tx).unwrap();
+        assert_eq!(
+            cache.try_insert(&bank1, tx.signatures[0], last_valid_block_height, None),
+            Err(SendTransactionFilter::AlreadyProcessed)
+        );
+        assert_eq!(cache.len(), 1);
+
+        assert_eq!(
+            cache.try_insert(
+                &bank1,
+                Signature::new_unique(),
+                bank1.block_height() - 1,
+                None
+            ),
+            Err(SendTransactionFilter::Expired)
+        );
+        assert_eq!(cache.len(), 1);
+
+        // pending transactions expire with the block height
+        let bank2 = Bank::new_from_parent(Arc::new(bank1), &Pubkey::default(), 2);
+        let bank3 = Bank::new_from_parent(Arc::new(bank2), &Pubkey::default(), 3);
+        assert!(bank3.block_height() > last_valid_block_height);
+        assert_eq!(
+            cache.try_insert(&bank3, Signature::new_unique(), u64::MAX, None),
+            Ok(())
+        );
+        assert_eq!(cache.len(), 1);
+
+        assert_eq!(
+            cache.stats(),
+            SendTransactionCacheStats {
+                pending: 1,
+                already_processed: 1,
+                expired: 1,
+            }
+        );
+        assert_eq!(cache.stats().filtered(), 3);
+    }
+
+    #[test]
+    fn test_send_transaction_cache_durable_nonce() {
+        let genesis = create_genesis_config(1_000_000_000);
+        let bank = Bank::new_for_tests(&genesis.genesis_config);
+        let mut cache = SendTransactionCache::default();
+
+        // the nonce account does not hold the durable nonce
+        let nonce_pubkey = Pubkey::new_unique();
+        assert_eq!(
+            cache.try_insert(
+                &bank,
+                Signature::new_unique(),
+                u64::MAX,
+                Some((nonce_pubkey, Hash::new_unique()))
+            ),
+            Err(SendTransactionFilter::Expired)
+        );
+        assert!(cache.is_empty());
+    }
+}
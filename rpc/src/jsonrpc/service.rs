@@ -8,7 +8,9 @@ use super::core::{
     rpc_accounts, rpc_accounts_scan, rpc_bank, rpc_full, rpc_minimal, JsonRpcConfig,
     JsonRpcRequestProcessor, MAX_REQUEST_BODY_SIZE,
 };
+use super::faucet::LocalFaucet;
 use crossbeam_channel::{unbounded, Receiver, Sender};
+use igloo_storage::state::StateCommitment;
 use jsonrpc_core::futures_util::TryStreamExt;
 use jsonrpc_core::MetaIoHandler;
 use jsonrpc_http_server::{
@@ -34,7 +36,7 @@ use solana_sdk::transaction::SanitizedTransaction;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::thread::{Builder, JoinHandle};
 use tokio_util::codec::{BytesCodec, FramedRead};
@@ -46,6 +48,7 @@ const LARGEST_ACCOUNTS_CACHE_DURATION: u64 = 60 * 60 * 2;
 pub struct JsonRpcService {
     thread_hdl: JoinHandle<()>,
     close_handle: Option<CloseHandle>,
+    address: SocketAddr,
 }
 
 struct RpcRequestMiddleware {
@@ -320,7 +323,10 @@ impl JsonRpcService {
         ledger_path: &Path,
         node_exit: Arc<RwLock<Exit>>,
         max_complete_transaction_status_slot: Arc<AtomicU64>,
+        confirmed_slot: Arc<AtomicU64>,
         prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+        faucet: Option<Arc<LocalFaucet>>,
+        state_commitment: Option<Arc<Mutex<StateCommitment>>>,
     ) -> Result<Self, String> {
         info!("rpc bound to {:?}", rpc_addr);
         info!("rpc configuration: {:?}", config);
@@ -420,7 +426,10 @@ impl JsonRpcService {
             None, // bigtable_ledger_storage,
             largest_accounts_cache,
             max_complete_transaction_status_slot,
+            confirmed_slot,
             prioritization_fee_cache,
+            faucet,
+            state_commitment,
         );
 
         let ledger_path = ledger_path.to_path_buf();
@@ -476,13 +485,15 @@ impl JsonRpcService {
                 }
 
                 let server = server.unwrap();
-                close_handle_sender.send(Ok(server.close_handle())).unwrap();
+                close_handle_sender
+                    .send(Ok((server.close_handle(), *server.address())))
+                    .unwrap();
                 server.wait();
                 exit_bigtable_ledger_upload_service.store(true, Ordering::Relaxed);
             })
             .unwrap();
 
-        let close_handle = close_handle_receiver.recv().unwrap()?;
+        let (close_handle, address) = close_handle_receiver.recv().unwrap()?;
         let close_handle_ = close_handle.clone();
         node_exit.write().unwrap().register_exit(Box::new(move || {
             close_handle_.close();
@@ -490,9 +501,15 @@ impl JsonRpcService {
         Ok(Self {
             thread_hdl,
             close_handle: Some(close_handle),
+            address,
         })
     }
 
+    /// Address the service is bound to, the actual port if `rpc_addr` asked for port 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
     pub fn exit(&mut self) {
         if let Some(c) = self.close_handle.take() {
             c.close()
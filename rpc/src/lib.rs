@@ -17,6 +17,7 @@ mod filter;
 mod parsed_token_accounts;
 
 pub mod jsonrpc;
+pub mod pubsub;
 pub mod service;
 
 use error::{Error, Result};
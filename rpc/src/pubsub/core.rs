@@ -0,0 +1,225 @@
+use {
+    super::subscriptions::{LogsFilter, RpcSubscriptions, SubscriptionId, SubscriptionParams},
+    crate::jsonrpc::core::{optimize_filters, verify_filter, verify_pubkey, verify_signature},
+    dashmap::DashSet,
+    jsonrpc_core::{Error, ErrorCode, Result},
+    jsonrpc_derive::rpc,
+    solana_account_decoder::UiAccountEncoding,
+    solana_rpc_client_api::config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSignatureSubscribeConfig,
+        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    std::sync::Arc,
+};
+
+#[rpc]
+pub trait RpcSolPubSub {
+    #[rpc(name = "accountSubscribe")]
+    fn account_subscribe(
+        &self,
+        pubkey_str: String,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<SubscriptionId>;
+
+    #[rpc(name = "accountUnsubscribe")]
+    fn account_unsubscribe(&self, id: SubscriptionId) -> Result<bool>;
+
+    #[rpc(name = "programSubscribe")]
+    fn program_subscribe(
+        &self,
+        pubkey_str: String,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> Result<SubscriptionId>;
+
+    #[rpc(name = "programUnsubscribe")]
+    fn program_unsubscribe(&self, id: SubscriptionId) -> Result<bool>;
+
+    #[rpc(name = "logsSubscribe")]
+    fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: Option<RpcTransactionLogsConfig>,
+    ) -> Result<SubscriptionId>;
+
+    #[rpc(name = "logsUnsubscribe")]
+    fn logs_unsubscribe(&self, id: SubscriptionId) -> Result<bool>;
+
+    #[rpc(name = "signatureSubscribe")]
+    fn signature_subscribe(
+        &self,
+        signature_str: String,
+        config: Option<RpcSignatureSubscribeConfig>,
+    ) -> Result<SubscriptionId>;
+
+    #[rpc(name = "signatureUnsubscribe")]
+    fn signature_unsubscribe(&self, id: SubscriptionId) -> Result<bool>;
+
+    #[rpc(name = "slotSubscribe")]
+    fn slot_subscribe(&self) -> Result<SubscriptionId>;
+
+    #[rpc(name = "slotUnsubscribe")]
+    fn slot_unsubscribe(&self, id: SubscriptionId) -> Result<bool>;
+}
+
+/// Pubsub methods of a single websocket connection.
+pub struct RpcSolPubSubImpl {
+    subscriptions: Arc<RpcSubscriptions>,
+    max_active_subscriptions: usize,
+    /// Subscriptions made over this connection, removed when it closes.
+    current_subscriptions: Arc<DashSet<SubscriptionId>>,
+}
+
+impl RpcSolPubSubImpl {
+    pub fn new(
+        subscriptions: Arc<RpcSubscriptions>,
+        max_active_subscriptions: usize,
+        current_subscriptions: Arc<DashSet<SubscriptionId>>,
+    ) -> Self {
+        Self {
+            subscriptions,
+            max_active_subscriptions,
+            current_subscriptions,
+        }
+    }
+
+    fn subscribe(&self, params: SubscriptionParams) -> Result<SubscriptionId> {
+        if self.subscriptions.len() >= self.max_active_subscriptions {
+            return Err(Error {
+                code: ErrorCode::InternalError,
+                message: "Internal Error: Subscription refused. Node subscription limit reached"
+                    .into(),
+                data: None,
+            });
+        }
+        let id = self.subscriptions.subscribe(params);
+        self.current_subscriptions.insert(id);
+        Ok(id)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        if self.current_subscriptions.remove(&id).is_some() {
+            // one-shot subscriptions may be gone already
+            self.subscriptions.unsubscribe(id);
+            Ok(true)
+        } else {
+            Err(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid subscription id.".into(),
+                data: None,
+            })
+        }
+    }
+}
+
+impl Drop for RpcSolPubSubImpl {
+    fn drop(&mut self) {
+        for id in self.current_subscriptions.iter() {
+            self.subscriptions.unsubscribe(*id);
+        }
+    }
+}
+
+impl RpcSolPubSub for RpcSolPubSubImpl {
+    fn account_subscribe(
+        &self,
+        pubkey_str: String,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<SubscriptionId> {
+        let RpcAccountInfoConfig {
+            encoding,
+            data_slice,
+            commitment,
+            min_context_slot: _,
+        } = config.unwrap_or_default();
+        self.subscribe(SubscriptionParams::Account {
+            pubkey: verify_pubkey(&pubkey_str)?,
+            encoding: encoding.unwrap_or(UiAccountEncoding::Binary),
+            data_slice,
+            commitment: commitment.unwrap_or_default().into(),
+        })
+    }
+
+    fn account_unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(id)
+    }
+
+    fn program_subscribe(
+        &self,
+        pubkey_str: String,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> Result<SubscriptionId> {
+        let config = config.unwrap_or_default();
+        let mut filters = config.filters.unwrap_or_default();
+        for filter in &filters {
+            verify_filter(filter)?;
+        }
+        optimize_filters(&mut filters);
+        self.subscribe(SubscriptionParams::Program {
+            pubkey: verify_pubkey(&pubkey_str)?,
+            encoding: config
+                .account_config
+                .encoding
+                .unwrap_or(UiAccountEncoding::Binary),
+            data_slice: config.account_config.data_slice,
+            filters,
+            commitment: config.account_config.commitment.unwrap_or_default().into(),
+        })
+    }
+
+    fn program_unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(id)
+    }
+
+    fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: Option<RpcTransactionLogsConfig>,
+    ) -> Result<SubscriptionId> {
+        let filter = match filter {
+            RpcTransactionLogsFilter::All => LogsFilter::All,
+            RpcTransactionLogsFilter::AllWithVotes => LogsFilter::AllWithVotes,
+            RpcTransactionLogsFilter::Mentions(keys) => {
+                if keys.len() != 1 {
+                    return Err(Error::invalid_params(
+                        "Invalid Request: Only 1 address supported",
+                    ));
+                }
+                LogsFilter::Mentions(verify_pubkey(&keys[0])?)
+            }
+        };
+        let commitment = config.and_then(|config| config.commitment);
+        self.subscribe(SubscriptionParams::Logs {
+            filter,
+            commitment: commitment.unwrap_or_default().into(),
+        })
+    }
+
+    fn logs_unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(id)
+    }
+
+    /// Received notifications are not supported, transactions are only notified once committed.
+    fn signature_subscribe(
+        &self,
+        signature_str: String,
+        config: Option<RpcSignatureSubscribeConfig>,
+    ) -> Result<SubscriptionId> {
+        let commitment = config.and_then(|config| config.commitment);
+        self.subscribe(SubscriptionParams::Signature {
+            signature: verify_signature(&signature_str)?,
+            commitment: commitment.unwrap_or_default().into(),
+        })
+    }
+
+    fn signature_unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(id)
+    }
+
+    fn slot_subscribe(&self) -> Result<SubscriptionId> {
+        self.subscribe(SubscriptionParams::Slot)
+    }
+
+    fn slot_unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(id)
+    }
+}
@@ -0,0 +1,317 @@
+use {
+    super::{
+        core::{RpcSolPubSub, RpcSolPubSubImpl},
+        subscriptions::{CommitNotifier, Notification, RpcSubscriptions},
+    },
+    crossbeam_channel::Receiver,
+    dashmap::DashSet,
+    igloo_storage::events::CommitEvent,
+    jsonrpc_core::IoHandler,
+    soketto::handshake::{server, Server},
+    solana_runtime::bank_forks::BankForks,
+    solana_sdk::exit::Exit,
+    std::{
+        net::SocketAddr,
+        str,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, RwLock,
+        },
+        thread::{self, Builder, JoinHandle},
+    },
+    stream_cancel::{Trigger, Tripwire},
+    thiserror::Error,
+    tokio::{net::TcpStream, pin, select, sync::broadcast},
+    tokio_util::compat::TokioAsyncReadCompatExt,
+};
+
+pub const DEFAULT_MAX_ACTIVE_SUBSCRIPTIONS: usize = 1_000_000;
+pub const DEFAULT_QUEUE_CAPACITY_ITEMS: usize = 65_536;
+pub const DEFAULT_WORKER_THREADS: usize = 1;
+
+#[derive(Debug, Clone)]
+pub struct PubSubConfig {
+    pub max_active_subscriptions: usize,
+    /// Notifications buffered for slow connections, connections lagging behind are closed.
+    pub queue_capacity_items: usize,
+    pub worker_threads: usize,
+}
+
+impl Default for PubSubConfig {
+    fn default() -> Self {
+        Self {
+            max_active_subscriptions: DEFAULT_MAX_ACTIVE_SUBSCRIPTIONS,
+            queue_capacity_items: DEFAULT_QUEUE_CAPACITY_ITEMS,
+            worker_threads: DEFAULT_WORKER_THREADS,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum ConnectionError {
+    #[error("handshake error: {0}")]
+    Handshake(#[from] soketto::handshake::Error),
+
+    #[error("connection error: {0}")]
+    Connection(#[from] soketto::connection::Error),
+
+    #[error("broadcast queue error: {0}")]
+    Broadcast(#[from] broadcast::error::RecvError),
+}
+
+/// Websocket server of the `*Subscribe` methods, fed by the commit events of the storage.
+pub struct PubSubService {
+    subscriptions: Arc<RpcSubscriptions>,
+    notifier: CommitNotifier,
+    thread_hdl: JoinHandle<()>,
+    trigger: Option<Trigger>,
+    exit: Arc<AtomicBool>,
+}
+
+impl PubSubService {
+    pub fn new(
+        pubsub_addr: SocketAddr,
+        config: PubSubConfig,
+        bank_forks: Arc<RwLock<BankForks>>,
+        confirmed_slot: Arc<AtomicU64>,
+        commit_events: Receiver<CommitEvent>,
+        node_exit: Arc<RwLock<Exit>>,
+    ) -> Result<Self, String> {
+        info!("rpc pubsub bound to {:?}", pubsub_addr);
+        // bind up front so a taken port fails the startup
+        let listener = std::net::TcpListener::bind(pubsub_addr)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|e| format!("failed to bind pubsub address {pubsub_addr}: {e}"))?;
+
+        let subscriptions = Arc::new(RpcSubscriptions::new(
+            bank_forks,
+            confirmed_slot,
+            config.queue_capacity_items,
+        ));
+        let exit = Arc::new(AtomicBool::new(false));
+        let notifier = CommitNotifier::new(subscriptions.clone(), commit_events, exit.clone());
+
+        let (trigger, tripwire) = Tripwire::new();
+        let subscriptions_ = subscriptions.clone();
+        let thread_hdl = Builder::new()
+            .name("solRpcPubSub".to_string())
+            .spawn(move || {
+                let runtime = tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1.max(config.worker_threads))
+                    .thread_name("solRpcPubSubRt")
+                    .enable_all()
+                    .build()
+                    .expect("Runtime");
+                if let Err(err) =
+                    runtime.block_on(listen(listener, config, subscriptions_, tripwire))
+                {
+                    error!("pubsub service failed: {}", err);
+                }
+            })
+            .unwrap();
+
+        let exit_ = exit.clone();
+        node_exit
+            .write()
+            .unwrap()
+            .register_exit(Box::new(move || exit_.store(true, Ordering::Relaxed)));
+        Ok(Self {
+            subscriptions,
+            notifier,
+            thread_hdl,
+            trigger: Some(trigger),
+            exit,
+        })
+    }
+
+    pub fn subscriptions(&self) -> &Arc<RpcSubscriptions> {
+        &self.subscriptions
+    }
+
+    pub fn exit(&mut self) {
+        self.exit.store(true, Ordering::Relaxed);
+        if let Some(trigger) = self.trigger.take() {
+            trigger.cancel();
+        }
+    }
+
+    pub fn join(mut self) -> thread::Result<()> {
+        self.exit();
+        self.notifier.join()?;
+        self.thread_hdl.join()
+    }
+}
+
+async fn listen(
+    listener: std::net::TcpListener,
+    config: PubSubConfig,
+    subscriptions: Arc<RpcSubscriptions>,
+    mut tripwire: Tripwire,
+) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    loop {
+        select! {
+            result = listener.accept() => match result {
+                Ok((socket, addr)) => {
+                    debug!("new pubsub client ({:?})", addr);
+                    let subscriptions = subscriptions.clone();
+                    let config = config.clone();
+                    let tripwire = tripwire.clone();
+                    tokio::spawn(async move {
+                        match handle_connection(socket, subscriptions, config, tripwire).await {
+                            Ok(()) => debug!("pubsub connection closed ({:?})", addr),
+                            Err(err) => warn!("pubsub connection error ({:?}): {}", addr, err),
+                        }
+                    });
+                }
+                Err(e) => error!("couldn't accept pubsub connection: {:?}", e),
+            },
+            _ = &mut tripwire => return Ok(()),
+        }
+    }
+}
+
+async fn handle_connection(
+    socket: TcpStream,
+    subscriptions: Arc<RpcSubscriptions>,
+    config: PubSubConfig,
+    mut tripwire: Tripwire,
+) -> Result<(), ConnectionError> {
+    let mut server = Server::new(socket.compat());
+    let request = server.receive_request().await?;
+    let accept = server::Response::Accept {
+        key: request.key(),
+        protocol: None,
+    };
+    server.send_response(&accept).await?;
+    let (mut sender, mut receiver) = server.into_builder().finish();
+
+    // subscribe before the first request, no notification of this connection gets lost
+    let mut broadcast_receiver = subscriptions.broadcast_receiver();
+    let current_subscriptions = Arc::new(DashSet::new());
+    let mut json_rpc_handler = IoHandler::new();
+    json_rpc_handler.extend_with(
+        RpcSolPubSubImpl::new(
+            subscriptions,
+            config.max_active_subscriptions,
+            current_subscriptions.clone(),
+        )
+        .to_delegate(),
+    );
+
+    let mut data = Vec::new();
+    loop {
+        {
+            // soketto is not cancel safe, the receive future is polled to completion
+            let receive_future = receiver.receive_data(&mut data);
+            pin!(receive_future);
+            loop {
+                select! {
+                    result = &mut receive_future => match result {
+                        Ok(_) => break,
+                        Err(soketto::connection::Error::Closed) => return Ok(()),
+                        Err(err) => return Err(err.into()),
+                    },
+                    // lagging connections are closed
+                    result = broadcast_receiver.recv() => {
+                        let notification: Arc<Notification> = result?;
+                        if current_subscriptions.contains(&notification.subscription) {
+                            sender.send_text(&notification.json).await?;
+                            sender.flush().await?;
+                        }
+                    },
+                    _ = &mut tripwire => {
+                        warn!("disconnecting websocket client: shutting down");
+                        return Ok(());
+                    },
+                }
+            }
+        }
+
+        let Ok(request) = str::from_utf8(&data) else {
+            break;
+        };
+        if let Some(response) = json_rpc_handler.handle_request(request).await {
+            sender.send_text(&response).await?;
+            sender.flush().await?;
+        }
+        data.clear();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        igloo_storage::events::CommittedTransaction,
+        solana_client::pubsub_client::PubsubClient,
+        solana_runtime::{bank::Bank, genesis_utils::create_genesis_config},
+        solana_sdk::{pubkey::Pubkey, signature::Signature},
+        std::time::Duration,
+    };
+
+    fn unused_addr() -> SocketAddr {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pubsub_service_notifications() {
+        let genesis = create_genesis_config(1_000_000_000);
+        let bank_forks = BankForks::new_rw_arc(Bank::new_for_tests(&genesis.genesis_config));
+        let bank = bank_forks.read().unwrap().root_bank();
+        let (commit_sender, commit_receiver) = crossbeam_channel::unbounded();
+        let pubsub_addr = unused_addr();
+        let service = PubSubService::new(
+            pubsub_addr,
+            PubSubConfig::default(),
+            bank_forks,
+            Arc::new(AtomicU64::new(0)),
+            commit_receiver,
+            Arc::new(RwLock::new(Exit::default())),
+        )
+        .unwrap();
+
+        let url = format!("ws://{pubsub_addr}");
+        let (_slot_subscription, slot_receiver) = PubsubClient::slot_subscribe(&url).unwrap();
+        let signature = Signature::new_unique();
+        let (_signature_subscription, signature_receiver) =
+            PubsubClient::signature_subscribe(&url, &signature, None).unwrap();
+        assert_eq!(service.subscriptions().len(), 2);
+
+        let bank1 = Arc::new(Bank::new_from_parent(bank, &Pubkey::default(), 1));
+        commit_sender
+            .send(CommitEvent::Block {
+                bank: bank1.clone(),
+                transactions: Arc::new(vec![CommittedTransaction {
+                    signature,
+                    err: None,
+                    log_messages: vec![],
+                    account_keys: vec![],
+                    writable_accounts: vec![],
+                    is_vote: false,
+                }]),
+            })
+            .unwrap();
+
+        let slot_info = slot_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(slot_info.slot, 1);
+        assert_eq!(slot_info.parent, 0);
+
+        // signature subscriptions default to finalized
+        assert!(signature_receiver
+            .recv_timeout(Duration::from_millis(200))
+            .is_err());
+        commit_sender.send(CommitEvent::Rooted(bank1)).unwrap();
+        let response = signature_receiver
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(response.context.slot, 1);
+
+        service.join().unwrap();
+    }
+}
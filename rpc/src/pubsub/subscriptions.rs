@@ -0,0 +1,676 @@
+use {
+    crate::{
+        filter::filter_allows,
+        jsonrpc::core::{commitment_bank, encode_account, get_encoded_account},
+    },
+    crossbeam_channel::{Receiver, RecvTimeoutError},
+    igloo_storage::events::{CommitEvent, CommittedTransaction},
+    serde_derive::Serialize,
+    solana_account_decoder::{UiAccount, UiAccountEncoding, UiDataSliceConfig},
+    solana_rpc_client_api::{
+        filter::RpcFilterType,
+        response::{
+            ProcessedSignatureResult, RpcKeyedAccount, RpcLogsResponse, RpcResponse,
+            RpcResponseContext, RpcSignatureResult, SlotInfo,
+        },
+    },
+    solana_runtime::{bank::Bank, bank_forks::BankForks},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        clock::Slot,
+        commitment_config::CommitmentConfig,
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::TransactionError,
+    },
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, RwLock,
+        },
+        thread::{self, Builder, JoinHandle},
+        time::Duration,
+    },
+    tokio::sync::broadcast,
+};
+
+pub type SubscriptionId = u64;
+
+/// Blocks waiting for their `confirmed` or `finalized` notifications. The oldest ones are
+/// dropped unnotified if the storage stops confirming for that long.
+const MAX_PENDING_BLOCKS: usize = 8192;
+
+const COMMIT_EVENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionCommitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl SubscriptionCommitment {
+    fn config(self) -> CommitmentConfig {
+        match self {
+            Self::Processed => CommitmentConfig::processed(),
+            Self::Confirmed => CommitmentConfig::confirmed(),
+            Self::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+impl From<CommitmentConfig> for SubscriptionCommitment {
+    #[allow(deprecated)]
+    fn from(commitment: CommitmentConfig) -> Self {
+        if commitment.is_finalized() {
+            Self::Finalized
+        } else if commitment.is_confirmed() {
+            Self::Confirmed
+        } else {
+            Self::Processed
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogsFilter {
+    /// All transactions except simple votes.
+    All,
+    AllWithVotes,
+    /// Transactions referencing the address.
+    Mentions(Pubkey),
+}
+
+impl LogsFilter {
+    fn allows(&self, tx: &CommittedTransaction) -> bool {
+        match self {
+            Self::All => !tx.is_vote,
+            Self::AllWithVotes => true,
+            Self::Mentions(pubkey) => tx.account_keys.contains(pubkey),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SubscriptionParams {
+    Account {
+        pubkey: Pubkey,
+        encoding: UiAccountEncoding,
+        data_slice: Option<UiDataSliceConfig>,
+        commitment: SubscriptionCommitment,
+    },
+    Program {
+        pubkey: Pubkey,
+        encoding: UiAccountEncoding,
+        data_slice: Option<UiDataSliceConfig>,
+        filters: Vec<RpcFilterType>,
+        commitment: SubscriptionCommitment,
+    },
+    /// Notified once, the subscription is removed afterwards.
+    Signature {
+        signature: Signature,
+        commitment: SubscriptionCommitment,
+    },
+    Logs {
+        filter: LogsFilter,
+        commitment: SubscriptionCommitment,
+    },
+    Slot,
+}
+
+impl SubscriptionParams {
+    fn commitment(&self) -> Option<SubscriptionCommitment> {
+        match self {
+            Self::Account { commitment, .. }
+            | Self::Program { commitment, .. }
+            | Self::Signature { commitment, .. }
+            | Self::Logs { commitment, .. } => Some(*commitment),
+            Self::Slot => None,
+        }
+    }
+}
+
+/// Notification serialized once and broadcast to all connections, each connection only
+/// forwards the notifications of its own subscriptions.
+#[derive(Debug)]
+pub struct Notification {
+    pub subscription: SubscriptionId,
+    pub json: String,
+}
+
+#[derive(Serialize)]
+struct NotificationMessage<'a, T> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: NotificationParams<T>,
+}
+
+#[derive(Serialize)]
+struct NotificationParams<T> {
+    result: T,
+    subscription: SubscriptionId,
+}
+
+/// Subscriptions of all websocket connections.
+pub struct RpcSubscriptions {
+    bank_forks: Arc<RwLock<BankForks>>,
+    confirmed_slot: Arc<AtomicU64>,
+    next_id: AtomicU64,
+    subscriptions: RwLock<HashMap<SubscriptionId, SubscriptionParams>>,
+    sender: broadcast::Sender<Arc<Notification>>,
+}
+
+impl RpcSubscriptions {
+    pub fn new(
+        bank_forks: Arc<RwLock<BankForks>>,
+        confirmed_slot: Arc<AtomicU64>,
+        queue_capacity: usize,
+    ) -> Self {
+        let (sender, _) = broadcast::channel(queue_capacity.max(1));
+        Self {
+            bank_forks,
+            confirmed_slot,
+            next_id: AtomicU64::new(0),
+            subscriptions: RwLock::new(HashMap::new()),
+            sender,
+        }
+    }
+
+    pub fn broadcast_receiver(&self) -> broadcast::Receiver<Arc<Notification>> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscribe(&self, params: SubscriptionParams) -> SubscriptionId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // hold the lock while checking, the notifier must not pass between check and insert
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        if let SubscriptionParams::Signature {
+            signature,
+            commitment,
+        } = &params
+        {
+            // signatures landed before subscribing are notified right away
+            let bank = commitment_bank(&self.bank_forks, &self.confirmed_slot, commitment.config());
+            if let Some((_, status)) = bank.get_signature_status_slot(signature) {
+                self.notify_signature(id, &bank, status.err());
+                return id;
+            }
+        }
+        subscriptions.insert(id, params);
+        id
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        self.subscriptions.write().unwrap().remove(&id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscriptions.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn send<T: serde::Serialize>(&self, subscription: SubscriptionId, method: &str, result: T) {
+        let json = serde_json::to_string(&NotificationMessage {
+            jsonrpc: "2.0",
+            method,
+            params: NotificationParams {
+                result,
+                subscription,
+            },
+        })
+        .expect("notification serializes");
+        // fails only if no connection is open
+        let _ = self
+            .sender
+            .send(Arc::new(Notification { subscription, json }));
+    }
+
+    fn notify_signature(&self, id: SubscriptionId, bank: &Bank, err: Option<TransactionError>) {
+        self.send(
+            id,
+            "signatureNotification",
+            RpcResponse {
+                context: RpcResponseContext::new(bank.slot()),
+                value: RpcSignatureResult::ProcessedSignature(ProcessedSignatureResult { err }),
+            },
+        );
+    }
+
+    fn notify_slot(&self, slot_info: SlotInfo) {
+        let subscriptions = self.subscriptions.read().unwrap();
+        for (id, params) in subscriptions.iter() {
+            if let SubscriptionParams::Slot = params {
+                self.send(*id, "slotNotification", slot_info);
+            }
+        }
+    }
+
+    /// Notifies the subscriptions at `commitment` about the transactions of `blocks`. Accounts
+    /// are read from `bank`, the latest of the blocks.
+    fn notify_blocks(
+        &self,
+        commitment: SubscriptionCommitment,
+        bank: &Bank,
+        blocks: &[Arc<Vec<CommittedTransaction>>],
+    ) {
+        let transactions = || blocks.iter().flat_map(|block| block.iter());
+        let written = transactions()
+            .flat_map(|tx| tx.writable_accounts.iter().copied())
+            .collect::<HashSet<_>>();
+        let context = RpcResponseContext::new(bank.slot());
+
+        let mut notified_signatures = vec![];
+        let subscriptions = self.subscriptions.read().unwrap();
+        for (id, params) in subscriptions
+            .iter()
+            .filter(|(_, params)| params.commitment() == Some(commitment))
+        {
+            match params {
+                SubscriptionParams::Account {
+                    pubkey,
+                    encoding,
+                    data_slice,
+                    ..
+                } => {
+                    if !written.contains(pubkey) {
+                        continue;
+                    }
+                    match encode_written_account(bank, pubkey, *encoding, *data_slice) {
+                        Ok(account) => self.send(
+                            *id,
+                            "accountNotification",
+                            RpcResponse {
+                                context: context.clone(),
+                                value: account,
+                            },
+                        ),
+                        Err(err) => warn!("failed to encode account {}: {:?}", pubkey, err),
+                    }
+                }
+                SubscriptionParams::Program {
+                    pubkey,
+                    encoding,
+                    data_slice,
+                    filters,
+                    ..
+                } => {
+                    for key in &written {
+                        let Some(account) = bank.get_account(key) else {
+                            continue;
+                        };
+                        if account.owner() != pubkey
+                            || !filters.iter().all(|filter| filter_allows(filter, &account))
+                        {
+                            continue;
+                        }
+                        match get_encoded_account(bank, key, *encoding, *data_slice, None) {
+                            Ok(Some(account)) => self.send(
+                                *id,
+                                "programNotification",
+                                RpcResponse {
+                                    context: context.clone(),
+                                    value: RpcKeyedAccount {
+                                        pubkey: key.to_string(),
+                                        account,
+                                    },
+                                },
+                            ),
+                            Ok(None) => {}
+                            Err(err) => warn!("failed to encode account {}: {:?}", key, err),
+                        }
+                    }
+                }
+                SubscriptionParams::Signature { signature, .. } => {
+                    if let Some(tx) = transactions().find(|tx| tx.signature == *signature) {
+                        self.notify_signature(*id, bank, tx.err.clone());
+                        notified_signatures.push(*id);
+                    }
+                }
+                SubscriptionParams::Logs { filter, .. } => {
+                    for tx in transactions().filter(|tx| filter.allows(tx)) {
+                        self.send(
+                            *id,
+                            "logsNotification",
+                            RpcResponse {
+                                context: context.clone(),
+                                value: RpcLogsResponse {
+                                    signature: tx.signature.to_string(),
+                                    err: tx.err.clone(),
+                                    logs: tx.log_messages.clone(),
+                                },
+                            },
+                        );
+                    }
+                }
+                SubscriptionParams::Slot => {}
+            }
+        }
+        drop(subscriptions);
+
+        if !notified_signatures.is_empty() {
+            let mut subscriptions = self.subscriptions.write().unwrap();
+            for id in notified_signatures {
+                subscriptions.remove(&id);
+            }
+        }
+    }
+}
+
+/// Closed accounts are notified as empty accounts.
+fn encode_written_account(
+    bank: &Bank,
+    pubkey: &Pubkey,
+    encoding: UiAccountEncoding,
+    data_slice: Option<UiDataSliceConfig>,
+) -> jsonrpc_core::Result<UiAccount> {
+    match get_encoded_account(bank, pubkey, encoding, data_slice, None)? {
+        Some(account) => Ok(account),
+        None => encode_account(&AccountSharedData::default(), pubkey, encoding, data_slice),
+    }
+}
+
+struct PendingBlock {
+    bank_id: u64,
+    transactions: Vec<Arc<Vec<CommittedTransaction>>>,
+}
+
+/// Tracks committed blocks until they are confirmed and rooted.
+struct PendingBlocks {
+    blocks: BTreeMap<Slot, PendingBlock>,
+    confirmed_slot: Slot,
+    root: Slot,
+}
+
+impl PendingBlocks {
+    fn new(subscriptions: &RpcSubscriptions) -> Self {
+        let root = subscriptions.bank_forks.read().unwrap().root();
+        Self {
+            blocks: BTreeMap::new(),
+            confirmed_slot: subscriptions
+                .confirmed_slot
+                .load(Ordering::Relaxed)
+                .max(root),
+            root,
+        }
+    }
+
+    fn handle(&mut self, subscriptions: &RpcSubscriptions, event: CommitEvent) {
+        match event {
+            CommitEvent::Block { bank, transactions } => {
+                let slot = bank.slot();
+                // blocks after this one were abandoned by a reorg
+                self.blocks.split_off(&(slot + 1));
+                self.confirmed_slot = self.confirmed_slot.min(slot.saturating_sub(1));
+                let block = self.blocks.entry(slot).or_insert_with(|| PendingBlock {
+                    bank_id: bank.bank_id(),
+                    transactions: vec![],
+                });
+                if block.bank_id != bank.bank_id() {
+                    // the slot itself was produced again
+                    block.bank_id = bank.bank_id();
+                    block.transactions.clear();
+                }
+                block.transactions.push(transactions.clone());
+                while self.blocks.len() > MAX_PENDING_BLOCKS {
+                    self.blocks.pop_first();
+                }
+
+                subscriptions.notify_slot(SlotInfo {
+                    slot,
+                    parent: bank.parent_slot(),
+                    root: self.root,
+                });
+                subscriptions.notify_blocks(
+                    SubscriptionCommitment::Processed,
+                    &bank,
+                    &[transactions],
+                );
+            }
+            CommitEvent::Confirmed(bank) => {
+                let blocks = self.transactions_between(self.confirmed_slot, bank.slot());
+                self.confirmed_slot = self.confirmed_slot.max(bank.slot());
+                subscriptions.notify_blocks(SubscriptionCommitment::Confirmed, &bank, &blocks);
+            }
+            CommitEvent::Rooted(bank) => {
+                let blocks = self.transactions_between(self.root, bank.slot());
+                self.root = self.root.max(bank.slot());
+                self.blocks = self.blocks.split_off(&(self.root + 1));
+                subscriptions.notify_blocks(SubscriptionCommitment::Finalized, &bank, &blocks);
+            }
+        }
+    }
+
+    /// Transactions of the blocks in `(from, to]`.
+    fn transactions_between(&self, from: Slot, to: Slot) -> Vec<Arc<Vec<CommittedTransaction>>> {
+        if from >= to {
+            return vec![];
+        }
+        self.blocks
+            .range(from + 1..=to)
+            .flat_map(|(_, block)| block.transactions.iter().cloned())
+            .collect()
+    }
+}
+
+/// Turns storage commit events into subscription notifications.
+pub struct CommitNotifier {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl CommitNotifier {
+    pub fn new(
+        subscriptions: Arc<RpcSubscriptions>,
+        commit_events: Receiver<CommitEvent>,
+        exit: Arc<AtomicBool>,
+    ) -> Self {
+        let thread_hdl = Builder::new()
+            .name("solRpcNotifier".to_string())
+            .spawn(move || {
+                let mut pending = PendingBlocks::new(&subscriptions);
+                while !exit.load(Ordering::Relaxed) {
+                    match commit_events.recv_timeout(COMMIT_EVENT_TIMEOUT) {
+                        Ok(event) => pending.handle(&subscriptions, event),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })
+            .unwrap();
+        Self { thread_hdl }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_runtime::genesis_utils::create_genesis_config,
+        solana_sdk::{signature::Keypair, signer::Signer, system_program, system_transaction},
+    };
+
+    struct Setup {
+        subscriptions: RpcSubscriptions,
+        pending: PendingBlocks,
+        receiver: broadcast::Receiver<Arc<Notification>>,
+        bank: Arc<Bank>,
+        mint_keypair: Keypair,
+    }
+
+    fn setup() -> Setup {
+        let genesis = create_genesis_config(1_000_000_000);
+        let bank_forks = BankForks::new_rw_arc(Bank::new_for_tests(&genesis.genesis_config));
+        let bank = bank_forks.read().unwrap().root_bank();
+        let subscriptions = RpcSubscriptions::new(bank_forks, Arc::new(AtomicU64::new(0)), 128);
+        let pending = PendingBlocks::new(&subscriptions);
+        let receiver = subscriptions.broadcast_receiver();
+        Setup {
+            subscriptions,
+            pending,
+            receiver,
+            bank,
+            mint_keypair: genesis.mint_keypair,
+        }
+    }
+
+    fn committed(signature: Signature, writable_accounts: Vec<Pubkey>) -> CommittedTransaction {
+        CommittedTransaction {
+            signature,
+            err: None,
+            log_messages: vec!["Program log: hello".to_string()],
+            account_keys: writable_accounts.clone(),
+            writable_accounts,
+            is_vote: false,
+        }
+    }
+
+    fn next_json(receiver: &mut broadcast::Receiver<Arc<Notification>>) -> serde_json::Value {
+        let notification = receiver.try_recv().unwrap();
+        serde_json::from_str(&notification.json).unwrap()
+    }
+
+    #[test]
+    fn test_notify_processed_and_confirmed() {
+        let Setup {
+            subscriptions,
+            mut pending,
+            mut receiver,
+            bank,
+            ..
+        } = setup();
+        let pubkey = Keypair::new().pubkey();
+        bank.store_account(
+            &pubkey,
+            &AccountSharedData::new(42, 0, &system_program::id()),
+        );
+
+        let slot_id = subscriptions.subscribe(SubscriptionParams::Slot);
+        let account_id = subscriptions.subscribe(SubscriptionParams::Account {
+            pubkey,
+            encoding: UiAccountEncoding::Base64,
+            data_slice: None,
+            commitment: SubscriptionCommitment::Confirmed,
+        });
+        let signature = Signature::new_unique();
+        let signature_id = subscriptions.subscribe(SubscriptionParams::Signature {
+            signature,
+            commitment: SubscriptionCommitment::Processed,
+        });
+        let logs_id = subscriptions.subscribe(SubscriptionParams::Logs {
+            filter: LogsFilter::Mentions(pubkey),
+            commitment: SubscriptionCommitment::Processed,
+        });
+        assert_eq!(subscriptions.len(), 4);
+
+        let bank1 = Arc::new(Bank::new_from_parent(bank, &Pubkey::default(), 1));
+        pending.handle(
+            &subscriptions,
+            CommitEvent::Block {
+                bank: bank1.clone(),
+                transactions: Arc::new(vec![committed(signature, vec![pubkey])]),
+            },
+        );
+
+        let mut notified = HashMap::new();
+        while let Ok(notification) = receiver.try_recv() {
+            let json: serde_json::Value = serde_json::from_str(&notification.json).unwrap();
+            notified.insert(notification.subscription, json);
+        }
+        assert_eq!(notified.len(), 3);
+        assert_eq!(notified[&slot_id]["method"], "slotNotification");
+        assert_eq!(notified[&slot_id]["params"]["result"]["slot"], 1);
+        assert_eq!(notified[&slot_id]["params"]["result"]["parent"], 0);
+        assert_eq!(
+            notified[&signature_id]["params"]["result"]["value"],
+            json!({ "err": null })
+        );
+        assert_eq!(
+            notified[&logs_id]["params"]["result"]["value"]["logs"],
+            json!(["Program log: hello"])
+        );
+        // signature subscriptions end with their notification
+        assert_eq!(subscriptions.len(), 3);
+
+        pending.handle(&subscriptions, CommitEvent::Confirmed(bank1));
+        let json = next_json(&mut receiver);
+        assert_eq!(json["method"], "accountNotification");
+        assert_eq!(json["params"]["subscription"], account_id);
+        assert_eq!(json["params"]["result"]["context"]["slot"], 1);
+        assert_eq!(json["params"]["result"]["value"]["lamports"], 42);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_reorged_blocks_are_not_finalized() {
+        let Setup {
+            subscriptions,
+            mut pending,
+            mut receiver,
+            bank,
+            ..
+        } = setup();
+        let signature = Signature::new_unique();
+        subscriptions.subscribe(SubscriptionParams::Signature {
+            signature,
+            commitment: SubscriptionCommitment::Finalized,
+        });
+
+        let bank1 = Arc::new(Bank::new_from_parent(bank.clone(), &Pubkey::default(), 1));
+        pending.handle(
+            &subscriptions,
+            CommitEvent::Block {
+                bank: bank1,
+                transactions: Arc::new(vec![committed(signature, vec![])]),
+            },
+        );
+        // slot 1 is produced again without the transaction
+        let bank1 = Arc::new(Bank::new_from_parent(bank, &Pubkey::default(), 1));
+        pending.handle(
+            &subscriptions,
+            CommitEvent::Block {
+                bank: bank1.clone(),
+                transactions: Arc::new(vec![]),
+            },
+        );
+        while receiver.try_recv().is_ok() {}
+
+        pending.handle(&subscriptions, CommitEvent::Rooted(bank1));
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(subscriptions.len(), 1);
+        assert!(pending.blocks.is_empty());
+    }
+
+    #[test]
+    fn test_signature_landed_before_subscribing() {
+        let Setup {
+            subscriptions,
+            mut receiver,
+            bank,
+            mint_keypair,
+            ..
+        } = setup();
+        let tx = system_transaction::transfer(
+            &mint_keypair,
+            &Pubkey::new_unique(),
+            bank.get_minimum_balance_for_rent_exemption(0),
+            bank.last_blockhash(),
+        );
+        bank.process_transaction(&tx).unwrap();
+
+        let id = subscriptions.subscribe(SubscriptionParams::Signature {
+            signature: tx.signatures[0],
+            commitment: SubscriptionCommitment::Processed,
+        });
+        let json = next_json(&mut receiver);
+        assert_eq!(json["params"]["subscription"], id);
+        assert_eq!(json["params"]["result"]["value"], json!({ "err": null }));
+        assert!(subscriptions.is_empty());
+    }
+}
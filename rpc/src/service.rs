@@ -1,8 +1,10 @@
 use crate::jsonrpc::core::JsonRpcConfig;
+use crate::jsonrpc::faucet::{FaucetConfig, LocalFaucet};
 use crate::jsonrpc::service::JsonRpcService;
+use crate::pubsub::service::{PubSubConfig, PubSubService};
 use crate::Result;
 use crossbeam_channel::{Receiver, Sender};
-use igloo_storage::RollupStorage;
+use igloo_storage::{state::StateCommitment, RollupStorage};
 use solana_ledger::blockstore::Blockstore;
 use solana_runtime::bank_forks::BankForks;
 use solana_runtime::prioritization_fee_cache::PrioritizationFeeCache;
@@ -13,12 +15,15 @@ use solana_sdk::transaction::SanitizedTransaction;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::path::Path;
 use std::sync::atomic::AtomicU64;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[derive(Debug, Clone)]
 pub struct RpcConfig {
     pub rpc_addr: SocketAddr,
     pub jsonrpc_config: JsonRpcConfig,
+    /// Websocket subscriptions are disabled if not set.
+    pub pubsub_addr: Option<SocketAddr>,
+    pub pubsub_config: PubSubConfig,
 }
 
 impl Default for RpcConfig {
@@ -26,6 +31,8 @@ impl Default for RpcConfig {
         Self {
             rpc_addr: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8899)),
             jsonrpc_config: JsonRpcConfig::default(),
+            pubsub_addr: Some(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8900))),
+            pubsub_config: PubSubConfig::default(),
         }
     }
 }
@@ -35,6 +42,7 @@ pub struct RpcService {
     // transactions to TPU. We do not consider to make the rpc service independently right now, the
     // exposed transaction receiver below should be used by transaction stream module directly.
     pub jsonrpc: JsonRpcService,
+    pub pubsub: Option<PubSubService>,
 }
 
 impl RpcService {
@@ -43,9 +51,20 @@ impl RpcService {
         rpc_config: RpcConfig,
         tx_channel: (Sender<SanitizedTransaction>, Receiver<SanitizedTransaction>),
         node_exit: Arc<RwLock<Exit>>,
-        storage: &RollupStorage,
+        storage: &mut RollupStorage,
     ) -> Result<Self> {
+        let commit_events = rpc_config
+            .pubsub_addr
+            .map(|pubsub_addr| (pubsub_addr, storage.subscribe_commit_events()));
         let storage_config = storage.config();
+        // dev clusters get a local faucet out of the box
+        let faucet = rpc_config
+            .jsonrpc_config
+            .faucet
+            .clone()
+            .or_else(|| storage_config.dev_mode.then(FaucetConfig::default))
+            .zip(storage_config.keypairs.mint_keypair.clone())
+            .map(|(config, keypair)| Arc::new(LocalFaucet::new(keypair, config)));
         let jsonrpc = Self::new_rpc_service(
             &rpc_config,
             storage_config.storage.snapshot_config.clone(),
@@ -62,7 +81,23 @@ impl RpcService {
                 .history_services()
                 .max_complete_transaction_status_slot
                 .clone(),
+            storage.confirmed_slot_tracker(),
+            faucet,
+            storage.state_commitment(),
         )?;
+        let pubsub = commit_events
+            .map(|(pubsub_addr, commit_events)| {
+                PubSubService::new(
+                    pubsub_addr,
+                    rpc_config.pubsub_config.clone(),
+                    storage.bank_forks(),
+                    storage.confirmed_slot_tracker(),
+                    commit_events,
+                    node_exit.clone(),
+                )
+                .map_err(crate::Error::InitPubSub)
+            })
+            .transpose()?;
 
         if storage_config.storage.halt_at_slot.is_some() {
             // Park with the RPC service running, ready for inspection!
@@ -70,11 +105,14 @@ impl RpcService {
             std::thread::park();
         }
 
-        Ok(Self { jsonrpc })
+        Ok(Self { jsonrpc, pubsub })
     }
 
     pub fn join(self) {
         self.jsonrpc.join().expect("jsonrpc_service");
+        if let Some(pubsub) = self.pubsub {
+            pubsub.join().expect("pubsub_service");
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -88,6 +126,9 @@ impl RpcService {
         ledger_path: &Path,
         node_exit: Arc<RwLock<Exit>>,
         max_complete_transaction_status_slot: Arc<AtomicU64>,
+        confirmed_slot: Arc<AtomicU64>,
+        faucet: Option<Arc<LocalFaucet>>,
+        state_commitment: Arc<Mutex<StateCommitment>>,
     ) -> Result<JsonRpcService> {
         // block min prioritization fee cache should be readable by RPC, and writable by validator
         // (by both replay stage and banking stage)
@@ -104,7 +145,10 @@ impl RpcService {
             ledger_path,
             node_exit,
             max_complete_transaction_status_slot.clone(),
+            confirmed_slot,
             prioritization_fee_cache,
+            faucet,
+            Some(state_commitment),
         )
         .map_err(crate::Error::InitJsonRpc)
     }
@@ -114,8 +158,9 @@ impl RpcService {
 mod tests {
     use super::*;
     use anyhow::Result;
-    use igloo_storage::config::GlobalConfig;
+    use igloo_storage::{config::GlobalConfig, error::BootstrapError};
     use solana_client::rpc_client::RpcClient;
+    use solana_sdk::genesis_config::DEFAULT_GENESIS_FILE;
 
     fn new_rpc_service(storage: &mut RollupStorage, rpc_config: RpcConfig) -> Result<RpcService> {
         let node_exit = Arc::new(RwLock::new(Exit::default()));
@@ -150,4 +195,70 @@ mod tests {
         rpc_service.join();
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_bootstrap_from_peer() -> Result<()> {
+        let ledger_path = tempfile::tempdir()?.into_path();
+        let mut storage = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+        storage.init()?;
+        for slot in 1..=2 {
+            storage.bump()?;
+            storage.force_save().await?;
+            if slot == 1 {
+                storage.snapshot(None)?;
+            } else {
+                storage.incremental_snapshot(None)?;
+            }
+        }
+
+        let rpc_config = RpcConfig {
+            rpc_addr: "127.0.0.1:0".parse()?,
+            pubsub_addr: None,
+            ..Default::default()
+        };
+        let rpc_service = new_rpc_service(&mut storage, rpc_config)?;
+        let peer_url = format!("http://{}", rpc_service.jsonrpc.address());
+        let new_config = |expected_genesis_hash| -> Result<GlobalConfig> {
+            let mut config = GlobalConfig::new(&tempfile::tempdir()?.into_path())?;
+            config.keypairs = storage.config().keypairs.clone();
+            config.storage.expected_genesis_hash = Some(expected_genesis_hash);
+            Ok(config)
+        };
+
+        assert!(matches!(
+            RollupStorage::bootstrap_from_peer(new_config(Hash::new_unique())?, &peer_url).await,
+            Err(igloo_storage::Error::BootstrapError(
+                BootstrapError::GenesisHashMismatch { .. }
+            ))
+        ));
+
+        // a genesis already in the ledger is checked as well
+        let config = new_config(Hash::new_unique())?;
+        std::fs::copy(
+            ledger_path.join(DEFAULT_GENESIS_FILE),
+            config.ledger_path.join(DEFAULT_GENESIS_FILE),
+        )?;
+        assert!(matches!(
+            RollupStorage::bootstrap_from_peer(config, &peer_url).await,
+            Err(igloo_storage::Error::BootstrapError(
+                BootstrapError::GenesisHashMismatch { .. }
+            ))
+        ));
+
+        let mut node = RollupStorage::bootstrap_from_peer(
+            new_config(storage.config().genesis.hash())?,
+            &peer_url,
+        )
+        .await?;
+        node.init()?;
+        assert_eq!(node.get_root(), 2);
+        assert_eq!(node.current_bank().hash(), storage.current_bank().hash());
+        assert_eq!(node.full_snapshot_archives()[0].slot, 1);
+        assert_eq!(node.incremental_snapshot_archives()[0].slot, 2);
+        node.close().await?;
+
+        rpc_service.join();
+        storage.close().await?;
+        Ok(())
+    }
 }
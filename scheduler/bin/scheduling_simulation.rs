@@ -1,6 +1,6 @@
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use igloo_executor::processor::TransactionProcessor;
 use igloo_scheduler::id_generator::IdGenerator;
+use igloo_scheduler::impls::no_lock_scheduler::NoLockScheduler;
 use igloo_scheduler::impls::prio_graph_scheduler::PrioGraphSchedulerWrapper;
 use igloo_scheduler::scheduler::Scheduler;
 use igloo_scheduler::scheduler_messages::{MaxAge, SchedulingBatch, SchedulingBatchResult};
@@ -8,6 +8,7 @@ use igloo_scheduler::status_slicing::{
     calculate_thread_load_summary, SvmWorkerSlicingStatus, WorkerStatusUpdate,
 };
 use igloo_scheduler::stopwatch::StopWatch;
+use igloo_scheduler::worker::Worker;
 use igloo_storage::{config::GlobalConfig, RollupStorage};
 use igloo_verifier::settings::{Settings, Switchs};
 use itertools::Itertools;
@@ -17,7 +18,6 @@ use solana_sdk::transaction::SanitizedTransaction;
 use solana_sdk::{
     pubkey::Pubkey, signature::Keypair, signer::Signer, system_program, system_transaction,
 };
-use std::borrow::Cow;
 use std::error::Error;
 use std::sync::Arc;
 use std::thread;
@@ -72,7 +72,7 @@ fn worker_process(
     status_sender: Sender<WorkerStatusUpdate>,
     completed_sender: Sender<SchedulingBatchResult>,
 ) -> Result<usize, E> {
-    let bank_processor = TransactionProcessor::new(store.current_bank(), settings);
+    let worker = Worker::new(store.current_bank(), settings, completed_sender);
     let mut success_count = 0;
     let mut idle_start = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -90,9 +90,10 @@ fn worker_process(
             thread_id,
             status: idle_status,
         })?;
-        // Process transactions
-        let execute_result = bank_processor.process(Cow::Borrowed(&scheduled_txs.transactions))?;
-        success_count += execute_result
+        // Process transactions, the retryable ones go back to the scheduler
+        let worker_output = worker.process(scheduled_txs)?;
+        success_count += worker_output
+            .output
             .execution_results
             .iter()
             .filter(|x| x.was_executed_successfully())
@@ -110,18 +111,6 @@ fn worker_process(
             eprintln!("send status error: {:?}", e);
         }
 
-        // TODO retryable_indexes logic
-        let result = SchedulingBatchResult {
-            batch: scheduled_txs,
-            retryable_indexes: vec![],
-        };
-
-        // it's ok to ignore send error.
-        // because error is handled by the scheduler.
-        // if scheduler exits, means all task is scheduled.
-        // no need to maintain channel now.
-        let _ = completed_sender.send(result);
-
         // Update idle_start for next iteration
         idle_start = active_end;
     }
@@ -129,6 +118,23 @@ fn worker_process(
     Ok(success_count)
 }
 
+fn schedule_all(mut scheduler: impl Scheduler, batches: Vec<SchedulingBatch>) -> Result<(), E> {
+    for batch in batches {
+        scheduler.schedule_batch(batch)?;
+        scheduler.receive_complete()?;
+    }
+    // batches still waiting in the scheduler are not carried anywhere
+    let unscheduled = scheduler
+        .flush()?
+        .iter()
+        .map(|batch| batch.ids.len())
+        .sum::<usize>();
+    if unscheduled > 0 {
+        println!("{unscheduled} transactions left unscheduled");
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), E> {
     let mut stopwatch = StopWatch::new("scheduling_simulation");
 
@@ -200,8 +206,7 @@ fn main() -> Result<(), E> {
     let mut batch_id_gen = IdGenerator::default();
     let mut tx_id_gen = IdGenerator::default();
 
-    let mut scheduler = PrioGraphSchedulerWrapper::new(senders.clone(), completed_receiver);
-    for chunk in transfer_txs
+    let batches = transfer_txs
         .into_iter()
         .chunks(SCHEDULER_BATCH_SIZE)
         .into_iter()
@@ -219,15 +224,18 @@ fn main() -> Result<(), E> {
                 max_ages: vec![MaxAge::default(); len],
             }
         })
-        .collect::<Vec<SchedulingBatch>>()
-    {
-        scheduler.schedule_batch(chunk)?;
-        scheduler.receive_complete()?;
+        .collect::<Vec<SchedulingBatch>>();
+    // `--no-lock` runs the baseline scheduler for comparison
+    if std::env::args().any(|arg| arg == "--no-lock") {
+        let scheduler = NoLockScheduler::new(senders.clone(), completed_receiver);
+        schedule_all(scheduler, batches)?;
+    } else {
+        let scheduler = PrioGraphSchedulerWrapper::new(senders.clone(), completed_receiver);
+        schedule_all(scheduler, batches)?;
     }
 
     // Close senders to signal workers to finish
     drop(senders);
-    drop(scheduler);
     drop(status_sender);
 
     // Wait for workers to finish and collect results
@@ -1,45 +1,342 @@
+use crate::impls::prio_graph_scheduler::in_flight_tracker::InFlightTracker;
 use crate::impls::prio_graph_scheduler::scheduler_error::SchedulerError;
 use crate::scheduler::Scheduler;
 use crate::scheduler_messages::{SchedulingBatch, SchedulingBatchResult};
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, Sender, TryRecvError, TrySendError};
+use std::collections::VecDeque;
 
-/// NoLockScheduler is a dummy scheduler that does not lock any resources.
+/// Max number of transactions sent to a worker at once.
+pub const TARGET_BATCH_SIZE: usize = 64;
+
+/// NoLockScheduler is a baseline scheduler that does not lock any resources.
+///
+/// Transactions are sent in arrival order, in batches of `TARGET_BATCH_SIZE`,
+/// to the worker with the fewest transactions in flight, ties going
+/// round-robin. When the worker channels are bounded and all of them are full,
+/// batches wait until some work completes, `flush` sends them before the
+/// scheduler is dropped.
 pub struct NoLockScheduler {
-    thread_num: usize,
     task_senders: Vec<Sender<SchedulingBatch>>,
+    task_finished_receiver: Receiver<SchedulingBatchResult>,
+    in_flight_tracker: InFlightTracker,
+    /// Worker that wins the next tie.
+    next_worker: usize,
+    /// Batches waiting for room in a worker channel, their ids are only
+    /// assigned once sent.
+    pending: VecDeque<SchedulingBatch>,
 }
 
 impl Scheduler for NoLockScheduler {
     fn new(
         schedule_task_senders: Vec<Sender<SchedulingBatch>>,
-        _: Receiver<SchedulingBatchResult>,
+        task_finished_receiver: Receiver<SchedulingBatchResult>,
     ) -> Self {
         Self {
-            thread_num: schedule_task_senders.len(),
+            in_flight_tracker: InFlightTracker::new(schedule_task_senders.len()),
             task_senders: schedule_task_senders,
+            task_finished_receiver,
+            next_worker: 0,
+            pending: VecDeque::new(),
         }
     }
 
     fn schedule_batch(&mut self, txs: SchedulingBatch) -> Result<(), SchedulerError> {
-        let exec_batch = 64;
-        txs.transactions
-            .chunks(exec_batch)
-            .enumerate()
-            .for_each(|(i, chunk)| {
-                let worker_id =
-                    (txs.batch_id.value() as usize % self.thread_num + i) % self.thread_num;
-                let batch = SchedulingBatch {
-                    batch_id: txs.batch_id,
-                    ids: txs.ids[i * exec_batch..(i + 1) * exec_batch].to_vec(),
-                    transactions: chunk.to_vec(),
-                    max_ages: vec![],
-                };
-                self.task_senders[worker_id].send(batch).unwrap();
+        if !txs.valid() {
+            return Err(SchedulerError::InvalidBatch(txs.batch_id));
+        }
+
+        let SchedulingBatch {
+            batch_id,
+            mut ids,
+            mut transactions,
+            mut max_ages,
+        } = txs;
+        while !ids.is_empty() {
+            let len = ids.len().min(TARGET_BATCH_SIZE);
+            self.pending.push_back(SchedulingBatch {
+                batch_id,
+                ids: ids.drain(..len).collect(),
+                transactions: transactions.drain(..len).collect(),
+                max_ages: max_ages.drain(..len).collect(),
             });
-        Ok(())
+        }
+        self.send_pending()
     }
 
+    /// Stops tracking the completed batches, and queues their retryable
+    /// transactions to be sent again.
     fn receive_complete(&mut self) -> Result<(), SchedulerError> {
+        loop {
+            match self.task_finished_receiver.try_recv() {
+                Ok(result) => self.complete(result),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(SchedulerError::DisconnectedRecvChannel(
+                        "finished consume work",
+                    ))
+                }
+            }
+        }
+        self.send_pending()
+    }
+
+    /// Sends every pending batch, waiting for work to complete while the
+    /// worker channels are full. Retryable transactions of the batches still
+    /// in flight are not waited for.
+    fn flush(&mut self) -> Result<Vec<SchedulingBatch>, SchedulerError> {
+        self.receive_complete()?;
+        while !self.pending.is_empty() {
+            let result = self
+                .task_finished_receiver
+                .recv()
+                .map_err(|_| SchedulerError::DisconnectedRecvChannel("finished consume work"))?;
+            self.complete(result);
+            self.receive_complete()?;
+        }
+        Ok(vec![])
+    }
+}
+
+impl NoLockScheduler {
+    /// Number of transactions waiting for room in a worker channel.
+    pub fn num_pending(&self) -> usize {
+        self.pending.iter().map(|batch| batch.ids.len()).sum()
+    }
+
+    /// Returns the number of transactions in flight for each worker.
+    pub fn num_in_flight_per_worker(&self) -> &[usize] {
+        self.in_flight_tracker.num_in_flight_per_thread()
+    }
+
+    /// Stops tracking a completed batch, and queues its retryable transactions.
+    fn complete(&mut self, result: SchedulingBatchResult) {
+        let SchedulingBatchResult {
+            batch,
+            retryable_indexes,
+        } = result;
+        self.in_flight_tracker.complete_batch(batch.batch_id);
+        if retryable_indexes.is_empty() {
+            return;
+        }
+
+        let mut retry = SchedulingBatch {
+            batch_id: batch.batch_id,
+            ids: Vec::with_capacity(retryable_indexes.len()),
+            transactions: Vec::with_capacity(retryable_indexes.len()),
+            max_ages: Vec::with_capacity(retryable_indexes.len()),
+        };
+        for index in retryable_indexes {
+            retry.ids.push(batch.ids[index]);
+            retry.transactions.push(batch.transactions[index].clone());
+            retry.max_ages.push(batch.max_ages[index]);
+        }
+        self.pending.push_back(retry);
+    }
+
+    /// Sends pending batches in order, until all worker channels are full.
+    fn send_pending(&mut self) -> Result<(), SchedulerError> {
+        while let Some(batch) = self.pending.pop_front() {
+            if let Some(batch) = self.try_send(batch)? {
+                self.pending.push_front(batch);
+                break;
+            }
+        }
         Ok(())
     }
+
+    /// Sends the batch to the least loaded worker with room for it, or hands
+    /// it back if there is none.
+    fn try_send(
+        &mut self,
+        mut batch: SchedulingBatch,
+    ) -> Result<Option<SchedulingBatch>, SchedulerError> {
+        let num_workers = self.task_senders.len();
+        let mut workers = (0..num_workers)
+            .map(|i| (self.next_worker + i) % num_workers)
+            .collect::<Vec<_>>();
+        // the sort is stable, ties keep the round-robin order
+        workers.sort_by_key(|worker| self.num_in_flight_per_worker()[*worker]);
+
+        for worker in workers {
+            batch.batch_id = self
+                .in_flight_tracker
+                .track_batch(batch.ids.len(), 0, worker);
+            match self.task_senders[worker].try_send(batch) {
+                Ok(()) => {
+                    self.next_worker = (worker + 1) % num_workers;
+                    return Ok(None);
+                }
+                Err(TrySendError::Full(full)) => {
+                    self.in_flight_tracker.complete_batch(full.batch_id);
+                    batch = full;
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    return Err(SchedulerError::DisconnectedSendChannel(
+                        "consume work sender",
+                    ))
+                }
+            }
+        }
+        Ok(Some(batch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::scheduler_messages::{MaxAge, TransactionBatchId, TransactionId},
+        crossbeam_channel::{bounded, unbounded},
+        solana_sdk::{
+            hash::Hash, pubkey::Pubkey, signature::Keypair, system_transaction,
+            transaction::SanitizedTransaction,
+        },
+    };
+
+    fn create_batch(first_id: u64, len: usize) -> SchedulingBatch {
+        let keypair = Keypair::new();
+        SchedulingBatch {
+            batch_id: TransactionBatchId::new(0),
+            ids: (first_id..first_id + len as u64)
+                .map(TransactionId::new)
+                .collect(),
+            transactions: (0..len)
+                .map(|_| {
+                    SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                        &keypair,
+                        &Pubkey::new_unique(),
+                        1,
+                        Hash::default(),
+                    ))
+                })
+                .collect(),
+            max_ages: (first_id..first_id + len as u64)
+                .map(|slot| MaxAge {
+                    epoch_invalidation_slot: slot,
+                    alt_invalidation_slot: slot,
+                })
+                .collect(),
+        }
+    }
+
+    fn complete(batch: SchedulingBatch, retryable_indexes: Vec<usize>) -> SchedulingBatchResult {
+        SchedulingBatchResult {
+            batch,
+            retryable_indexes,
+        }
+    }
+
+    #[test]
+    fn test_schedule_partial_batches() {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..2).map(|_| unbounded()).unzip();
+        let (_finished_sender, finished_receiver) = unbounded();
+        let mut scheduler = NoLockScheduler::new(senders, finished_receiver);
+
+        scheduler.schedule_batch(create_batch(0, 100)).unwrap();
+        let first = receivers[0].try_recv().unwrap();
+        let second = receivers[1].try_recv().unwrap();
+        assert!(first.valid() && second.valid());
+        assert_eq!(first.ids.len(), TARGET_BATCH_SIZE);
+        assert_eq!(second.ids.len(), 100 - TARGET_BATCH_SIZE);
+        assert_eq!(second.ids[0], TransactionId::new(TARGET_BATCH_SIZE as u64));
+        assert_eq!(
+            second.max_ages[0].epoch_invalidation_slot,
+            TARGET_BATCH_SIZE as u64
+        );
+        assert_eq!(
+            scheduler.num_in_flight_per_worker(),
+            &[TARGET_BATCH_SIZE, 100 - TARGET_BATCH_SIZE]
+        );
+
+        // the least loaded worker gets the next batch
+        scheduler.schedule_batch(create_batch(100, 10)).unwrap();
+        assert_eq!(receivers[1].try_recv().unwrap().ids.len(), 10);
+        assert!(receivers[0].is_empty());
+    }
+
+    #[test]
+    fn test_schedule_invalid_batch() {
+        let (sender, _receiver) = unbounded();
+        let (_finished_sender, finished_receiver) = unbounded();
+        let mut scheduler = NoLockScheduler::new(vec![sender], finished_receiver);
+
+        let mut batch = create_batch(0, 2);
+        batch.max_ages.clear();
+        assert!(matches!(
+            scheduler.schedule_batch(batch),
+            Err(SchedulerError::InvalidBatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_schedule_backpressure() {
+        let (sender, receiver) = bounded(1);
+        let (finished_sender, finished_receiver) = unbounded();
+        let mut scheduler = NoLockScheduler::new(vec![sender], finished_receiver);
+
+        scheduler
+            .schedule_batch(create_batch(0, 2 * TARGET_BATCH_SIZE + 1))
+            .unwrap();
+        assert_eq!(scheduler.num_pending(), TARGET_BATCH_SIZE + 1);
+        let batch = receiver.try_recv().unwrap();
+        assert!(receiver.is_empty());
+
+        // completing work makes room for the pending batches, in order
+        finished_sender.send(complete(batch, vec![])).unwrap();
+        scheduler.receive_complete().unwrap();
+        assert_eq!(scheduler.num_pending(), 1);
+        let batch = receiver.try_recv().unwrap();
+        assert_eq!(batch.ids[0], TransactionId::new(TARGET_BATCH_SIZE as u64));
+        assert_eq!(scheduler.num_in_flight_per_worker(), &[TARGET_BATCH_SIZE]);
+
+        finished_sender.send(complete(batch, vec![])).unwrap();
+        scheduler.receive_complete().unwrap();
+        assert_eq!(scheduler.num_pending(), 0);
+        assert_eq!(receiver.try_recv().unwrap().ids.len(), 1);
+    }
+
+    #[test]
+    fn test_flush_waits_for_room() {
+        let (sender, receiver) = bounded(1);
+        let (finished_sender, finished_receiver) = unbounded();
+        let mut scheduler = NoLockScheduler::new(vec![sender], finished_receiver);
+        let worker = std::thread::spawn(move || {
+            let mut received = 0;
+            while let Ok(batch) = receiver.recv() {
+                received += batch.ids.len();
+                // gone once the scheduler is dropped
+                let _ = finished_sender.send(complete(batch, vec![]));
+            }
+            received
+        });
+
+        scheduler
+            .schedule_batch(create_batch(0, 3 * TARGET_BATCH_SIZE))
+            .unwrap();
+        assert!(scheduler.flush().unwrap().is_empty());
+        assert_eq!(scheduler.num_pending(), 0);
+        drop(scheduler);
+        assert_eq!(worker.join().unwrap(), 3 * TARGET_BATCH_SIZE);
+    }
+
+    #[test]
+    fn test_retryable_transactions_are_sent_again() {
+        let (sender, receiver) = unbounded();
+        let (finished_sender, finished_receiver) = unbounded();
+        let mut scheduler = NoLockScheduler::new(vec![sender], finished_receiver);
+
+        scheduler.schedule_batch(create_batch(0, 3)).unwrap();
+        let batch = receiver.try_recv().unwrap();
+        finished_sender.send(complete(batch, vec![0, 2])).unwrap();
+        scheduler.receive_complete().unwrap();
+
+        let retry = receiver.try_recv().unwrap();
+        assert!(retry.valid());
+        assert_eq!(
+            retry.ids,
+            vec![TransactionId::new(0), TransactionId::new(2)]
+        );
+        assert_eq!(retry.max_ages[1].epoch_invalidation_slot, 2);
+        assert_eq!(scheduler.num_in_flight_per_worker(), &[2]);
+    }
 }
@@ -4,23 +4,67 @@ pub mod scheduler;
 pub mod scheduler_error;
 pub mod scheduler_metrics;
 pub mod thread_aware_account_locks;
+pub mod transaction_filter;
 pub mod transaction_priority_id;
 pub mod transaction_state;
 pub mod transaction_state_container;
 
 use crate::impls::prio_graph_scheduler::scheduler::PrioGraphScheduler;
 use crate::impls::prio_graph_scheduler::scheduler_error::SchedulerError;
+use crate::impls::prio_graph_scheduler::scheduler_metrics::SchedulerCountMetrics;
+use crate::impls::prio_graph_scheduler::transaction_filter::{
+    pre_graph_filter, pre_lock_filter, FilterCounts,
+};
 use crate::impls::prio_graph_scheduler::transaction_state::SanitizedTransactionTTL;
 use crate::impls::prio_graph_scheduler::transaction_state_container::TransactionStateContainer;
 use crate::scheduler::Scheduler;
-use crate::scheduler_messages::{SchedulingBatch, SchedulingBatchResult};
+use crate::scheduler_messages::{SchedulingBatch, SchedulingBatchResult, TransactionBatchId};
 use crossbeam_channel::{Receiver, Sender};
+use solana_compute_budget::compute_budget_processor::process_compute_budget_instructions;
+use solana_cost_model::cost_model::CostModel;
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    feature_set::FeatureSet,
+    fee::FeeBudgetLimits,
+    saturating_add_assign,
+    signature::Signature,
+    transaction::{SanitizedTransaction, TransactionError},
+};
+use std::sync::Arc;
 
 pub const TARGET_NUM_TRANSACTIONS_PER_BATCH: usize = 128;
+/// Max number of transactions the wrapper keeps in its container, lowest
+/// priority transactions are dropped once it is full.
+pub const CONTAINER_CAPACITY: usize = 10240;
+/// Multiplier of the fee before it is divided by the cost, so that the priority
+/// of transactions paying less lamports than their cost is not rounded down to 0.
+const PRIORITY_MULTIPLIER: u64 = 1_000_000;
 
 pub struct PrioGraphSchedulerWrapper {
     inner: PrioGraphScheduler,
     container: TransactionStateContainer,
+    feature_set: FeatureSet,
+    /// Working bank the transactions are filtered against, nothing is
+    /// filtered without one.
+    bank: Option<Arc<Bank>>,
+    /// Transactions dropped by the filters since the last `take_dropped`.
+    dropped: Vec<(Signature, TransactionError)>,
+    count_metrics: SchedulerCountMetrics,
+}
+
+impl PrioGraphSchedulerWrapper {
+    /// Signatures of the transactions dropped by the filters, with the reason
+    /// they were dropped for. They are removed from the scheduler and never
+    /// sent to a worker.
+    pub fn take_dropped(&mut self) -> Vec<(Signature, TransactionError)> {
+        std::mem::take(&mut self.dropped)
+    }
+
+    /// Number of transactions waiting to be scheduled, the ones sent to a
+    /// worker are not counted.
+    pub fn num_queued(&self) -> usize {
+        self.container.len()
+    }
 }
 
 impl Scheduler for PrioGraphSchedulerWrapper {
@@ -30,41 +74,266 @@ impl Scheduler for PrioGraphSchedulerWrapper {
     ) -> Self {
         Self {
             inner: PrioGraphScheduler::new(schedule_task_senders, task_finished_receivers),
-            container: TransactionStateContainer::with_capacity(10240),
+            container: TransactionStateContainer::with_capacity(CONTAINER_CAPACITY),
+            feature_set: FeatureSet::all_enabled(),
+            bank: None,
+            dropped: vec![],
+            count_metrics: SchedulerCountMetrics::default(),
         }
     }
 
     fn schedule_batch(&mut self, mut txs: SchedulingBatch) -> Result<(), SchedulerError> {
+        self.count_metrics
+            .maybe_report_and_reset_slot(self.bank.as_ref().map(|bank| bank.slot()));
+        let should_report = self.count_metrics.interval_has_data();
+        self.count_metrics
+            .maybe_report_and_reset_interval(should_report);
+
         for ((tx, tx_id), max_age) in txs
             .transactions
             .drain(..)
             .zip(txs.ids.drain(..))
             .zip(txs.max_ages.drain(..))
         {
+            let (priority, cost) = calculate_priority_and_cost(&tx, &self.feature_set);
             self.container.insert_new_transaction(
                 tx_id,
                 SanitizedTransactionTTL {
                     transaction: tx,
                     max_age,
                 },
-                // TODO migrate priority
-                0,
-                100,
+                priority,
+                cost,
             );
         }
+        self.schedule_queued()
+    }
 
-        self.inner.schedule(
-            &mut self.container,
-            // TODO: migrate pre-filter transactions
-            |_, result| result.fill(true),
-            |_| true,
-        )?;
+    fn receive_complete(&mut self) -> Result<(), SchedulerError> {
+        let (num_finished, num_retryable) = self.inner.receive_completed(&mut self.container)?;
+        self.count_metrics.update(|metrics| {
+            saturating_add_assign!(metrics.num_finished, num_finished);
+            saturating_add_assign!(metrics.num_retryable, num_retryable);
+        });
         Ok(())
     }
 
-    fn receive_complete(&mut self) -> Result<(), SchedulerError> {
-        // TODO metrics logic
-        self.inner.receive_completed(&mut self.container)?;
+    /// Transactions expired, already processed or not payable in `bank` are
+    /// dropped from then on.
+    fn set_bank(&mut self, bank: Arc<Bank>) {
+        self.bank = Some(bank);
+    }
+
+    /// Schedules the queued transactions once more, the ones still blocked by
+    /// conflicts are taken out of the container, highest priority first.
+    fn flush(&mut self) -> Result<Vec<SchedulingBatch>, SchedulerError> {
+        self.receive_complete()?;
+        self.schedule_queued()?;
+
+        let mut unscheduled = SchedulingBatch {
+            batch_id: TransactionBatchId::new(0),
+            ids: Vec::with_capacity(self.container.len()),
+            transactions: Vec::with_capacity(self.container.len()),
+            max_ages: Vec::with_capacity(self.container.len()),
+        };
+        while let Some(priority_id) = self.container.pop() {
+            let transaction_ttl = self
+                .container
+                .get_transaction_ttl(&priority_id.id)
+                .expect("queued transaction must exist")
+                .clone();
+            self.container.remove_by_id(&priority_id.id);
+            unscheduled.ids.push(priority_id.id);
+            unscheduled.transactions.push(transaction_ttl.transaction);
+            unscheduled.max_ages.push(transaction_ttl.max_age);
+        }
+        Ok(if unscheduled.ids.is_empty() {
+            vec![]
+        } else {
+            vec![unscheduled]
+        })
+    }
+}
+
+impl PrioGraphSchedulerWrapper {
+    fn schedule_queued(&mut self) -> Result<(), SchedulerError> {
+        let summary = match self.bank.as_deref() {
+            Some(bank) => {
+                let counts = FilterCounts::default();
+                let summary = self.inner.schedule(
+                    &mut self.container,
+                    |txs, results| pre_graph_filter(bank, txs, results, &counts),
+                    |transaction_ttl| pre_lock_filter(bank.slot(), transaction_ttl, &counts),
+                )?;
+                self.count_metrics
+                    .update(|metrics| counts.accumulate_into(metrics));
+                self.dropped.extend(counts.take_dropped());
+                summary
+            }
+            None => self.inner.schedule(
+                &mut self.container,
+                |_, results| results.fill(true),
+                |_| true,
+            )?,
+        };
+        self.count_metrics.update(|metrics| {
+            saturating_add_assign!(metrics.num_scheduled, summary.num_scheduled);
+            saturating_add_assign!(metrics.num_unschedulable, summary.num_unschedulable);
+            saturating_add_assign!(metrics.num_schedule_filtered_out, summary.num_filtered_out);
+        });
         Ok(())
     }
 }
+
+/// Returns the priority and the estimated cost of a transaction. The priority is the
+/// prioritization fee requested by its compute-budget instructions (compute unit price
+/// times the requested compute units) per unit of cost. Transactions with invalid
+/// compute-budget instructions get the lowest priority, they fail at execution anyway.
+pub fn calculate_priority_and_cost(
+    transaction: &SanitizedTransaction,
+    feature_set: &FeatureSet,
+) -> (u64, u64) {
+    let cost = CostModel::calculate_cost(transaction, feature_set).sum();
+    let fee =
+        process_compute_budget_instructions(transaction.message().program_instructions_iter())
+            .map(|limits| FeeBudgetLimits::from(limits).prioritization_fee)
+            .unwrap_or_default();
+    // An offset of 1 avoids dividing by zero.
+    (
+        fee.saturating_mul(PRIORITY_MULTIPLIER)
+            .saturating_div(cost.saturating_add(1)),
+        cost,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::scheduler_messages::{MaxAge, TransactionBatchId, TransactionId},
+        crossbeam_channel::unbounded,
+        solana_runtime::genesis_utils::create_genesis_config,
+        solana_sdk::{
+            clock::Slot, compute_budget::ComputeBudgetInstruction, fee_calculator::FeeRateGovernor,
+            hash::Hash, instruction::Instruction, message::Message, signature::Keypair,
+            signer::Signer, system_instruction, system_transaction, transaction::Transaction,
+        },
+    };
+
+    fn transfer(compute_budget_ixs: Vec<Instruction>) -> SanitizedTransaction {
+        let from_keypair = Keypair::new();
+        let mut ixs = vec![system_instruction::transfer(
+            &from_keypair.pubkey(),
+            &solana_sdk::pubkey::new_rand(),
+            1,
+        )];
+        ixs.extend(compute_budget_ixs);
+        let message = Message::new(&ixs, Some(&from_keypair.pubkey()));
+        SanitizedTransaction::from_transaction_for_tests(Transaction::new(
+            &[&from_keypair],
+            message,
+            Hash::default(),
+        ))
+    }
+
+    #[test]
+    fn test_calculate_priority_and_cost() {
+        let feature_set = FeatureSet::all_enabled();
+        let (no_fee_priority, no_fee_cost) =
+            calculate_priority_and_cost(&transfer(vec![]), &feature_set);
+        assert_eq!(no_fee_priority, 0);
+        assert!(no_fee_cost > 0);
+
+        let priorities = [1_000, 10_000].map(|price| {
+            calculate_priority_and_cost(
+                &transfer(vec![ComputeBudgetInstruction::set_compute_unit_price(
+                    price,
+                )]),
+                &feature_set,
+            )
+            .0
+        });
+        assert!(0 < priorities[0] && priorities[0] < priorities[1]);
+
+        // requesting less compute units lowers the fee at the same price
+        let (limited_priority, _) = calculate_priority_and_cost(
+            &transfer(vec![
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+            ]),
+            &feature_set,
+        );
+        assert!(limited_priority < priorities[1]);
+
+        // duplicated compute-budget instructions are invalid
+        let (invalid_priority, _) = calculate_priority_and_cost(
+            &transfer(vec![
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+            ]),
+            &feature_set,
+        );
+        assert_eq!(invalid_priority, 0);
+    }
+
+    #[test]
+    fn test_wrapper_drops_filtered_transactions() {
+        let mut genesis = create_genesis_config(1_000_000_000);
+        genesis.genesis_config.fee_rate_governor = FeeRateGovernor::new(5_000, 0);
+        let bank = Arc::new(Bank::new_for_tests(&genesis.genesis_config));
+        let blockhash = bank.last_blockhash();
+        let transfer = |from: &Keypair, blockhash| {
+            SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                from,
+                &solana_sdk::pubkey::new_rand(),
+                1,
+                blockhash,
+            ))
+        };
+        let transactions = vec![
+            transfer(&genesis.mint_keypair, blockhash),
+            transfer(&genesis.mint_keypair, Hash::new_unique()),
+            transfer(&Keypair::new(), blockhash),
+        ];
+        let signatures = transactions
+            .iter()
+            .map(|transaction| *transaction.signature())
+            .collect::<Vec<_>>();
+
+        let (sender, receiver) = unbounded();
+        let (_completed_sender, completed_receiver) = unbounded();
+        let mut scheduler = PrioGraphSchedulerWrapper::new(vec![sender], completed_receiver);
+        scheduler.set_bank(bank);
+        scheduler
+            .schedule_batch(SchedulingBatch {
+                batch_id: TransactionBatchId::new(0),
+                ids: (0..3).map(TransactionId::new).collect(),
+                transactions,
+                max_ages: vec![
+                    MaxAge {
+                        epoch_invalidation_slot: Slot::MAX,
+                        alt_invalidation_slot: Slot::MAX,
+                    };
+                    3
+                ],
+            })
+            .unwrap();
+
+        let scheduled = receiver
+            .try_iter()
+            .flat_map(|batch| batch.ids)
+            .collect::<Vec<_>>();
+        assert_eq!(scheduled, vec![TransactionId::new(0)]);
+        // transactions of equal priority are filtered in any order
+        let mut dropped = scheduler.take_dropped();
+        dropped.sort_by_key(|(signature, _)| signatures.iter().position(|s| s == signature));
+        assert_eq!(
+            dropped,
+            vec![
+                (signatures[1], TransactionError::BlockhashNotFound),
+                (signatures[2], TransactionError::InsufficientFundsForFee),
+            ]
+        );
+        assert!(scheduler.take_dropped().is_empty());
+    }
+}
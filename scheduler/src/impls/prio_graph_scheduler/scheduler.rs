@@ -57,8 +57,9 @@ impl PrioGraphScheduler {
     /// otherwise.
     /// `pre_lock_filter` is used to filter out transactions after they have
     /// made it to the top of the prio-graph, and immediately before locks are
-    /// checked and taken. This fn should return `true` for transactions that
-    /// should be scheduled, and `false` otherwise.
+    /// checked and taken. It gets the `MaxAge` of the transaction alongside
+    /// it, and should return `true` for transactions that should be
+    /// scheduled, and `false` otherwise.
     ///
     /// Uses a `PrioGraph` to perform look-ahead during the scheduling of transactions.
     /// This, combined with internal tracking of threads' in-flight transactions, allows
@@ -68,7 +69,7 @@ impl PrioGraphScheduler {
         &mut self,
         container: &mut TransactionStateContainer,
         pre_graph_filter: impl Fn(&[&SanitizedTransaction], &mut [bool]),
-        pre_lock_filter: impl Fn(&SanitizedTransaction) -> bool,
+        pre_lock_filter: impl Fn(&SanitizedTransactionTTL) -> bool,
     ) -> Result<SchedulingSummary, SchedulerError> {
         let num_threads = self.consume_work_senders.len();
         let max_cu_per_thread = MAX_BLOCK_UNITS / num_threads as u64;
@@ -537,16 +538,17 @@ enum TransactionSchedulingError {
 
 fn try_schedule_transaction(
     transaction_state: &mut TransactionState,
-    pre_lock_filter: impl Fn(&SanitizedTransaction) -> bool,
+    pre_lock_filter: impl Fn(&SanitizedTransactionTTL) -> bool,
     blocking_locks: &mut ReadWriteAccountSet,
     account_locks: &mut ThreadAwareAccountLocks,
     num_threads: usize,
     thread_selector: impl Fn(ThreadSet) -> ThreadId,
 ) -> Result<TransactionSchedulingInfo, TransactionSchedulingError> {
-    let transaction = &transaction_state.transaction_ttl().transaction;
-    if !pre_lock_filter(transaction) {
+    let transaction_ttl = transaction_state.transaction_ttl();
+    if !pre_lock_filter(transaction_ttl) {
         return Err(TransactionSchedulingError::Filtered);
     }
+    let transaction = &transaction_ttl.transaction;
 
     // Check if this transaction conflicts with any blocked transactions
     let message = transaction.message();
@@ -713,7 +715,7 @@ mod tests {
         results.fill(true);
     }
 
-    fn test_pre_lock_filter(_tx: &SanitizedTransaction) -> bool {
+    fn test_pre_lock_filter(_tx: &SanitizedTransactionTTL) -> bool {
         true
     }
 
@@ -888,8 +890,9 @@ mod tests {
         ]);
 
         // 2nd transaction should be filtered out and dropped before locking.
-        let pre_lock_filter =
-            |tx: &SanitizedTransaction| tx.message().fee_payer() != &keypair.pubkey();
+        let pre_lock_filter = |tx: &SanitizedTransactionTTL| {
+            tx.transaction.message().fee_payer() != &keypair.pubkey()
+        };
         let scheduling_summary = scheduler
             .schedule(&mut container, test_pre_graph_filter, pre_lock_filter)
             .unwrap();
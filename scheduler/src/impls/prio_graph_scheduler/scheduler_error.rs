@@ -1,3 +1,4 @@
+use crate::scheduler_messages::TransactionBatchId;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,4 +7,6 @@ pub enum SchedulerError {
     DisconnectedSendChannel(&'static str),
     #[error("Recv channel disconnected: {0}")]
     DisconnectedRecvChannel(&'static str),
+    #[error("Invalid scheduling batch: {0}")]
+    InvalidBatch(TransactionBatchId),
 }
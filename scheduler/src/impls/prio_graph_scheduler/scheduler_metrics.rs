@@ -78,6 +78,18 @@ pub struct SchedulerCountMetricsInner {
     pub num_dropped_on_age_and_status: usize,
     /// Number of transactions that were dropped due to exceeded capacity.
     pub num_dropped_on_capacity: usize,
+    /// Number of transactions that were dropped because their `MaxAge` has
+    /// passed the current slot.
+    pub num_dropped_on_max_age: usize,
+    /// Number of transactions that were dropped because their blockhash is
+    /// not in the bank's queue.
+    pub num_dropped_on_blockhash_not_found: usize,
+    /// Number of transactions that were dropped because their fee payer
+    /// cannot cover the fee.
+    pub num_dropped_on_fee_payer: usize,
+    /// Number of transactions that were dropped because they are already in
+    /// the status cache.
+    pub num_dropped_on_already_processed: usize,
     /// Min prioritization fees in the transaction container
     pub min_prioritization_fees: u64,
     /// Max prioritization fees in the transaction container
@@ -149,6 +161,18 @@ impl SchedulerCountMetricsInner {
                 i64
             ),
             ("num_dropped_on_capacity", self.num_dropped_on_capacity, i64),
+            ("num_dropped_on_max_age", self.num_dropped_on_max_age, i64),
+            (
+                "num_dropped_on_blockhash_not_found",
+                self.num_dropped_on_blockhash_not_found,
+                i64
+            ),
+            ("num_dropped_on_fee_payer", self.num_dropped_on_fee_payer, i64),
+            (
+                "num_dropped_on_already_processed",
+                self.num_dropped_on_already_processed,
+                i64
+            ),
             ("min_priority", self.get_min_priority(), i64),
             ("max_priority", self.get_max_priority(), i64)
         );
@@ -174,6 +198,10 @@ impl SchedulerCountMetricsInner {
             || self.num_dropped_on_clear != 0
             || self.num_dropped_on_age_and_status != 0
             || self.num_dropped_on_capacity != 0
+            || self.num_dropped_on_max_age != 0
+            || self.num_dropped_on_blockhash_not_found != 0
+            || self.num_dropped_on_fee_payer != 0
+            || self.num_dropped_on_already_processed != 0
     }
 
     fn reset(&mut self) {
@@ -192,6 +220,10 @@ impl SchedulerCountMetricsInner {
         self.num_dropped_on_clear = 0;
         self.num_dropped_on_age_and_status = 0;
         self.num_dropped_on_capacity = 0;
+        self.num_dropped_on_max_age = 0;
+        self.num_dropped_on_blockhash_not_found = 0;
+        self.num_dropped_on_fee_payer = 0;
+        self.num_dropped_on_already_processed = 0;
         self.min_prioritization_fees = u64::MAX;
         self.max_prioritization_fees = 0;
     }
@@ -0,0 +1,232 @@
+use {
+    super::{
+        scheduler_metrics::SchedulerCountMetricsInner, transaction_state::SanitizedTransactionTTL,
+    },
+    solana_runtime::bank::Bank,
+    solana_sdk::{
+        clock::{Slot, MAX_PROCESSING_AGE},
+        saturating_add_assign,
+        signature::Signature,
+        transaction::{SanitizedTransaction, TransactionError},
+    },
+    solana_svm::transaction_error_metrics::TransactionErrorMetrics,
+    std::cell::{Cell, RefCell},
+};
+
+/// Transactions dropped by the filters of one scheduling pass, and their
+/// number by reason. The filters are `Fn`, so the counters are cells.
+#[derive(Default)]
+pub struct FilterCounts {
+    max_age: Cell<usize>,
+    blockhash_not_found: Cell<usize>,
+    fee_payer: Cell<usize>,
+    already_processed: Cell<usize>,
+    age_and_status: Cell<usize>,
+    dropped: RefCell<Vec<(Signature, TransactionError)>>,
+}
+
+impl FilterCounts {
+    pub fn accumulate_into(&self, metrics: &mut SchedulerCountMetricsInner) {
+        saturating_add_assign!(metrics.num_dropped_on_max_age, self.max_age.get());
+        saturating_add_assign!(
+            metrics.num_dropped_on_blockhash_not_found,
+            self.blockhash_not_found.get()
+        );
+        saturating_add_assign!(metrics.num_dropped_on_fee_payer, self.fee_payer.get());
+        saturating_add_assign!(
+            metrics.num_dropped_on_already_processed,
+            self.already_processed.get()
+        );
+        saturating_add_assign!(
+            metrics.num_dropped_on_age_and_status,
+            self.age_and_status.get()
+        );
+    }
+
+    /// Signatures of the dropped transactions, with the reason they were
+    /// dropped for.
+    pub fn take_dropped(&self) -> Vec<(Signature, TransactionError)> {
+        self.dropped.take()
+    }
+
+    fn count(&self, transaction: &SanitizedTransaction, err: TransactionError) {
+        let counter = match err {
+            TransactionError::BlockhashNotFound => &self.blockhash_not_found,
+            TransactionError::AlreadyProcessed => &self.already_processed,
+            TransactionError::InsufficientFundsForFee => &self.fee_payer,
+            TransactionError::ResanitizationNeeded => &self.max_age,
+            _ => &self.age_and_status,
+        };
+        counter.set(counter.get().saturating_add(1));
+        self.dropped
+            .borrow_mut()
+            .push((*transaction.signature(), err));
+    }
+}
+
+/// Drops transactions whose blockhash is not in the queue of `bank`, that are
+/// already in its status cache, or whose fee payer cannot cover the fee.
+pub fn pre_graph_filter(
+    bank: &Bank,
+    transactions: &[&SanitizedTransaction],
+    results: &mut [bool],
+    counts: &FilterCounts,
+) {
+    let lock_results = vec![Ok(()); transactions.len()];
+    let mut error_counters = TransactionErrorMetrics::default();
+    let check_results = bank.check_transactions(
+        transactions,
+        &lock_results,
+        MAX_PROCESSING_AGE,
+        &mut error_counters,
+    );
+
+    for ((transaction, check_result), result) in transactions
+        .iter()
+        .zip(check_results)
+        .zip(results.iter_mut())
+    {
+        *result = match check_result.and_then(|_| check_fee_payer(bank, transaction)) {
+            Ok(()) => true,
+            Err(err) => {
+                counts.count(transaction, err);
+                false
+            }
+        };
+    }
+}
+
+/// Drops transactions sanitized against an epoch or address lookup tables
+/// that may have changed by `slot`.
+pub fn pre_lock_filter(
+    slot: Slot,
+    transaction_ttl: &SanitizedTransactionTTL,
+    counts: &FilterCounts,
+) -> bool {
+    let max_age = &transaction_ttl.max_age;
+    if slot > max_age.epoch_invalidation_slot || slot > max_age.alt_invalidation_slot {
+        counts.count(
+            &transaction_ttl.transaction,
+            TransactionError::ResanitizationNeeded,
+        );
+        return false;
+    }
+    true
+}
+
+fn check_fee_payer(
+    bank: &Bank,
+    transaction: &SanitizedTransaction,
+) -> Result<(), TransactionError> {
+    let message = transaction.message();
+    // `None` if neither the blockhash nor a durable nonce gives the fee rate.
+    let fee = bank
+        .get_fee_for_message(message)
+        .ok_or(TransactionError::BlockhashNotFound)?;
+    if bank.get_balance(message.fee_payer()) < fee {
+        return Err(TransactionError::InsufficientFundsForFee);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::scheduler_messages::MaxAge,
+        solana_runtime::genesis_utils::create_genesis_config,
+        solana_sdk::{
+            fee_calculator::FeeRateGovernor, hash::Hash, signature::Keypair, signer::Signer,
+            system_transaction,
+        },
+    };
+
+    #[test]
+    fn test_pre_graph_filter() {
+        let mut genesis = create_genesis_config(1_000_000_000);
+        genesis.genesis_config.fee_rate_governor = FeeRateGovernor::new(5_000, 0);
+        let bank = Bank::new_for_tests(&genesis.genesis_config);
+        let blockhash = bank.last_blockhash();
+        let transfer = |from: &Keypair, blockhash| {
+            system_transaction::transfer(from, &solana_sdk::pubkey::new_rand(), 1, blockhash)
+        };
+
+        let processed = transfer(&genesis.mint_keypair, blockhash);
+        bank.process_transaction(&processed).unwrap();
+        let transactions = [
+            transfer(&genesis.mint_keypair, blockhash),
+            processed,
+            transfer(&genesis.mint_keypair, Hash::new_unique()),
+            transfer(&Keypair::new(), blockhash),
+        ]
+        .map(SanitizedTransaction::from_transaction_for_tests);
+
+        let counts = FilterCounts::default();
+        let mut results = [true; 4];
+        pre_graph_filter(
+            &bank,
+            &transactions.iter().collect::<Vec<_>>(),
+            &mut results,
+            &counts,
+        );
+        assert_eq!(results, [true, false, false, false]);
+
+        let mut metrics = SchedulerCountMetricsInner::default();
+        counts.accumulate_into(&mut metrics);
+        assert_eq!(metrics.num_dropped_on_already_processed, 1);
+        assert_eq!(metrics.num_dropped_on_blockhash_not_found, 1);
+        assert_eq!(metrics.num_dropped_on_fee_payer, 1);
+        assert_eq!(metrics.num_dropped_on_max_age, 0);
+        assert_eq!(
+            counts.take_dropped(),
+            vec![
+                (
+                    *transactions[1].signature(),
+                    TransactionError::AlreadyProcessed
+                ),
+                (
+                    *transactions[2].signature(),
+                    TransactionError::BlockhashNotFound
+                ),
+                (
+                    *transactions[3].signature(),
+                    TransactionError::InsufficientFundsForFee
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pre_lock_filter() {
+        let keypair = Keypair::new();
+        let transaction_ttl =
+            |epoch_invalidation_slot, alt_invalidation_slot| SanitizedTransactionTTL {
+                transaction: SanitizedTransaction::from_transaction_for_tests(
+                    system_transaction::transfer(&keypair, &keypair.pubkey(), 1, Hash::default()),
+                ),
+                max_age: MaxAge {
+                    epoch_invalidation_slot,
+                    alt_invalidation_slot,
+                },
+            };
+
+        let counts = FilterCounts::default();
+        assert!(pre_lock_filter(10, &transaction_ttl(10, 10), &counts));
+        assert!(!pre_lock_filter(
+            10,
+            &transaction_ttl(9, Slot::MAX),
+            &counts
+        ));
+        assert!(!pre_lock_filter(
+            10,
+            &transaction_ttl(Slot::MAX, 9),
+            &counts
+        ));
+
+        let mut metrics = SchedulerCountMetricsInner::default();
+        counts.accumulate_into(&mut metrics);
+        assert_eq!(metrics.num_dropped_on_max_age, 2);
+        assert_eq!(metrics.num_dropped_on_age_and_status, 0);
+        assert_eq!(counts.take_dropped().len(), 2);
+    }
+}
@@ -1,6 +1,8 @@
 pub mod id_generator;
 pub mod impls;
+pub mod parallel_processor;
 pub mod scheduler;
 pub mod scheduler_messages;
 pub mod status_slicing;
 pub mod stopwatch;
+pub mod worker;
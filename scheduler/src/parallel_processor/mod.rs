@@ -0,0 +1,337 @@
+use crate::id_generator::IdGenerator;
+use crate::impls::prio_graph_scheduler::{PrioGraphSchedulerWrapper, CONTAINER_CAPACITY};
+use crate::scheduler::Scheduler;
+use crate::scheduler_messages::{MaxAge, SchedulingBatch, SchedulingBatchResult, TransactionId};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use igloo_executor::{
+    processor::{BlockProcessor, TransactionProcessor},
+    BlockPayload, Error, Result,
+};
+use igloo_storage::execution::TransactionsResultWrapper;
+use igloo_verifier::settings::Settings;
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    clock::Slot,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{SanitizedTransaction, TransactionError},
+};
+use solana_svm::{
+    account_loader::TransactionLoadResult,
+    transaction_processor::LoadAndExecuteSanitizedTransactionsOutput,
+    transaction_results::TransactionExecutionResult,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    thread,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// A `BlockProcessor` that executes a block with N worker threads, scheduled
+/// by the `PrioGraphSchedulerWrapper`.
+///
+/// All transactions run against the same uncommitted bank, just like the
+/// sequential path, so the per-transaction results do not depend on which
+/// worker ran them. The results are gathered back into the original batches
+/// and order, which keeps the committed entries and the bank hash identical
+/// to `SequentialBlockProcessor`. Transactions the scheduler drops, e.g. with
+/// an unknown blockhash or a fee payer unable to pay, are not executed.
+pub struct ParallelBlockProcessor {
+    num_workers: usize,
+}
+
+impl ParallelBlockProcessor {
+    pub fn new(num_workers: usize) -> Result<Self> {
+        if num_workers == 0 {
+            return Err(Error::BlockProcessError(
+                "a parallel block processor needs at least one worker".to_string(),
+            ));
+        }
+        Ok(Self { num_workers })
+    }
+}
+
+impl BlockProcessor for ParallelBlockProcessor {
+    fn process_block(
+        &self,
+        bank: Arc<Bank>,
+        settings: &Settings,
+        block: &BlockPayload,
+    ) -> Result<Vec<TransactionsResultWrapper>> {
+        let (mut collector, schedulable) =
+            BlockCollector::new(block, bank.get_transaction_account_lock_limit());
+        if collector.is_complete() {
+            return Ok(collector.into_results());
+        }
+
+        thread::scope(|scope| {
+            let (senders, receivers): (Vec<_>, Vec<_>) =
+                (0..self.num_workers).map(|_| unbounded()).unzip();
+            let (completed_sender, completed_receiver) = unbounded();
+            let (output_sender, output_receiver) = unbounded();
+
+            for receiver in receivers {
+                let processor = TransactionProcessor::new(bank.clone(), settings.clone());
+                let completed_sender = completed_sender.clone();
+                let output_sender = output_sender.clone();
+                scope.spawn(move || {
+                    worker_loop(processor, receiver, completed_sender, output_sender)
+                });
+            }
+            // only the workers may keep the channels alive, so a dead worker
+            // pool shows up as a disconnected receiver instead of a hang
+            drop(completed_sender);
+            drop(output_sender);
+
+            let mut scheduler = PrioGraphSchedulerWrapper::new(senders, completed_receiver);
+            scheduler.set_bank(bank.clone());
+            let mut batch_id_gen = IdGenerator::default();
+            let mut ids_by_signature = HashMap::<Signature, Vec<TransactionId>>::new();
+            for (id, transaction) in schedulable.iter() {
+                ids_by_signature
+                    .entry(*transaction.signature())
+                    .or_default()
+                    .push(*id);
+            }
+            let mut schedulable = schedulable.into_iter();
+            let mut in_scheduler = 0;
+            while !collector.is_complete() {
+                // feed no more than the container can hold, otherwise the
+                // overflow would be dropped and never executed
+                let (ids, transactions): (Vec<_>, Vec<_>) = schedulable
+                    .by_ref()
+                    .take(CONTAINER_CAPACITY - in_scheduler)
+                    .unzip();
+                in_scheduler += ids.len();
+                let max_ages = vec![
+                    MaxAge {
+                        epoch_invalidation_slot: Slot::MAX,
+                        alt_invalidation_slot: Slot::MAX,
+                    };
+                    ids.len()
+                ];
+                scheduler
+                    .schedule_batch(SchedulingBatch {
+                        batch_id: batch_id_gen.gen(),
+                        ids,
+                        transactions,
+                        max_ages,
+                    })
+                    .map_err(|e| Error::BlockProcessError(e.to_string()))?;
+                for (signature, err) in scheduler.take_dropped() {
+                    let id = ids_by_signature
+                        .get_mut(&signature)
+                        .and_then(Vec::pop)
+                        .expect("dropped transactions are from the block");
+                    in_scheduler -= 1;
+                    collector.collect_dropped(id, err);
+                }
+                if collector.is_complete() {
+                    break;
+                }
+                // with nothing in flight no output will ever come
+                if scheduler.num_queued() == in_scheduler {
+                    return Err(Error::BlockProcessError(format!(
+                        "{in_scheduler} transactions can not be scheduled"
+                    )));
+                }
+
+                let executed = output_receiver
+                    .recv()
+                    .map_err(|e| Error::BlockProcessError(e.to_string()))??;
+                in_scheduler -= executed.ids.len();
+                collector.collect(executed);
+
+                scheduler
+                    .receive_complete()
+                    .map_err(|e| Error::BlockProcessError(e.to_string()))?;
+            }
+
+            Ok(collector.into_results())
+        })
+    }
+}
+
+/// Output of one `SchedulingBatch` executed by a worker.
+struct ExecutedBatch {
+    ids: Vec<TransactionId>,
+    output: LoadAndExecuteSanitizedTransactionsOutput,
+}
+
+fn worker_loop(
+    processor: TransactionProcessor,
+    receiver: Receiver<SchedulingBatch>,
+    completed_sender: Sender<SchedulingBatchResult>,
+    output_sender: Sender<Result<ExecutedBatch>>,
+) {
+    while let Ok(batch) = receiver.recv() {
+        let result = processor.process(Cow::Borrowed(&batch.transactions));
+        let ids = batch.ids.clone();
+
+        // Report completion before the output, so the account locks are
+        // already released once the driver wakes up on the output.
+        let _ = completed_sender.send(SchedulingBatchResult {
+            batch,
+            retryable_indexes: vec![],
+        });
+        if output_sender
+            .send(result.map(|output| ExecutedBatch { ids, output }))
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+type TransactionOutput = (TransactionLoadResult, TransactionExecutionResult);
+
+/// Reassembles per-transaction outputs into the batches of the block.
+struct BlockCollector {
+    positions: HashMap<TransactionId, (usize, usize)>,
+    batches: Vec<Vec<Option<TransactionOutput>>>,
+    metrics: LoadAndExecuteSanitizedTransactionsOutput,
+    remaining: usize,
+}
+
+impl BlockCollector {
+    /// Returns the collector and the transactions left to schedule.
+    ///
+    /// The sequential path locks the accounts of every batch at once, later
+    /// transactions conflicting with earlier ones in the same batch fail with
+    /// `AccountInUse`. The scheduler would run them one after another instead,
+    /// so they are resolved here in the same way and never scheduled.
+    fn new(
+        block: &BlockPayload,
+        tx_account_lock_limit: usize,
+    ) -> (Self, Vec<(TransactionId, SanitizedTransaction)>) {
+        let mut id_gen = IdGenerator::default();
+        let mut positions = HashMap::new();
+        let mut schedulable = vec![];
+        let batches = block
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(batch_index, transactions)| {
+                let mut locks = BatchLocks::default();
+                transactions
+                    .iter()
+                    .enumerate()
+                    .map(|(index, transaction)| {
+                        if !locks.try_lock(transaction, tx_account_lock_limit) {
+                            return Some((
+                                Err(TransactionError::AccountInUse),
+                                TransactionExecutionResult::NotExecuted(
+                                    TransactionError::AccountInUse,
+                                ),
+                            ));
+                        }
+                        let id: TransactionId = id_gen.gen();
+                        positions.insert(id, (batch_index, index));
+                        schedulable.push((id, transaction.clone()));
+                        None
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let collector = Self {
+            remaining: schedulable.len(),
+            positions,
+            batches,
+            metrics: empty_output(),
+        };
+        (collector, schedulable)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn collect(&mut self, executed: ExecutedBatch) {
+        let ExecutedBatch { ids, output } = executed;
+        self.metrics.error_metrics.accumulate(&output.error_metrics);
+        self.metrics
+            .execute_timings
+            .accumulate(&output.execute_timings);
+
+        for (id, load_result, execution_result) in
+            itertools::izip!(ids, output.loaded_transactions, output.execution_results)
+        {
+            let (batch_index, index) = self.positions[&id];
+            self.batches[batch_index][index] = Some((load_result, execution_result));
+            self.remaining -= 1;
+        }
+    }
+
+    /// Records a transaction dropped by the scheduler as not executed.
+    fn collect_dropped(&mut self, id: TransactionId, err: TransactionError) {
+        let (batch_index, index) = self.positions[&id];
+        self.batches[batch_index][index] = Some((
+            Err(err.clone()),
+            TransactionExecutionResult::NotExecuted(err),
+        ));
+        self.remaining -= 1;
+    }
+
+    /// Metrics and timings of the whole block are reported with the first batch.
+    fn into_results(self) -> Vec<TransactionsResultWrapper> {
+        let mut metrics = Some(self.metrics);
+        self.batches
+            .into_iter()
+            .map(|outputs| {
+                let mut output = metrics.take().unwrap_or_else(empty_output);
+                (output.loaded_transactions, output.execution_results) = outputs
+                    .into_iter()
+                    .map(|output| output.expect("every transaction should be executed"))
+                    .unzip();
+                output.into()
+            })
+            .collect()
+    }
+}
+
+fn empty_output() -> LoadAndExecuteSanitizedTransactionsOutput {
+    LoadAndExecuteSanitizedTransactionsOutput {
+        error_metrics: Default::default(),
+        execute_timings: Default::default(),
+        execution_results: vec![],
+        loaded_transactions: vec![],
+    }
+}
+
+/// Mirrors the account locking the bank does for a single batch.
+#[derive(Default)]
+struct BatchLocks {
+    writable: HashSet<Pubkey>,
+    readonly: HashSet<Pubkey>,
+}
+
+impl BatchLocks {
+    fn try_lock(&mut self, transaction: &SanitizedTransaction, lock_limit: usize) -> bool {
+        // Invalid transactions take no locks, the worker rejects them with
+        // the same error as the sequential path.
+        let Ok(locks) = transaction.get_account_locks(lock_limit) else {
+            return true;
+        };
+
+        let conflicted = locks
+            .writable
+            .iter()
+            .any(|key| self.writable.contains(*key) || self.readonly.contains(*key))
+            || locks
+                .readonly
+                .iter()
+                .any(|key| self.writable.contains(*key));
+        if conflicted {
+            return false;
+        }
+
+        self.writable.extend(locks.writable.into_iter().copied());
+        self.readonly.extend(locks.readonly.into_iter().copied());
+        true
+    }
+}
@@ -0,0 +1,189 @@
+use super::ParallelBlockProcessor;
+use anyhow::Result;
+use igloo_executor::{BlockPayload, Executor};
+use igloo_storage::config::{GlobalConfig, KeypairsConfig};
+use solana_sdk::{
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction, system_transaction,
+    transaction::{SanitizedTransaction, Transaction},
+};
+use std::{fs, path::Path, sync::Arc};
+
+const NUM_ACCOUNTS: usize = 64;
+const INIT_BALANCE: u64 = 1_000_000;
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+fn open_executor(ledger_path: &Path, keypairs: &KeypairsConfig) -> Result<Executor> {
+    let mut config = GlobalConfig::new(ledger_path)?;
+    config.keypairs = keypairs.clone();
+    Ok(Executor::new_with_config(config)?)
+}
+
+fn sanitized(tx: Transaction) -> SanitizedTransaction {
+    SanitizedTransaction::from_transaction_for_tests(tx)
+}
+
+fn funding_block(mint: &Keypair, accounts: &[Keypair], blockhash: Hash) -> BlockPayload {
+    let transfers = accounts
+        .iter()
+        .map(|account| (account.pubkey(), INIT_BALANCE))
+        .collect::<Vec<_>>();
+    let message = Message::new(
+        &system_instruction::transfer_many(&mint.pubkey(), &transfers),
+        Some(&mint.pubkey()),
+    );
+    BlockPayload::new(vec![sanitized(Transaction::new(
+        &[mint],
+        message,
+        blockhash,
+    ))])
+}
+
+/// Transfers between the funded accounts, split into several batches. Every
+/// batch has a transaction conflicting with an earlier one of the same batch,
+/// and batches also touch accounts used by other batches.
+fn transfers_block(accounts: &[Keypair], blockhash: Hash) -> BlockPayload {
+    let batches = accounts
+        .chunks(16)
+        .map(|chunk| {
+            let mut batch = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, from)| {
+                    sanitized(system_transaction::transfer(
+                        from,
+                        &accounts[(i * 7 + 3) % accounts.len()].pubkey(),
+                        1_000 + i as u64,
+                        blockhash,
+                    ))
+                })
+                .collect::<Vec<_>>();
+            batch.push(sanitized(system_transaction::transfer(
+                &chunk[0],
+                &Pubkey::new_unique(),
+                10,
+                blockhash,
+            )));
+            batch
+        })
+        .collect();
+    BlockPayload::new_with_batches(batches)
+}
+
+fn assert_same_block(sequential: &Executor, parallel: &Executor, slot: u64) -> Result<()> {
+    let sequential = sequential.storage()?;
+    let parallel = parallel.storage()?;
+    assert_eq!(
+        sequential.get_bank(slot)?.hash(),
+        parallel.get_bank(slot)?.hash()
+    );
+    assert_eq!(
+        sequential.blockstore().get_slot_entries(slot, 0)?,
+        parallel.blockstore().get_slot_entries(slot, 0)?
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn parallel_block_matches_sequential() -> Result<()> {
+    let sequential_path = tempfile::tempdir()?.into_path();
+    let executor = Executor::new_for_test(&sequential_path)?;
+    let keypairs = executor.storage()?.keypairs().clone();
+    executor.close().await?;
+
+    // both executors start from the very same genesis
+    let parallel_path = tempfile::tempdir()?.into_path();
+    copy_dir(&sequential_path, &parallel_path)?;
+    let mut sequential = open_executor(&sequential_path, &keypairs)?;
+    let mut parallel = open_executor(&parallel_path, &keypairs)?;
+    parallel.set_block_processor(Arc::new(ParallelBlockProcessor::new(4)?));
+
+    let mint = keypairs.mint_keypair.as_ref().unwrap();
+    let accounts = (0..NUM_ACCOUNTS)
+        .map(|_| Keypair::new())
+        .collect::<Vec<_>>();
+
+    let blockhash = sequential.storage()?.current_bank().last_blockhash();
+    let block = funding_block(mint, &accounts, blockhash);
+    let info = sequential.new_block(block.clone()).await?;
+    assert_eq!(parallel.new_block(block).await?.head.slot, info.head.slot);
+    assert_same_block(&sequential, &parallel, info.head.slot)?;
+    for account in accounts.iter() {
+        assert_eq!(parallel.storage()?.balance(&account.pubkey()), INIT_BALANCE);
+    }
+
+    let blockhash = sequential.storage()?.current_bank().last_blockhash();
+    let block = transfers_block(&accounts, blockhash);
+    let info = sequential.new_block(block.clone()).await?;
+    assert_eq!(parallel.new_block(block).await?.head.slot, info.head.slot);
+    assert_same_block(&sequential, &parallel, info.head.slot)?;
+    for account in accounts.iter() {
+        assert_eq!(
+            sequential.storage()?.balance(&account.pubkey()),
+            parallel.storage()?.balance(&account.pubkey())
+        );
+    }
+
+    // transactions the scheduler filters out are not executed either
+    let blockhash = sequential.storage()?.current_bank().last_blockhash();
+    let block = BlockPayload::new(vec![
+        sanitized(system_transaction::transfer(
+            &accounts[0],
+            &Pubkey::new_unique(),
+            10,
+            blockhash,
+        )),
+        sanitized(system_transaction::transfer(
+            &accounts[1],
+            &Pubkey::new_unique(),
+            10,
+            Hash::new_unique(),
+        )),
+        sanitized(system_transaction::transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            10,
+            blockhash,
+        )),
+    ]);
+    let info = sequential.new_block(block.clone()).await?;
+    assert_eq!(parallel.new_block(block).await?.head.slot, info.head.slot);
+    assert_same_block(&sequential, &parallel, info.head.slot)?;
+    assert_eq!(
+        parallel
+            .storage()?
+            .get_bank(info.head.slot)?
+            .transaction_count(),
+        sequential
+            .storage()?
+            .get_bank(info.head.slot)?
+            .transaction_count()
+    );
+
+    sequential.close().await?;
+    parallel.close().await?;
+    Ok(())
+}
+
+#[test]
+fn parallel_block_processor_needs_workers() {
+    assert!(ParallelBlockProcessor::new(0).is_err());
+    assert!(ParallelBlockProcessor::new(1).is_ok());
+}
@@ -1,6 +1,8 @@
 use crate::impls::prio_graph_scheduler::scheduler_error::SchedulerError;
 use crate::scheduler_messages::{SchedulingBatch, SchedulingBatchResult};
 use crossbeam_channel::{Receiver, Sender};
+use solana_runtime::bank::Bank;
+use std::sync::Arc;
 
 /// A Scheduler is a single-thread centralized scheduling thread.
 ///
@@ -24,4 +26,15 @@ pub trait Scheduler {
     fn schedule_batch(&mut self, txs: SchedulingBatch) -> Result<(), SchedulerError>;
 
     fn receive_complete(&mut self) -> Result<(), SchedulerError>;
+
+    /// Sets the working bank the scheduled transactions are executed against,
+    /// schedulers may drop the transactions that cannot land in it.
+    fn set_bank(&mut self, _bank: Arc<Bank>) {}
+
+    /// Sends what the scheduler can still send before it is dropped, blocking
+    /// until there is room in the worker channels. Returns the transactions
+    /// left unscheduled, to be scheduled again by the next scheduler.
+    fn flush(&mut self) -> Result<Vec<SchedulingBatch>, SchedulerError> {
+        Ok(vec![])
+    }
 }
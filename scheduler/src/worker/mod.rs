@@ -0,0 +1,327 @@
+use crate::scheduler_messages::{
+    MaxAge, SchedulingBatch, SchedulingBatchResult, TransactionBatchId, TransactionId,
+};
+use crossbeam_channel::{Receiver, Sender};
+use igloo_executor::{processor::TransactionProcessor, Result};
+use igloo_storage::{blockstore::txs::CommitBatch, execution::TransactionsResultWrapper};
+use igloo_verifier::settings::Settings;
+use solana_cost_model::cost_model::CostModel;
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    pubkey::Pubkey,
+    transaction::{SanitizedTransaction, TransactionError},
+};
+use solana_svm::{
+    transaction_processor::LoadAndExecuteSanitizedTransactionsOutput,
+    transaction_results::TransactionExecutionResult,
+};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+mod pool;
+#[cfg(test)]
+mod tests;
+
+pub use pool::WorkerPool;
+
+/// What became of a transaction handed to a `Worker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    /// Executed, successfully or not, its result is to be committed.
+    Committed,
+    /// Not executed, and never will be.
+    Dropped(TransactionError),
+    /// Not executed, but may be once the conflicting transactions finished
+    /// or the next block starts.
+    Retryable(TransactionError),
+}
+
+impl TransactionOutcome {
+    pub fn new(result: &TransactionExecutionResult) -> Self {
+        match result {
+            TransactionExecutionResult::Executed { .. } => Self::Committed,
+            TransactionExecutionResult::NotExecuted(err) if is_retryable(err) => {
+                Self::Retryable(err.clone())
+            }
+            TransactionExecutionResult::NotExecuted(err) => Self::Dropped(err.clone()),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Retryable(_))
+    }
+}
+
+/// Errors that only depend on the transactions running alongside, or on the
+/// block being full.
+pub fn is_retryable(err: &TransactionError) -> bool {
+    matches!(
+        err,
+        TransactionError::AccountInUse
+            | TransactionError::WouldExceedMaxBlockCostLimit
+            | TransactionError::WouldExceedMaxAccountCostLimit
+            | TransactionError::WouldExceedMaxVoteCostLimit
+            | TransactionError::WouldExceedAccountDataBlockLimit
+    )
+}
+
+/// A `SchedulingBatch` executed by a `Worker`, with one outcome per transaction.
+pub struct WorkerOutput {
+    pub batch_id: TransactionBatchId,
+    pub ids: Vec<TransactionId>,
+    pub transactions: Vec<SanitizedTransaction>,
+    pub max_ages: Vec<MaxAge>,
+    pub outcomes: Vec<TransactionOutcome>,
+    pub output: LoadAndExecuteSanitizedTransactionsOutput,
+}
+
+impl WorkerOutput {
+    /// Indexes of the retryable transactions, in ascending order.
+    pub fn retryable_indexes(&self) -> Vec<usize> {
+        self.outcomes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, outcome)| outcome.is_retryable().then_some(index))
+            .collect()
+    }
+
+    /// The committed transactions and their results, or `None` if there are
+    /// none. Dropped and retryable transactions are left out, the latter are
+    /// committed with the batch they are executed in.
+    pub fn into_commit(self) -> Option<(TransactionsResultWrapper, CommitBatch<'static>)> {
+        let Self {
+            transactions,
+            outcomes,
+            mut output,
+            ..
+        } = self;
+        let committed = outcomes
+            .iter()
+            .map(|outcome| *outcome == TransactionOutcome::Committed)
+            .collect::<Vec<_>>();
+        if !committed.contains(&true) {
+            return None;
+        }
+
+        let mut keep = committed.iter().copied();
+        output.loaded_transactions.retain(|_| keep.next().unwrap());
+        let mut keep = committed.iter().copied();
+        output.execution_results.retain(|_| keep.next().unwrap());
+        let transactions = transactions
+            .into_iter()
+            .zip(committed)
+            .filter_map(|(transaction, committed)| committed.then_some(transaction))
+            .collect::<Vec<_>>();
+        Some((output.into(), CommitBatch::new(transactions.into())))
+    }
+}
+
+/// Account locks of the transactions executed in a slot, held until the slot
+/// is committed.
+///
+/// Batches run against the uncommitted bank of the slot: a transaction using an
+/// account written by another one of the slot would not see its changes, and
+/// one writing an account read by another one would be replayed in a different
+/// state. Such transactions are not executed before the next slot.
+#[derive(Debug, Default)]
+pub struct SlotLocks {
+    write_locks: HashSet<Pubkey>,
+    read_locks: HashMap<Pubkey, u64>,
+}
+
+impl SlotLocks {
+    /// Locks the accounts of `transaction`, returns false if one of them
+    /// conflicts with a transaction locked before.
+    pub fn try_lock(&mut self, transaction: &SanitizedTransaction) -> bool {
+        let locks = transaction.get_account_locks_unchecked();
+        let conflicts = locks
+            .writable
+            .iter()
+            .any(|key| self.write_locks.contains(*key) || self.read_locks.contains_key(*key))
+            || locks
+                .readonly
+                .iter()
+                .any(|key| self.write_locks.contains(*key));
+        if conflicts {
+            return false;
+        }
+
+        self.write_locks
+            .extend(locks.writable.iter().map(|key| **key));
+        for key in locks.readonly {
+            *self.read_locks.entry(*key).or_default() += 1;
+        }
+        true
+    }
+
+    /// Releases the accounts of a transaction locked but not executed.
+    pub fn unlock(&mut self, transaction: &SanitizedTransaction) {
+        let locks = transaction.get_account_locks_unchecked();
+        for key in locks.writable {
+            self.write_locks.remove(key);
+        }
+        for key in locks.readonly {
+            if let Entry::Occupied(mut count) = self.read_locks.entry(*key) {
+                *count.get_mut() -= 1;
+                if *count.get() == 0 {
+                    count.remove();
+                }
+            }
+        }
+    }
+}
+
+/// Executes the batches sent by a `Scheduler` against a bank, and reports
+/// the retryable transactions back to it.
+///
+/// Transactions take their cost from the bank's cost tracker and lock their
+/// accounts in the `SlotLocks` before they are executed. The ones that do not
+/// fit in the block are retryable, so are the ones conflicting with
+/// transactions executed earlier in the slot or running on other workers.
+pub struct Worker {
+    bank: Arc<Bank>,
+    processor: TransactionProcessor,
+    completed_sender: Sender<SchedulingBatchResult>,
+    locks: Arc<Mutex<SlotLocks>>,
+}
+
+impl Worker {
+    pub fn new(
+        bank: Arc<Bank>,
+        settings: Settings,
+        completed_sender: Sender<SchedulingBatchResult>,
+    ) -> Self {
+        Self::new_with_locks(bank, settings, completed_sender, Default::default())
+    }
+
+    /// A worker sharing the locks of the slot with the other workers of a pool.
+    pub fn new_with_locks(
+        bank: Arc<Bank>,
+        settings: Settings,
+        completed_sender: Sender<SchedulingBatchResult>,
+        locks: Arc<Mutex<SlotLocks>>,
+    ) -> Self {
+        Self {
+            processor: TransactionProcessor::new(bank.clone(), settings),
+            bank,
+            completed_sender,
+            locks,
+        }
+    }
+
+    /// Processes batches until the scheduler hangs up, passing every output
+    /// to `on_output`.
+    pub fn run(
+        &self,
+        receiver: &Receiver<SchedulingBatch>,
+        mut on_output: impl FnMut(WorkerOutput),
+    ) -> Result<()> {
+        while let Ok(batch) = receiver.recv() {
+            on_output(self.process(batch)?);
+        }
+        Ok(())
+    }
+
+    /// Executes the batch and hands it back to the scheduler, which releases
+    /// its locks and re-queues the retryable transactions.
+    pub fn process(&self, batch: SchedulingBatch) -> Result<WorkerOutput> {
+        let output = self.execute(&batch)?;
+        // the scheduler may already be gone once all work is sent, nothing
+        // is left to retry then
+        let _ = self.completed_sender.send(SchedulingBatchResult {
+            batch,
+            retryable_indexes: output.retryable_indexes(),
+        });
+        Ok(output)
+    }
+
+    fn execute(&self, batch: &SchedulingBatch) -> Result<WorkerOutput> {
+        let costs = batch
+            .transactions
+            .iter()
+            .map(|transaction| CostModel::calculate_cost(transaction, &self.bank.feature_set))
+            .collect::<Vec<_>>();
+        let reservations = {
+            let mut locks = self.locks.lock().unwrap();
+            let mut cost_tracker = self.bank.write_cost_tracker().unwrap();
+            batch
+                .transactions
+                .iter()
+                .zip(&costs)
+                .map(|(transaction, cost)| {
+                    if !locks.try_lock(transaction) {
+                        return Err(TransactionError::AccountInUse);
+                    }
+                    cost_tracker.try_add(cost).map(|_| ()).map_err(|err| {
+                        locks.unlock(transaction);
+                        TransactionError::from(err)
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let reserved = reservations
+            .iter()
+            .map(|reservation| reservation.is_ok())
+            .collect::<Vec<_>>();
+        let transactions = batch
+            .transactions
+            .iter()
+            .zip(&reserved)
+            .filter(|(_, reserved)| **reserved)
+            .map(|(transaction, _)| transaction.clone())
+            .collect::<Vec<_>>();
+        let mut output = self.processor.process(Cow::Owned(transactions))?;
+
+        // Merge the transactions left out back in, in batch order.
+        let mut loaded = std::mem::take(&mut output.loaded_transactions).into_iter();
+        let mut executed = std::mem::take(&mut output.execution_results).into_iter();
+        (output.loaded_transactions, output.execution_results) = reservations
+            .into_iter()
+            .map(|reservation| match reservation {
+                Ok(()) => (
+                    loaded.next().expect("one result per transaction"),
+                    executed.next().expect("one result per transaction"),
+                ),
+                Err(err) => (
+                    Err(err.clone()),
+                    TransactionExecutionResult::NotExecuted(err),
+                ),
+            })
+            .unzip();
+
+        // Transactions reserved but not executed give their cost and locks back.
+        {
+            let mut locks = self.locks.lock().unwrap();
+            let mut cost_tracker = self.bank.write_cost_tracker().unwrap();
+            for (((transaction, cost), reserved), result) in batch
+                .transactions
+                .iter()
+                .zip(&costs)
+                .zip(reserved)
+                .zip(&output.execution_results)
+            {
+                if reserved && !result.was_executed() {
+                    locks.unlock(transaction);
+                    cost_tracker.remove(cost);
+                }
+            }
+        }
+
+        Ok(WorkerOutput {
+            batch_id: batch.batch_id,
+            ids: batch.ids.clone(),
+            transactions: batch.transactions.clone(),
+            max_ages: batch.max_ages.clone(),
+            outcomes: output
+                .execution_results
+                .iter()
+                .map(TransactionOutcome::new)
+                .collect(),
+            output,
+        })
+    }
+}
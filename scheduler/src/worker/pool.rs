@@ -0,0 +1,191 @@
+use super::{SlotLocks, Worker, WorkerOutput};
+use crate::{
+    scheduler::Scheduler,
+    scheduler_messages::{SchedulingBatch, TransactionBatchId},
+};
+use crossbeam_channel::{unbounded, Receiver};
+use igloo_executor::{processor::TransactionProcessor, Error, Result};
+use igloo_storage::{blockstore::txs::CommitBatch, RollupStorage};
+use igloo_verifier::settings::Settings;
+use solana_runtime::bank::Bank;
+use solana_sdk::clock::Slot;
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+/// N `Worker` threads executing the batches of a `Scheduler` against the
+/// bank of one slot.
+///
+/// Executed batches are only committed once the slot ends, that is once the
+/// scheduler is flushed and dropped and the workers ran out of batches.
+/// Transactions the scheduler could not send by then are handed back by
+/// `commit`, to be scheduled in the next slot. Like the sequential path, every
+/// batch runs against the uncommitted bank of the slot, so the workers share
+/// the `SlotLocks` of the slot: transactions conflicting with one executed
+/// earlier in the slot are handed back as well.
+///
+/// ```ignore
+/// storage.bump()?;
+/// let (pool, mut scheduler) =
+///     WorkerPool::new::<PrioGraphSchedulerWrapper>(storage.current_bank(), &settings, 4);
+/// scheduler.schedule_batch(batch)?;
+/// scheduler.receive_complete()?;
+/// let carried = pool.commit(scheduler, &mut storage).await?;
+///
+/// storage.bump()?;
+/// let (pool, mut scheduler) =
+///     WorkerPool::new::<PrioGraphSchedulerWrapper>(storage.current_bank(), &settings, 4);
+/// scheduler.schedule_batch(carried)?;
+/// ```
+pub struct WorkerPool {
+    bank: Arc<Bank>,
+    settings: Settings,
+    handles: Vec<JoinHandle<Result<()>>>,
+    output_receiver: Receiver<WorkerOutput>,
+}
+
+impl WorkerPool {
+    /// Spawns `num_workers` workers bound to `bank`, and the scheduler feeding
+    /// them.
+    pub fn new<S: Scheduler>(
+        bank: Arc<Bank>,
+        settings: &Settings,
+        num_workers: usize,
+    ) -> (Self, S) {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..num_workers).map(|_| unbounded()).unzip();
+        let (completed_sender, completed_receiver) = unbounded();
+        let (output_sender, output_receiver) = unbounded();
+        let locks = Arc::new(Mutex::new(SlotLocks::default()));
+
+        let handles = receivers
+            .into_iter()
+            .enumerate()
+            .map(|(index, receiver)| {
+                let worker = Worker::new_with_locks(
+                    bank.clone(),
+                    settings.clone(),
+                    completed_sender.clone(),
+                    locks.clone(),
+                );
+                let output_sender = output_sender.clone();
+                thread::Builder::new()
+                    .name(format!("iglooWorker{index:02}"))
+                    .spawn(move || {
+                        worker.run(&receiver, |output| {
+                            // the pool outlives its workers, the send never fails
+                            let _ = output_sender.send(output);
+                        })
+                    })
+                    .expect("spawn worker thread")
+            })
+            .collect();
+
+        let mut scheduler = S::new(senders, completed_receiver);
+        scheduler.set_bank(bank.clone());
+        let pool = Self {
+            bank,
+            settings: settings.clone(),
+            handles,
+            output_receiver,
+        };
+        (pool, scheduler)
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.bank.slot()
+    }
+
+    /// Flushes and drops the scheduler of the pool, waits for the workers to
+    /// finish, then commits the executed batches into `storage` in the order
+    /// they were scheduled. A slot without any executed transaction is
+    /// committed as an empty batch.
+    ///
+    /// Returns the transactions left unscheduled or retryable at the end of
+    /// the slot, in a single batch for the scheduler of the next slot.
+    pub async fn commit<S: Scheduler>(
+        self,
+        mut scheduler: S,
+        storage: &mut RollupStorage,
+    ) -> Result<SchedulingBatch> {
+        let current_slot = storage.current_bank().slot();
+        if current_slot != self.slot() {
+            return Err(Error::BlockProcessError(format!(
+                "workers executed slot {} but the storage is at slot {current_slot}",
+                self.slot()
+            )));
+        }
+
+        let unscheduled = scheduler
+            .flush()
+            .map_err(|e| Error::BlockProcessError(e.to_string()))?;
+        // the workers finish once the scheduler closes their channels
+        drop(scheduler);
+
+        for handle in self.handles {
+            handle
+                .join()
+                .map_err(|_| Error::BlockProcessError("worker thread panicked".to_string()))??;
+        }
+
+        let mut outputs = self.output_receiver.try_iter().collect::<Vec<_>>();
+        // batch ids are generated counting down
+        outputs.sort_by_key(|output| Reverse(output.batch_id.value()));
+
+        // a retryable transaction may have been sent again, its last outcome
+        // tells whether it is left to the next slot
+        let mut carried = BTreeMap::new();
+        for output in &outputs {
+            for (index, outcome) in output.outcomes.iter().enumerate() {
+                if outcome.is_retryable() {
+                    carried.insert(
+                        output.ids[index],
+                        (output.transactions[index].clone(), output.max_ages[index]),
+                    );
+                } else {
+                    carried.remove(&output.ids[index]);
+                }
+            }
+        }
+        for batch in unscheduled {
+            for ((id, transaction), max_age) in batch
+                .ids
+                .into_iter()
+                .zip(batch.transactions)
+                .zip(batch.max_ages)
+            {
+                carried.insert(id, (transaction, max_age));
+            }
+        }
+        if !carried.is_empty() {
+            log::debug!(
+                "{} transactions left to the slot after {}",
+                carried.len(),
+                self.slot()
+            );
+        }
+
+        let (mut results, mut batches): (Vec<_>, Vec<_>) = outputs
+            .into_iter()
+            .filter_map(WorkerOutput::into_commit)
+            .unzip();
+        // like a block of deposits only, an empty slot still needs an entry
+        if batches.is_empty() {
+            let processor = TransactionProcessor::new(self.bank, self.settings);
+            results.push(processor.process(Cow::Owned(vec![]))?.into());
+            batches.push(CommitBatch::new(Cow::Owned(vec![])));
+        }
+        storage.commit(results, batches).await?;
+
+        let (ids, (transactions, max_ages)) = carried.into_iter().unzip();
+        Ok(SchedulingBatch {
+            batch_id: TransactionBatchId::new(0),
+            ids,
+            transactions,
+            max_ages,
+        })
+    }
+}
@@ -0,0 +1,366 @@
+use super::{TransactionOutcome, Worker, WorkerPool};
+use crate::impls::no_lock_scheduler::NoLockScheduler;
+use crate::impls::prio_graph_scheduler::{
+    scheduler::PrioGraphScheduler, transaction_state::SanitizedTransactionTTL,
+    transaction_state_container::TransactionStateContainer, PrioGraphSchedulerWrapper,
+};
+use crate::scheduler::Scheduler;
+use crate::scheduler_messages::{MaxAge, SchedulingBatch, TransactionBatchId, TransactionId};
+use anyhow::Result;
+use crossbeam_channel::unbounded;
+use igloo_storage::{config::GlobalConfig, RollupStorage};
+use igloo_verifier::settings::{Settings, Switchs};
+use solana_cost_model::cost_model::CostModel;
+use solana_runtime::{bank::Bank, genesis_utils::create_genesis_config};
+use solana_sdk::{
+    clock::Slot,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_transaction,
+    transaction::{SanitizedTransaction, TransactionError},
+};
+use std::sync::Arc;
+
+fn setup() -> (Arc<Bank>, Keypair) {
+    let genesis = create_genesis_config(1_000_000_000);
+    let bank = Arc::new(Bank::new_for_tests(&genesis.genesis_config));
+    (bank, genesis.mint_keypair)
+}
+
+fn settings() -> Settings {
+    Settings {
+        switchs: Switchs {
+            tx_sanity_check: false,
+            txs_conflict_check: true,
+        },
+        ..Default::default()
+    }
+}
+
+fn transfer(bank: &Bank, from: &Keypair, recent_blockhash: Hash) -> SanitizedTransaction {
+    SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+        from,
+        &Pubkey::new_unique(),
+        bank.get_minimum_balance_for_rent_exemption(0),
+        recent_blockhash,
+    ))
+}
+
+fn batch(id: u64, transactions: Vec<SanitizedTransaction>) -> SchedulingBatch {
+    let len = transactions.len();
+    SchedulingBatch {
+        batch_id: TransactionBatchId::new(id),
+        ids: (0..len as u64).map(TransactionId::new).collect(),
+        transactions,
+        max_ages: vec![
+            MaxAge {
+                epoch_invalidation_slot: Slot::MAX,
+                alt_invalidation_slot: Slot::MAX,
+            };
+            len
+        ],
+    }
+}
+
+/// Only `count` transfers fit in the block of `bank`.
+fn limit_block_to_transfers(bank: &Bank, count: u64) {
+    let cost = CostModel::calculate_cost(
+        &transfer(bank, &Keypair::new(), Hash::default()),
+        &bank.feature_set,
+    )
+    .sum();
+    bank.write_cost_tracker()
+        .unwrap()
+        .set_limits(u64::MAX, cost * count, u64::MAX);
+}
+
+#[test]
+fn worker_classifies_results() {
+    let (bank, mint) = setup();
+    let (completed_sender, completed_receiver) = unbounded();
+    let worker = Worker::new(bank.clone(), settings(), completed_sender);
+
+    let output = worker
+        .process(batch(
+            0,
+            vec![
+                transfer(&bank, &mint, bank.last_blockhash()),
+                // write-locks the same fee payer
+                transfer(&bank, &mint, bank.last_blockhash()),
+                transfer(&bank, &mint, Hash::new_unique()),
+            ],
+        ))
+        .unwrap();
+    assert_eq!(
+        output.outcomes,
+        vec![
+            TransactionOutcome::Committed,
+            TransactionOutcome::Retryable(TransactionError::AccountInUse),
+            TransactionOutcome::Dropped(TransactionError::BlockhashNotFound),
+        ]
+    );
+    assert_eq!(output.output.execution_results.len(), 3);
+
+    let result = completed_receiver.try_recv().unwrap();
+    assert_eq!(result.batch.ids.len(), 3);
+    assert_eq!(result.retryable_indexes, vec![1]);
+}
+
+#[test]
+fn worker_retries_transactions_exceeding_block_limit() {
+    let (bank, mint) = setup();
+    limit_block_to_transfers(&bank, 1);
+    let (completed_sender, _completed_receiver) = unbounded();
+    let worker = Worker::new(bank.clone(), settings(), completed_sender);
+
+    // a dropped transaction gives its block space back
+    let output = worker
+        .process(batch(0, vec![transfer(&bank, &mint, Hash::new_unique())]))
+        .unwrap();
+    assert_eq!(
+        output.outcomes,
+        vec![TransactionOutcome::Dropped(
+            TransactionError::BlockhashNotFound
+        )]
+    );
+
+    let output = worker
+        .process(batch(
+            1,
+            vec![transfer(&bank, &mint, bank.last_blockhash())],
+        ))
+        .unwrap();
+    assert_eq!(output.outcomes, vec![TransactionOutcome::Committed]);
+
+    // another fee payer, the mint is locked until the end of the slot
+    let output = worker
+        .process(batch(
+            2,
+            vec![transfer(&bank, &Keypair::new(), bank.last_blockhash())],
+        ))
+        .unwrap();
+    assert_eq!(
+        output.outcomes,
+        vec![TransactionOutcome::Retryable(
+            TransactionError::WouldExceedMaxBlockCostLimit
+        )]
+    );
+    assert!(output.output.loaded_transactions[0].is_err());
+}
+
+#[test]
+fn retryable_transactions_are_requeued() {
+    let (bank, mint) = setup();
+    limit_block_to_transfers(&bank, 0);
+    let (work_sender, work_receiver) = unbounded();
+    let (completed_sender, completed_receiver) = unbounded();
+    let mut scheduler = PrioGraphScheduler::new(vec![work_sender], completed_receiver);
+    let worker = Worker::new(bank.clone(), settings(), completed_sender);
+
+    let mut container = TransactionStateContainer::with_capacity(16);
+    container.insert_new_transaction(
+        TransactionId::new(0),
+        SanitizedTransactionTTL {
+            transaction: transfer(&bank, &mint, bank.last_blockhash()),
+            max_age: MaxAge {
+                epoch_invalidation_slot: Slot::MAX,
+                alt_invalidation_slot: Slot::MAX,
+            },
+        },
+        1,
+        1,
+    );
+    let summary = scheduler
+        .schedule(&mut container, |_, results| results.fill(true), |_| true)
+        .unwrap();
+    assert_eq!(summary.num_scheduled, 1);
+    assert!(container.is_empty());
+
+    worker.process(work_receiver.try_recv().unwrap()).unwrap();
+    assert_eq!(scheduler.receive_completed(&mut container).unwrap(), (1, 1));
+    assert_eq!(container.len(), 1);
+
+    // scheduled again once the block has room
+    limit_block_to_transfers(&bank, 1);
+    let summary = scheduler
+        .schedule(&mut container, |_, results| results.fill(true), |_| true)
+        .unwrap();
+    assert_eq!(summary.num_scheduled, 1);
+    let output = worker.process(work_receiver.try_recv().unwrap()).unwrap();
+    assert_eq!(output.outcomes, vec![TransactionOutcome::Committed]);
+    assert_eq!(scheduler.receive_completed(&mut container).unwrap(), (1, 0));
+    assert!(container.is_empty());
+}
+
+#[tokio::test]
+async fn worker_pool_commits_scheduled_batches() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    store.init()?;
+    store.bump()?;
+    let keypairs = store.config().keypairs().clone();
+    let mint = keypairs.mint_keypair.unwrap();
+    let validator = keypairs.validator_keypair.unwrap();
+    let bank = store.current_bank();
+
+    let (pool, mut scheduler) =
+        WorkerPool::new::<PrioGraphSchedulerWrapper>(bank.clone(), &settings(), 2);
+    assert_eq!(pool.slot(), bank.slot());
+    let transactions = vec![
+        transfer(&bank, &mint, bank.last_blockhash()),
+        transfer(&bank, &validator, bank.last_blockhash()),
+        transfer(&bank, &mint, Hash::new_unique()),
+    ];
+    let recipients = transactions
+        .iter()
+        .map(|transaction| transaction.message().account_keys()[1])
+        .collect::<Vec<_>>();
+    let signatures = transactions
+        .iter()
+        .map(|transaction| *transaction.signature())
+        .collect::<Vec<_>>();
+    scheduler.schedule_batch(batch(0, transactions))?;
+    pool.commit(scheduler, &mut store).await?;
+
+    // the transaction with an unknown blockhash is dropped
+    let rent = bank.get_minimum_balance_for_rent_exemption(0);
+    assert_eq!(store.balance(&recipients[0]), rent);
+    assert_eq!(store.balance(&recipients[1]), rent);
+    assert_eq!(store.balance(&recipients[2]), 0);
+    assert!(bank.get_signature_status(&signatures[0]).is_some());
+    assert!(bank.get_signature_status(&signatures[1]).is_some());
+    assert!(bank.get_signature_status(&signatures[2]).is_none());
+
+    // the next slot needs a pool of its own
+    store.bump()?;
+    let (pool, scheduler) = WorkerPool::new::<PrioGraphSchedulerWrapper>(bank, &settings(), 2);
+    assert!(pool.commit(scheduler, &mut store).await.is_err());
+
+    store.close().await?;
+    Ok(())
+}
+
+#[test]
+fn worker_locks_accounts_until_the_end_of_the_slot() {
+    let (bank, mint) = setup();
+    let (completed_sender, _completed_receiver) = unbounded();
+    let worker = Worker::new(bank.clone(), settings(), completed_sender);
+
+    // a dropped transaction releases its locks
+    let output = worker
+        .process(batch(0, vec![transfer(&bank, &mint, Hash::new_unique())]))
+        .unwrap();
+    assert_eq!(
+        output.outcomes,
+        vec![TransactionOutcome::Dropped(
+            TransactionError::BlockhashNotFound
+        )]
+    );
+
+    let output = worker
+        .process(batch(
+            1,
+            vec![transfer(&bank, &mint, bank.last_blockhash())],
+        ))
+        .unwrap();
+    assert_eq!(output.outcomes, vec![TransactionOutcome::Committed]);
+
+    // the transfer above is not committed to the bank yet
+    let output = worker
+        .process(batch(
+            2,
+            vec![transfer(&bank, &mint, bank.last_blockhash())],
+        ))
+        .unwrap();
+    assert_eq!(
+        output.outcomes,
+        vec![TransactionOutcome::Retryable(
+            TransactionError::AccountInUse
+        )]
+    );
+}
+
+#[tokio::test]
+async fn worker_pool_executes_one_transfer_per_payer_and_slot() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    store.init()?;
+    store.bump()?;
+    let mint = store.config().keypairs().mint_keypair.clone().unwrap();
+    let bank = store.current_bank();
+    let rent = bank.get_minimum_balance_for_rent_exemption(0);
+    let balance = store.balance(&mint.pubkey());
+
+    let (pool, mut scheduler) = WorkerPool::new::<NoLockScheduler>(bank.clone(), &settings(), 2);
+    let transactions = [
+        transfer(&bank, &mint, bank.last_blockhash()),
+        transfer(&bank, &mint, bank.last_blockhash()),
+    ];
+    for (id, transaction) in transactions.iter().enumerate() {
+        let mut batch = batch(id as u64, vec![transaction.clone()]);
+        batch.ids = vec![TransactionId::new(id as u64)];
+        scheduler.schedule_batch(batch)?;
+    }
+    let carried = pool.commit(scheduler, &mut store).await?;
+
+    // whichever ran first, the other one waits for the next slot
+    let received = transactions
+        .iter()
+        .map(|transaction| store.balance(&transaction.message().account_keys()[1]))
+        .collect::<Vec<_>>();
+    assert_eq!(received.iter().sum::<u64>(), rent);
+    let spent = balance - store.balance(&mint.pubkey());
+    assert!(spent > rent && spent < 2 * rent);
+    let landed = transactions
+        .iter()
+        .filter(|transaction| bank.get_signature_status(transaction.signature()).is_some())
+        .collect::<Vec<_>>();
+    assert_eq!(landed.len(), 1);
+    assert_eq!(carried.transactions.len(), 1);
+    assert_ne!(carried.transactions[0].signature(), landed[0].signature());
+
+    store.bump()?;
+    let bank = store.current_bank();
+    let (pool, mut scheduler) = WorkerPool::new::<NoLockScheduler>(bank.clone(), &settings(), 2);
+    scheduler.schedule_batch(carried)?;
+    let carried = pool.commit(scheduler, &mut store).await?;
+    assert!(carried.ids.is_empty());
+    assert!(transactions
+        .iter()
+        .all(|transaction| bank.get_signature_status(transaction.signature()).is_some()));
+    for transaction in &transactions {
+        assert_eq!(
+            store.balance(&transaction.message().account_keys()[1]),
+            rent
+        );
+    }
+
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn worker_pool_commits_empty_slot() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    store.init()?;
+    store.bump()?;
+    let bank = store.current_bank();
+
+    let (pool, mut scheduler) =
+        WorkerPool::new::<PrioGraphSchedulerWrapper>(bank.clone(), &settings(), 2);
+    // nothing executed
+    scheduler.schedule_batch(batch(
+        0,
+        vec![transfer(&bank, &Keypair::new(), Hash::new_unique())],
+    ))?;
+    pool.commit(scheduler, &mut store).await?;
+
+    assert!(store.blockstore().is_full(bank.slot()));
+    assert_eq!(bank.transaction_count(), 0);
+    store.confirm(bank.slot())?;
+
+    store.close().await?;
+    Ok(())
+}
@@ -2,8 +2,6 @@ use solana_entry::entry::Entry;
 use solana_runtime::{
     bank::{Bank, ExecutedTransactionCounts, NewBankOptions, TotalAccountsStats},
     installed_scheduler_pool::BankWithScheduler,
-    snapshot_bank_utils,
-    snapshot_utils::ArchiveFormat,
 };
 use solana_sdk::{
     account::{AccountSharedData, ReadableAccount},
@@ -18,9 +16,7 @@ use solana_svm::{
 use std::sync::Arc;
 
 use crate::{
-    blockstore::txs::CommitBatch,
-    error::{AccountDbError, BankError},
-    execution::TransactionsResultWrapper,
+    blockstore::txs::CommitBatch, error::AccountDbError, execution::TransactionsResultWrapper,
     BankInfo, Result, RollupStorage,
 };
 
@@ -51,32 +47,6 @@ impl RollupStorage {
         bank_forks.set_accounts_hash_interval_slots(interval);
     }
 
-    pub fn snapshot(&self, slot: Option<Slot>) -> Result<()> {
-        let slot = match slot {
-            Some(slot) => slot,
-            None => self.current_height(),
-        };
-        let bank = self
-            .bank_forks
-            .read()
-            .unwrap()
-            .get(slot)
-            .ok_or(BankError::BankNotExists(slot))?;
-
-        let ledger_path = self.config.ledger_path.clone();
-        snapshot_bank_utils::bank_to_full_snapshot_archive(
-            &ledger_path,
-            &bank,
-            None,
-            ledger_path.join("full"),
-            ledger_path.join("incremental"),
-            ArchiveFormat::from_cli_arg("zstd")
-                .ok_or(BankError::Common("Unsupported archive format".to_string()))?,
-        )
-        .map_err(|e| BankError::Common(format!("Failed to snapshot bank: {e}").to_string()))?;
-        Ok(())
-    }
-
     pub fn current_bank(&self) -> Arc<Bank> {
         self.bank.clone()
     }
@@ -100,6 +70,7 @@ impl RollupStorage {
         );
         let new = self.bank_forks.write().unwrap().insert(new);
         self.bank = new.clone();
+        self.committed_ahead = Default::default();
         Ok(new)
     }
 
@@ -121,6 +92,7 @@ impl RollupStorage {
         }
 
         self.register_ticks(entries)?;
+        self.sync_state(&self.bank)?;
         Ok(rtn)
     }
 
@@ -9,6 +9,7 @@ use solana_core::{
     snapshot_packager_service::SnapshotPackagerService,
 };
 use solana_gossip::cluster_info::ClusterInfo;
+use solana_ledger::blockstore::Blockstore;
 use solana_runtime::{
     accounts_background_service::{
         AbsRequestHandlers, AbsRequestSender, AccountsBackgroundService, PrunedBanksRequestHandler,
@@ -21,7 +22,13 @@ use solana_runtime::{
 };
 use tokio::time::sleep;
 
-use crate::{config::StorageConfig, sig_hub::SignalHub, Error, Result, RollupStorage};
+use crate::{
+    broadcast::BroadcastService,
+    cleanup::{LedgerCleaner, LedgerCleanupService},
+    config::StorageConfig,
+    sig_hub::SignalHub,
+    Error, Result, RollupStorage,
+};
 
 #[allow(dead_code)]
 pub struct StorageBackground {
@@ -29,6 +36,8 @@ pub struct StorageBackground {
     pub(crate) accounts_background_request_sender: AbsRequestSender,
     pub(crate) accounts_hash_verifier: AccountsHashVerifier,
     pub(crate) snapshot_packager_service: SnapshotPackagerService,
+    pub(crate) ledger_cleanup_service: Option<LedgerCleanupService>,
+    pub(crate) broadcast_service: Option<BroadcastService>,
 }
 
 impl RollupStorage {
@@ -96,6 +105,7 @@ impl RollupStorage {
 impl StorageBackground {
     pub fn new(
         bank_forks: Arc<RwLock<BankForks>>,
+        blockstore: Arc<Blockstore>,
         config: &StorageConfig,
         hub: &mut SignalHub,
         exit: Arc<AtomicBool>,
@@ -137,6 +147,35 @@ impl StorageBackground {
             ))?,
         };
 
+        let broadcast_service = config
+            .broadcast_addr
+            .map(|addr| {
+                let ledger_signal_receiver = hub.ledger_signal_receiver.take().ok_or(
+                    Error::InitCommon("ledger signal receiver is None".to_string()),
+                )?;
+                BroadcastService::new(
+                    addr,
+                    blockstore.clone(),
+                    bank_forks.clone(),
+                    ledger_signal_receiver,
+                    exit.clone(),
+                )
+            })
+            .transpose()?;
+
+        let ledger_cleanup_service = config.ledger_retention.map(|retention| {
+            LedgerCleanupService::new(
+                LedgerCleaner::new(
+                    retention,
+                    blockstore,
+                    bank_forks.clone(),
+                    config.snapshot_config.clone(),
+                ),
+                config.ledger_cleanup_interval,
+                exit.clone(),
+            )
+        });
+
         let last_full_snapshot_slot = starting_snapshot_hashes.map(|x| x.full.0 .0);
         let accounts_background_service = AccountsBackgroundService::new(
             bank_forks.clone(),
@@ -153,6 +192,8 @@ impl StorageBackground {
             accounts_hash_verifier,
             accounts_background_request_sender,
             snapshot_packager_service,
+            ledger_cleanup_service,
+            broadcast_service,
         })
     }
 
@@ -166,5 +207,13 @@ impl StorageBackground {
         self.snapshot_packager_service
             .join()
             .expect("snapshot_packager_service");
+        if let Some(ledger_cleanup_service) = self.ledger_cleanup_service {
+            ledger_cleanup_service
+                .join()
+                .expect("ledger_cleanup_service");
+        }
+        if let Some(broadcast_service) = self.broadcast_service {
+            broadcast_service.join().expect("broadcast_service");
+        }
     }
 }
@@ -0,0 +1,407 @@
+use crate::{
+    error::{BroadcastError, StorageError},
+    replica::LeaderSlot,
+    Result,
+};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use solana_ledger::blockstore::Blockstore;
+use solana_runtime::bank_forks::BankForks;
+use solana_sdk::clock::Slot;
+use std::{
+    io::{self, Read},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(test)]
+mod tests;
+
+const LOOP_INTERVAL: Duration = Duration::from_millis(100);
+/// Followers slower than this are dropped, they resume from their cursor.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(1);
+/// Slots sent to a follower per round, so a catching up one does not hold
+/// back the others.
+const MAX_SLOTS_PER_ROUND: usize = 64;
+
+const SHREDS_FORMAT: u8 = 0;
+const ENTRIES_FORMAT: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    /// Data and coding shreds, as stored by the leader.
+    Shreds,
+    /// Entries, shredded again by the follower.
+    Entries,
+}
+
+/// Sent by a follower once connected, the slots from `from_slot` on are
+/// streamed back as `LeaderSlot` frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subscription {
+    pub from_slot: Slot,
+    pub format: FeedFormat,
+}
+
+impl Subscription {
+    pub const LEN: usize = 9;
+
+    pub fn encode(&self) -> [u8; Self::LEN] {
+        let mut buf = [0; Self::LEN];
+        buf[..8].copy_from_slice(&self.from_slot.to_le_bytes());
+        buf[8] = match self.format {
+            FeedFormat::Shreds => SHREDS_FORMAT,
+            FeedFormat::Entries => ENTRIES_FORMAT,
+        };
+        buf
+    }
+
+    pub fn decode(buf: &[u8; Self::LEN]) -> Option<Self> {
+        let format = match buf[8] {
+            SHREDS_FORMAT => FeedFormat::Shreds,
+            ENTRIES_FORMAT => FeedFormat::Entries,
+            _ => return None,
+        };
+        let mut from_slot = [0; 8];
+        from_slot.copy_from_slice(&buf[..8]);
+        Some(Self {
+            from_slot: Slot::from_le_bytes(from_slot),
+            format,
+        })
+    }
+}
+
+/// Reads the full slot `slot` from the blockstore in the given format, fed
+/// along with `root`, the latest slot finalized by the leader.
+pub fn read_leader_slot(
+    blockstore: &Blockstore,
+    slot: Slot,
+    root: Slot,
+    format: FeedFormat,
+) -> Result<LeaderSlot> {
+    let bank_hash = blockstore
+        .get_bank_hash(slot)
+        .ok_or(StorageError::UnknownBankHash(slot))?;
+    Ok(match format {
+        FeedFormat::Shreds => {
+            let mut shreds = blockstore.get_data_shreds_for_slot(slot, 0)?;
+            shreds.extend(blockstore.get_coding_shreds_for_slot(slot, 0)?);
+            LeaderSlot::Shreds {
+                slot,
+                bank_hash,
+                root,
+                shreds,
+            }
+        }
+        FeedFormat::Entries => LeaderSlot::Entries {
+            slot,
+            parent: blockstore
+                .meta(slot)?
+                .and_then(|meta| meta.parent_slot)
+                .ok_or(StorageError::UnknownSlotMeta(slot))?,
+            bank_hash,
+            root,
+            entries: blockstore.get_slot_entries(slot, 0)?,
+        },
+    })
+}
+
+/// A follower connected but not subscribed yet, its subscription is read
+/// without blocking the other followers.
+struct PendingFollower {
+    addr: SocketAddr,
+    stream: TcpStream,
+    buf: [u8; Subscription::LEN],
+    read: usize,
+    connected_at: Instant,
+}
+
+impl PendingFollower {
+    fn new(stream: TcpStream, addr: SocketAddr) -> Result<Self> {
+        stream.set_nonblocking(true).map_err(BroadcastError::from)?;
+        Ok(Self {
+            addr,
+            stream,
+            buf: [0; Subscription::LEN],
+            read: 0,
+            connected_at: Instant::now(),
+        })
+    }
+
+    /// Reads the available bytes of the subscription, returns it once
+    /// complete.
+    fn poll(&mut self) -> Result<Option<Subscription>> {
+        while self.read < Subscription::LEN {
+            match (&self.stream).read(&mut self.buf[self.read..]) {
+                Ok(0) => return Err(io_error(io::ErrorKind::UnexpectedEof)),
+                Ok(read) => self.read += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if self.connected_at.elapsed() < SUBSCRIPTION_TIMEOUT {
+                        return Ok(None);
+                    }
+                    return Err(io_error(io::ErrorKind::TimedOut));
+                }
+                Err(e) => return Err(BroadcastError::from(e).into()),
+            }
+        }
+        Subscription::decode(&self.buf)
+            .map(Some)
+            .ok_or(BroadcastError::InvalidSubscription(self.addr).into())
+    }
+
+    fn subscribe(self, subscription: Subscription) -> Result<Follower> {
+        self.stream
+            .set_nonblocking(false)
+            .map_err(BroadcastError::from)?;
+        self.stream
+            .set_write_timeout(Some(WRITE_TIMEOUT))
+            .map_err(BroadcastError::from)?;
+        Ok(Follower {
+            addr: self.addr,
+            stream: self.stream,
+            format: subscription.format,
+            cursor: subscription.from_slot,
+        })
+    }
+}
+
+struct Follower {
+    addr: SocketAddr,
+    stream: TcpStream,
+    format: FeedFormat,
+    /// Lowest slot not sent yet.
+    cursor: Slot,
+}
+
+impl Follower {
+    /// Sends the full slots rooted since the cursor, in slot order, along
+    /// with the root of the leader. Returns the number of rooted slots gone
+    /// through, at most `MAX_SLOTS_PER_ROUND`.
+    ///
+    /// The cursor only moves past the slots sent and the roots known to be
+    /// abandoned, a root still missing shreds holds it back.
+    fn send_slots(&mut self, blockstore: &Blockstore, root: Slot) -> Result<usize> {
+        let lowest_cleanup_slot = blockstore.lowest_cleanup_slot();
+        if lowest_cleanup_slot > 0 && self.cursor <= lowest_cleanup_slot {
+            let first_retained_slot = lowest_cleanup_slot + 1;
+            LeaderSlot::write_purged_to(&mut self.stream, first_retained_slot)
+                .map_err(BroadcastError::from)?;
+            return Err(BroadcastError::SlotsPurged {
+                cursor: self.cursor,
+                first_retained_slot,
+            }
+            .into());
+        }
+        let max_root = blockstore.max_root();
+        if self.cursor > max_root {
+            return Ok(0);
+        }
+        let mut num_slots = 0;
+        for (slot, meta) in blockstore.slot_meta_iterator(self.cursor)? {
+            if slot > max_root || num_slots == MAX_SLOTS_PER_ROUND {
+                break;
+            }
+            // the cursor is not a root when the follower resumes after a slot
+            // that was never confirmed, e.g. abandoned by a reorg, it moves
+            // past such slots along with the next root
+            if !blockstore.is_root(slot) {
+                continue;
+            }
+            // rooted without shreds, e.g. the slot of a snapshot, or dead
+            let abandoned = meta.received == 0 || blockstore.is_dead(slot);
+            if !abandoned {
+                if !meta.is_full() {
+                    break;
+                }
+                read_leader_slot(blockstore, slot, root, self.format)?
+                    .write_to(&mut self.stream)
+                    .map_err(BroadcastError::from)?;
+            }
+            self.cursor = slot + 1;
+            num_slots += 1;
+        }
+        Ok(num_slots)
+    }
+
+    /// Streams the slots to the follower on its own thread, so a slow one
+    /// does not hold back the others. Woken up by `signal` on new slots.
+    fn spawn(
+        mut self,
+        blockstore: Arc<Blockstore>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        exit: Arc<AtomicBool>,
+    ) -> Result<FollowerHandle> {
+        let addr = self.addr;
+        let (signal_sender, signal_receiver) = bounded(1);
+        let t_follower = Builder::new()
+            .name("iglooFollower".to_string())
+            .spawn(move || {
+                while !exit.load(Ordering::Relaxed) {
+                    let root = bank_forks.read().unwrap().root();
+                    match self.send_slots(&blockstore, root) {
+                        // catching up
+                        Ok(MAX_SLOTS_PER_ROUND) => continue,
+                        Ok(_) => {}
+                        Err(e) => {
+                            info!("dropping follower {}: {e}", self.addr);
+                            return;
+                        }
+                    }
+                    if let Err(RecvTimeoutError::Disconnected) =
+                        signal_receiver.recv_timeout(LOOP_INTERVAL)
+                    {
+                        return;
+                    }
+                }
+            })
+            .map_err(BroadcastError::from)?;
+        Ok(FollowerHandle {
+            addr,
+            signal_sender,
+            t_follower,
+        })
+    }
+}
+
+struct FollowerHandle {
+    addr: SocketAddr,
+    signal_sender: Sender<()>,
+    t_follower: JoinHandle<()>,
+}
+
+impl FollowerHandle {
+    /// Wakes the follower up, returns whether it is still streaming.
+    fn notify(&self) -> bool {
+        !matches!(
+            self.signal_sender.try_send(()),
+            Err(TrySendError::Disconnected(_))
+        )
+    }
+
+    fn join(self) {
+        if self.t_follower.join().is_err() {
+            error!("follower {} thread panicked", self.addr);
+        }
+    }
+}
+
+/// Streams the slots the sequencer has completed and confirmed to the
+/// followers connected on `StorageConfig::broadcast_addr`, e.g. replicas or
+/// indexers. Woken up by the new shred signal of the blockstore.
+///
+/// Subscriptions are read without blocking, then each follower is streamed to
+/// from its own thread. A follower subscribes from a slot and is dropped on any
+/// error, it resumes by subscribing again from the slot after the last one
+/// received. A follower behind the slots purged by the ledger cleanup gets a
+/// purged frame and is dropped, see `LeaderSlot::write_purged_to`. Every slot
+/// carries the root of the bank forks, the followers root the slots the
+/// leader finalized only.
+pub struct BroadcastService {
+    local_addr: SocketAddr,
+    t_broadcast: JoinHandle<()>,
+}
+
+impl BroadcastService {
+    pub fn new(
+        addr: SocketAddr,
+        blockstore: Arc<Blockstore>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        ledger_signal_receiver: Receiver<bool>,
+        exit: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(addr).map_err(BroadcastError::from)?;
+        listener
+            .set_nonblocking(true)
+            .map_err(BroadcastError::from)?;
+        let local_addr = listener.local_addr().map_err(BroadcastError::from)?;
+        let t_broadcast = Builder::new()
+            .name("iglooBroadcast".to_string())
+            .spawn(move || {
+                let mut pending = vec![];
+                let mut followers: Vec<FollowerHandle> = vec![];
+                while !exit.load(Ordering::Relaxed) {
+                    accept_followers(&listener, &mut pending);
+                    for follower in poll_subscriptions(&mut pending) {
+                        match follower.spawn(blockstore.clone(), bank_forks.clone(), exit.clone()) {
+                            Ok(follower) => followers.push(follower),
+                            Err(e) => warn!("spawning follower failed: {e}"),
+                        }
+                    }
+                    match ledger_signal_receiver.recv_timeout(LOOP_INTERVAL) {
+                        Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                        // the blockstore is closing
+                        Err(RecvTimeoutError::Disconnected) => thread::sleep(LOOP_INTERVAL),
+                    }
+                    let (streaming, dropped) = followers
+                        .into_iter()
+                        .partition::<Vec<_>, _>(FollowerHandle::notify);
+                    dropped.into_iter().for_each(FollowerHandle::join);
+                    followers = streaming;
+                }
+                followers.into_iter().for_each(FollowerHandle::join);
+            })
+            .expect("spawn broadcast thread");
+        Ok(Self {
+            local_addr,
+            t_broadcast,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.t_broadcast.join()
+    }
+}
+
+fn accept_followers(listener: &TcpListener, pending: &mut Vec<PendingFollower>) {
+    loop {
+        match listener.accept() {
+            Ok((stream, addr)) => match PendingFollower::new(stream, addr) {
+                Ok(follower) => pending.push(follower),
+                Err(e) => warn!("rejecting follower {addr}: {e}"),
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                warn!("accepting followers failed: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the followers whose subscription is complete, the others are kept
+/// pending until `SUBSCRIPTION_TIMEOUT`.
+fn poll_subscriptions(pending: &mut Vec<PendingFollower>) -> Vec<Follower> {
+    let mut subscribed = vec![];
+    for mut follower in std::mem::take(pending) {
+        let addr = follower.addr;
+        match follower.poll() {
+            Ok(None) => pending.push(follower),
+            Ok(Some(subscription)) => match follower.subscribe(subscription) {
+                Ok(follower) => {
+                    info!(
+                        "follower {addr} subscribed from slot {} as {:?}",
+                        follower.cursor, follower.format
+                    );
+                    subscribed.push(follower);
+                }
+                Err(e) => warn!("rejecting follower {addr}: {e}"),
+            },
+            Err(e) => warn!("rejecting follower {addr}: {e}"),
+        }
+    }
+    subscribed
+}
+
+fn io_error(kind: io::ErrorKind) -> crate::Error {
+    BroadcastError::from(io::Error::from(kind)).into()
+}
@@ -0,0 +1,177 @@
+use super::{FeedFormat, Subscription};
+use crate::{
+    config::GlobalConfig, replica::LeaderSlot, tests::mock::commit_empty_block, RollupStorage,
+};
+use anyhow::Result;
+use solana_entry::entry::create_ticks;
+use solana_ledger::blockstore::entries_to_test_shreds;
+use solana_sdk::{clock::Slot, hash::Hash};
+use std::{
+    io::{self, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+fn subscribe(store: &RollupStorage, from_slot: Slot, format: FeedFormat) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(store.broadcast_addr().unwrap())?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.write_all(&Subscription { from_slot, format }.encode())?;
+    Ok(stream)
+}
+
+async fn confirm_new_block(store: &mut RollupStorage, slot: Slot) -> Result<()> {
+    store.bump()?;
+    commit_empty_block(store).await?;
+    store.confirm(slot)?;
+    Ok(())
+}
+
+#[test]
+fn subscription_encoding_works() {
+    let subscription = Subscription {
+        from_slot: 42,
+        format: FeedFormat::Entries,
+    };
+    assert_eq!(
+        Subscription::decode(&subscription.encode()),
+        Some(subscription)
+    );
+
+    let mut buf = subscription.encode();
+    buf[8] = 2;
+    assert_eq!(Subscription::decode(&buf), None);
+}
+
+#[tokio::test]
+async fn broadcast_streams_confirmed_slots() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut config = GlobalConfig::new_temp(&ledger_path)?;
+    config.storage.broadcast_addr = Some("127.0.0.1:0".parse()?);
+    let mut store = RollupStorage::new(config)?;
+    store.init()?;
+    confirm_new_block(&mut store, 1).await?;
+    confirm_new_block(&mut store, 2).await?;
+    // completed but not confirmed yet
+    store.bump()?;
+    commit_empty_block(&mut store).await?;
+
+    // a follower not subscribing does not hold back the others
+    let _idle = TcpStream::connect(store.broadcast_addr().unwrap())?;
+    let mut follower = subscribe(&store, 1, FeedFormat::Shreds)?;
+    for slot in 1..=2 {
+        let LeaderSlot::Shreds {
+            slot: read,
+            bank_hash,
+            root,
+            shreds,
+        } = LeaderSlot::read_from(&mut follower)?.unwrap()
+        else {
+            panic!("shreds expected");
+        };
+        assert_eq!(read, slot);
+        assert_eq!(bank_hash, store.get_bank(slot)?.hash());
+        // confirmed but not finalized
+        assert_eq!(root, 0);
+        assert_eq!(
+            shreds.len(),
+            store.blockstore.get_data_shreds_for_slot(slot, 0)?.len()
+                + store.blockstore.get_coding_shreds_for_slot(slot, 0)?.len()
+        );
+    }
+
+    // new slots are streamed once confirmed, along with the new root
+    store.set_root(1, None)?;
+    store.confirm(3)?;
+    let leader_slot = LeaderSlot::read_from(&mut follower)?.unwrap();
+    assert_eq!((leader_slot.slot(), leader_slot.root()), (3, 1));
+
+    // resuming from a cursor, as entries
+    let mut follower = subscribe(&store, 3, FeedFormat::Entries)?;
+    match LeaderSlot::read_from(&mut follower)?.unwrap() {
+        LeaderSlot::Entries {
+            slot,
+            parent,
+            bank_hash,
+            entries,
+            ..
+        } => {
+            assert_eq!((slot, parent), (3, 2));
+            assert_eq!(bank_hash, store.get_bank(3)?.hash());
+            assert_eq!(entries, store.blockstore.get_slot_entries(3, 0)?);
+        }
+        other => panic!("entries expected, got {other:?}"),
+    }
+
+    // an invalid subscription is closed
+    let mut follower = TcpStream::connect(store.broadcast_addr().unwrap())?;
+    follower.set_read_timeout(Some(Duration::from_secs(5)))?;
+    follower.write_all(&[0xff; Subscription::LEN])?;
+    assert!(LeaderSlot::read_from(&mut follower)?.is_none());
+
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn broadcast_resumes_from_any_cursor() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut config = GlobalConfig::new_temp(&ledger_path)?;
+    config.storage.broadcast_addr = Some("127.0.0.1:0".parse()?);
+    let mut store = RollupStorage::new(config)?;
+    store.init()?;
+    confirm_new_block(&mut store, 1).await?;
+    confirm_new_block(&mut store, 2).await?;
+    // slot 3 is skipped, the cursor of a follower past slot 2 is not a root
+    store.bump_slot(4)?;
+    commit_empty_block(&mut store).await?;
+    store.confirm(4)?;
+
+    let mut follower = subscribe(&store, 3, FeedFormat::Entries)?;
+    match LeaderSlot::read_from(&mut follower)?.unwrap() {
+        LeaderSlot::Entries { slot, parent, .. } => assert_eq!((slot, parent), (4, 2)),
+        other => panic!("entries expected, got {other:?}"),
+    }
+
+    // slots purged by the ledger cleanup are answered with an error
+    *store.blockstore.lowest_cleanup_slot.write().unwrap() = 2;
+    let mut follower = subscribe(&store, 1, FeedFormat::Shreds)?;
+    let err = LeaderSlot::read_from(&mut follower).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("before 3"));
+    assert!(LeaderSlot::read_from(&mut follower)?.is_none());
+    // the retained ones are still streamed
+    let mut follower = subscribe(&store, 3, FeedFormat::Shreds)?;
+    assert_eq!(LeaderSlot::read_from(&mut follower)?.unwrap().slot(), 4);
+
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn broadcast_waits_for_roots_missing_shreds() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut config = GlobalConfig::new_temp(&ledger_path)?;
+    config.storage.broadcast_addr = Some("127.0.0.1:0".parse()?);
+    let mut store = RollupStorage::new(config)?;
+    store.init()?;
+    confirm_new_block(&mut store, 1).await?;
+
+    // slot 2 is rooted before all of its shreds are in
+    let mut shreds =
+        entries_to_test_shreds(&create_ticks(64, 0, Hash::default()), 2, 1, true, 0, true);
+    let last = shreds.split_off(shreds.len() - 1);
+    store.blockstore.insert_shreds(shreds, None, false)?;
+    store
+        .blockstore
+        .insert_bank_hash(2, Hash::new_unique(), false);
+    store.blockstore.set_roots(std::iter::once(&2))?;
+
+    let mut follower = subscribe(&store, 1, FeedFormat::Shreds)?;
+    assert_eq!(LeaderSlot::read_from(&mut follower)?.unwrap().slot(), 1);
+    store.blockstore.insert_shreds(last, None, false)?;
+    assert!(store.blockstore.is_full(2));
+    assert_eq!(LeaderSlot::read_from(&mut follower)?.unwrap().slot(), 2);
+
+    store.close().await?;
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::config::LedgerRetention;
+use solana_ledger::blockstore::{Blockstore, PurgeType};
+use solana_runtime::{bank_forks::BankForks, snapshot_config::SnapshotConfig, snapshot_utils};
+use solana_sdk::clock::Slot;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[cfg(test)]
+mod tests;
+
+const LOOP_INTERVAL: Duration = Duration::from_millis(100);
+
+impl LedgerRetention {
+    /// Lowest slot to keep, given the highest confirmed slot and the
+    /// finalized one. Slots above the finalized slot may still be reorged, so
+    /// they are always kept.
+    pub fn first_retained_slot(&self, confirmed: Slot, finalized: Slot) -> Slot {
+        let slot = match *self {
+            Self::LastSlots(slots) => confirmed.saturating_add(1).saturating_sub(slots),
+            Self::Finalized { margin } => finalized.saturating_sub(margin),
+        };
+        slot.min(finalized)
+    }
+}
+
+/// Purges the shreds, transaction statuses and address signatures of the
+/// slots below the ones retained by a `LedgerRetention`.
+pub struct LedgerCleaner {
+    retention: LedgerRetention,
+    blockstore: Arc<Blockstore>,
+    bank_forks: Arc<RwLock<BankForks>>,
+    snapshot_config: SnapshotConfig,
+    /// Lowest slot not purged yet.
+    next_purge_slot: Slot,
+}
+
+impl LedgerCleaner {
+    pub fn new(
+        retention: LedgerRetention,
+        blockstore: Arc<Blockstore>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        snapshot_config: SnapshotConfig,
+    ) -> Self {
+        let next_purge_slot = blockstore
+            .slot_meta_iterator(0)
+            .ok()
+            .and_then(|mut metas| metas.next())
+            .map_or(0, |(slot, _)| slot);
+        Self {
+            retention,
+            blockstore,
+            bank_forks,
+            snapshot_config,
+            next_purge_slot,
+        }
+    }
+
+    /// Lowest slot to keep. When snapshots are generated, the slots after the
+    /// latest snapshot archive are kept as well, a restart replays them.
+    pub fn first_retained_slot(&self) -> Slot {
+        let finalized = self.bank_forks.read().unwrap().root();
+        let slot = self
+            .retention
+            .first_retained_slot(self.blockstore.max_root(), finalized);
+        if !self.snapshot_config.should_generate_snapshots() {
+            return slot;
+        }
+        highest_snapshot_archive_slot(&self.snapshot_config).map_or(0, |archive| slot.min(archive))
+    }
+
+    /// Purges the slots below `first_retained_slot`, returns the highest
+    /// purged slot if any.
+    pub fn cleanup(&mut self) -> Option<Slot> {
+        let first_retained_slot = self.first_retained_slot();
+        if first_retained_slot <= self.next_purge_slot {
+            return None;
+        }
+
+        let lowest_cleanup_slot = first_retained_slot - 1;
+        // readers of the purged slots get `SlotCleanedUp` from now on
+        *self.blockstore.lowest_cleanup_slot.write().unwrap() = lowest_cleanup_slot;
+        self.blockstore
+            .purge_slots(self.next_purge_slot, lowest_cleanup_slot, PurgeType::Exact);
+        self.blockstore.set_max_expired_slot(lowest_cleanup_slot);
+        debug!(
+            "purged ledger slots {}..={lowest_cleanup_slot}",
+            self.next_purge_slot
+        );
+        self.next_purge_slot = first_retained_slot;
+        Some(lowest_cleanup_slot)
+    }
+}
+
+/// Runs a `LedgerCleaner` every `StorageConfig::ledger_cleanup_interval`.
+pub struct LedgerCleanupService {
+    t_cleanup: JoinHandle<()>,
+}
+
+impl LedgerCleanupService {
+    pub fn new(mut cleaner: LedgerCleaner, interval: Duration, exit: Arc<AtomicBool>) -> Self {
+        let t_cleanup = Builder::new()
+            .name("iglooLedgerClean".to_string())
+            .spawn(move || {
+                let mut last_cleanup = Instant::now();
+                while !exit.load(Ordering::Relaxed) {
+                    if last_cleanup.elapsed() >= interval {
+                        cleaner.cleanup();
+                        last_cleanup = Instant::now();
+                    }
+                    thread::sleep(LOOP_INTERVAL);
+                }
+            })
+            .expect("spawn ledger cleanup thread");
+        Self { t_cleanup }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.t_cleanup.join()
+    }
+}
+
+fn highest_snapshot_archive_slot(snapshot_config: &SnapshotConfig) -> Option<Slot> {
+    let full = snapshot_utils::get_highest_full_snapshot_archive_slot(
+        &snapshot_config.full_snapshot_archives_dir,
+    )?;
+    Some(
+        snapshot_utils::get_highest_incremental_snapshot_archive_slot(
+            &snapshot_config.incremental_snapshot_archives_dir,
+            full,
+        )
+        .unwrap_or(full),
+    )
+}
@@ -0,0 +1,111 @@
+use super::LedgerCleaner;
+use crate::{
+    config::{GlobalConfig, LedgerRetention},
+    tests::mock::commit_empty_block,
+    RollupStorage,
+};
+use anyhow::Result;
+use solana_runtime::snapshot_config::SnapshotConfig;
+use solana_sdk::clock::Slot;
+
+fn first_ledger_slot(store: &RollupStorage) -> Option<Slot> {
+    store
+        .blockstore
+        .slot_meta_iterator(0)
+        .unwrap()
+        .next()
+        .map(|(slot, _)| slot)
+}
+
+/// Confirms slots up to `confirmed`, then finalizes them up to `finalized`.
+async fn new_store(
+    confirmed: Slot,
+    finalized: Slot,
+    generate_snapshots: bool,
+) -> Result<RollupStorage> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut config = GlobalConfig::new_temp(&ledger_path)?;
+    if !generate_snapshots {
+        config.storage.snapshot_config = SnapshotConfig::new_load_only();
+    }
+    let mut store = RollupStorage::new(config)?;
+    store.init()?;
+    for slot in 1..=confirmed {
+        store.bump()?;
+        commit_empty_block(&mut store).await?;
+        store.confirm(slot)?;
+    }
+    store.set_root(finalized, None)?;
+    Ok(store)
+}
+
+fn new_cleaner(store: &RollupStorage, retention: LedgerRetention) -> LedgerCleaner {
+    LedgerCleaner::new(
+        retention,
+        store.blockstore(),
+        store.bank_forks(),
+        store.config.storage.snapshot_config.clone(),
+    )
+}
+
+#[test]
+fn first_retained_slot_works() {
+    let last_slots = LedgerRetention::LastSlots(3);
+    assert_eq!(last_slots.first_retained_slot(10, 10), 8);
+    assert_eq!(last_slots.first_retained_slot(10, 5), 5);
+    assert_eq!(last_slots.first_retained_slot(1, 1), 0);
+
+    let finalized = LedgerRetention::Finalized { margin: 2 };
+    assert_eq!(finalized.first_retained_slot(10, 6), 4);
+    assert_eq!(finalized.first_retained_slot(10, 1), 0);
+}
+
+#[tokio::test]
+async fn cleanup_keeps_last_slots() -> Result<()> {
+    let store = new_store(6, 6, false).await?;
+    let mut cleaner = new_cleaner(&store, LedgerRetention::LastSlots(2));
+    assert_eq!(first_ledger_slot(&store), Some(0));
+
+    assert_eq!(cleaner.cleanup(), Some(4));
+    assert_eq!(first_ledger_slot(&store), Some(5));
+    assert_eq!(store.blockstore.lowest_cleanup_slot(), 4);
+    assert!(store.blockstore.meta(4)?.is_none());
+    assert!(store.blockstore.is_full(5));
+    // the parent of slot 5 is gone, slot 6 is the first complete block
+    assert_eq!(store.blockstore.get_first_available_block()?, 6);
+    assert!(store.blockstore.get_rooted_block(4, false).is_err());
+
+    // nothing new to purge
+    assert_eq!(cleaner.cleanup(), None);
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cleanup_keeps_unfinalized_slots() -> Result<()> {
+    let store = new_store(6, 3, false).await?;
+    let mut cleaner = new_cleaner(&store, LedgerRetention::Finalized { margin: 1 });
+
+    assert_eq!(cleaner.cleanup(), Some(1));
+    assert_eq!(first_ledger_slot(&store), Some(2));
+
+    // confirmed but not finalized slots are kept whatever the retention
+    let mut cleaner = new_cleaner(&store, LedgerRetention::LastSlots(1));
+    assert_eq!(cleaner.first_retained_slot(), 3);
+    assert_eq!(cleaner.cleanup(), Some(2));
+    assert_eq!(first_ledger_slot(&store), Some(3));
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn cleanup_keeps_slots_after_latest_snapshot() -> Result<()> {
+    // snapshots are generated but none is archived yet
+    let store = new_store(4, 4, true).await?;
+    let mut cleaner = new_cleaner(&store, LedgerRetention::LastSlots(1));
+    assert_eq!(cleaner.first_retained_slot(), 0);
+    assert_eq!(cleaner.cleanup(), None);
+    assert_eq!(first_ledger_slot(&store), Some(0));
+    store.close().await?;
+    Ok(())
+}
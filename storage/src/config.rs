@@ -1,5 +1,6 @@
 use std::{
     collections::HashSet,
+    net::SocketAddr,
     path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
@@ -21,7 +22,7 @@ use solana_sdk::{
 };
 use solana_svm::runtime_config::RuntimeConfig;
 
-use crate::{init::init_config, Result, RollupStorage};
+use crate::{init::init_config, state::DEFAULT_STATE_HISTORY_SLOTS, Result, RollupStorage};
 
 pub const MAX_GENESIS_ARCHIVE_UNPACKED_SIZE: u64 = 10 * 1024 * 1024; // 10 MiB
 
@@ -110,6 +111,24 @@ pub struct StorageConfig {
     pub runtime_config: RuntimeConfig,
     pub history_config: HistoryConfig,
     pub use_snapshot_archives_at_startup: UseSnapshotArchivesAtStartup,
+    /// Rooted slots whose state root and account proofs stay available.
+    pub state_history_slots: u64,
+    /// Slots kept in the ledger, everything is kept if `None`.
+    pub ledger_retention: Option<LedgerRetention>,
+    pub ledger_cleanup_interval: Duration,
+    /// Address the completed slots are streamed to followers on, no
+    /// broadcast if `None`.
+    pub broadcast_addr: Option<SocketAddr>,
+}
+
+/// Which slots the ledger cleanup keeps, slots above the finalized one are
+/// never purged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerRetention {
+    /// The last N confirmed slots.
+    LastSlots(u64),
+    /// The slots from `margin` slots below the L1-finalized slot.
+    Finalized { margin: u64 },
 }
 
 #[derive(Clone)]
@@ -145,6 +164,10 @@ impl Default for StorageConfig {
             runtime_config: RuntimeConfig::default(),
             history_config: Default::default(),
             use_snapshot_archives_at_startup: UseSnapshotArchivesAtStartup::default(),
+            state_history_slots: DEFAULT_STATE_HISTORY_SLOTS,
+            ledger_retention: None,
+            ledger_cleanup_interval: Duration::from_secs(10),
+            broadcast_addr: None,
         }
     }
 }
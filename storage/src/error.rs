@@ -1,5 +1,6 @@
 use solana_ledger::blockstore::BlockstoreError;
-use solana_sdk::clock::Slot;
+use solana_sdk::{clock::Slot, hash::Hash};
+use std::net::SocketAddr;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -38,6 +39,21 @@ pub enum Error {
 
     #[error(transparent)]
     AccountDbError(#[from] AccountDbError),
+
+    #[error(transparent)]
+    StateError(#[from] StateError),
+
+    #[error(transparent)]
+    SnapshotError(#[from] SnapshotError),
+
+    #[error(transparent)]
+    BootstrapError(#[from] BootstrapError),
+
+    #[error(transparent)]
+    ReplicaError(#[from] ReplicaError),
+
+    #[error(transparent)]
+    BroadcastError(#[from] BroadcastError),
 }
 
 #[derive(Debug, Error)]
@@ -51,6 +67,9 @@ pub enum StorageError {
     #[error("Unknown slot meta, slot: {0}")]
     UnknownSlotMeta(Slot),
 
+    #[error("Unknown bank hash, slot: {0}")]
+    UnknownBankHash(Slot),
+
     #[error("Unknown last index, slot: {0}")]
     UnknownLastIndex(Slot),
 
@@ -90,3 +109,106 @@ pub enum AccountDbError {
     #[error("Convert transaction error: {0}")]
     ConvertTxError(String),
 }
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("State of slot {0} is not available")]
+    SlotNotAvailable(Slot),
+
+    #[error("Persist state failed: {0}")]
+    PersistFailed(String),
+
+    #[error("Corrupted state: {0}")]
+    Corrupted(String),
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Archive snapshot failed: {0}")]
+    ArchiveFailed(String),
+
+    #[error("Invalid snapshot archive: {0}")]
+    InvalidArchive(String),
+
+    #[error("No full snapshot archive to build an incremental one on")]
+    NoFullSnapshot,
+
+    #[error("Slot {slot} is not after the full snapshot at slot {full_slot}")]
+    NotAfterFullSnapshot { slot: Slot, full_slot: Slot },
+
+    #[error("Incremental snapshot based on slot {base_slot} does not build on full snapshot at slot {full_slot}")]
+    BaseSlotMismatch { base_slot: Slot, full_slot: Slot },
+
+    #[error("Snapshot archive io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum BootstrapError {
+    #[error("Expected genesis hash is not configured")]
+    MissingExpectedGenesisHash,
+
+    #[error("Request to peer failed: {0}")]
+    RequestFailed(String),
+
+    #[error("Peer does not serve {0}")]
+    NotFound(String),
+
+    #[error("Invalid genesis: {0}")]
+    InvalidGenesis(String),
+
+    #[error("Genesis hash mismatch: hash={hash} expected={expected}")]
+    GenesisHashMismatch { hash: Hash, expected: Hash },
+
+    #[error("Bootstrap io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ReplicaError {
+    #[error("Unknown leader of slot {0}")]
+    UnknownLeader(Slot),
+
+    #[error("Invalid shred of slot {slot} at index {index}")]
+    InvalidShred { slot: Slot, index: u32 },
+
+    #[error("Invalid parent {parent} of slot {slot}")]
+    InvalidParent { slot: Slot, parent: Slot },
+
+    #[error("Slot {0} is not full")]
+    SlotNotFull(Slot),
+
+    #[error("Parent {parent:?} of slot {slot} is not replayed")]
+    UnknownParent { slot: Slot, parent: Option<Slot> },
+
+    #[error("Slot {0} is replayed already")]
+    AlreadyReplayed(Slot),
+
+    #[error("Bank hash {actual} of slot {slot} does not match {expected} of the leader")]
+    BankHashMismatch {
+        slot: Slot,
+        expected: Hash,
+        actual: Hash,
+    },
+
+    #[error("Replay of slot {0} failed: {1}")]
+    ReplayFailed(Slot, String),
+
+    #[error("Slot {0} is dead")]
+    DeadSlot(Slot),
+}
+
+#[derive(Debug, Error)]
+pub enum BroadcastError {
+    #[error("Invalid subscription from follower {0}")]
+    InvalidSubscription(SocketAddr),
+
+    #[error("Slot {cursor} is purged, the first retained slot is {first_retained_slot}")]
+    SlotsPurged {
+        cursor: Slot,
+        first_retained_slot: Slot,
+    },
+
+    #[error("Broadcast io error: {0}")]
+    Io(#[from] std::io::Error),
+}
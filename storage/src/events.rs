@@ -1,13 +1,101 @@
-use crate::{history::TransactionBatchHistoryInfo, RollupStorage};
-use crossbeam_channel::Receiver;
+use crate::{
+    blockstore::txs::CommitBatch, execution::TransactionsResultWrapper,
+    history::TransactionBatchHistoryInfo, RollupStorage,
+};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use solana_runtime::bank::Bank;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::TransactionError};
+use std::sync::Arc;
 
 impl RollupStorage {
     pub fn on_block_complete(&self, history_info: TransactionBatchHistoryInfo) {
         self.notify_block_complete();
         self.send_transaction_history_status(history_info);
     }
+
+    /// Subscribes to commit events, e.g. to drive websocket notifications.
+    pub fn subscribe_commit_events(&mut self) -> Receiver<CommitEvent> {
+        let (sender, receiver) = unbounded();
+        self.events.commit_senders.push(sender);
+        receiver
+    }
+
+    pub(crate) fn has_commit_subscribers(&self) -> bool {
+        !self.events.commit_senders.is_empty()
+    }
+
+    pub(crate) fn send_commit_event(&mut self, event: CommitEvent) {
+        // subscribers dropping their receiver are removed
+        self.events
+            .commit_senders
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
 }
 
+#[derive(Default)]
 pub struct EventsHub {
     pub ledger_signal_receiver: Option<Receiver<bool>>,
+    pub commit_senders: Vec<Sender<CommitEvent>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CommitEvent {
+    /// Transactions have been committed into the working bank.
+    Block {
+        bank: Arc<Bank>,
+        transactions: Arc<Vec<CommittedTransaction>>,
+    },
+    /// The bank has been confirmed by [`RollupStorage::confirm`].
+    Confirmed(Arc<Bank>),
+    /// The bank has become the root of the bank forks.
+    Rooted(Arc<Bank>),
+}
+
+/// Executed transaction of a committed block.
+#[derive(Debug, Clone)]
+pub struct CommittedTransaction {
+    pub signature: Signature,
+    pub err: Option<TransactionError>,
+    pub log_messages: Vec<String>,
+    pub account_keys: Vec<Pubkey>,
+    pub writable_accounts: Vec<Pubkey>,
+    pub is_vote: bool,
+}
+
+impl CommittedTransaction {
+    pub(crate) fn collect(
+        results: &[TransactionsResultWrapper],
+        batches: &[CommitBatch],
+    ) -> Vec<Self> {
+        results
+            .iter()
+            .zip(batches)
+            .flat_map(|(result, batch)| {
+                result
+                    .output
+                    .execution_results
+                    .iter()
+                    .zip(batch.transactions())
+            })
+            .filter_map(|(execution_result, tx)| {
+                let details = execution_result.details()?;
+                let message = tx.message();
+                let account_keys = message.account_keys().iter().copied().collect::<Vec<_>>();
+                let writable_accounts = account_keys
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| message.is_writable(*index))
+                    .map(|(_, key)| *key)
+                    .collect();
+                Some(Self {
+                    signature: *tx.signature(),
+                    err: details.status.clone().err(),
+                    log_messages: details.log_messages.clone().unwrap_or_default(),
+                    account_keys,
+                    writable_accounts,
+                    is_vote: tx.is_simple_vote_transaction(),
+                })
+            })
+            .collect()
+    }
 }
@@ -1,10 +1,11 @@
 use crate::Error;
-use crate::{blockstore::txs::CommitBatch, Result, RollupStorage};
+use crate::{blockstore::txs::CommitBatch, events::CommittedTransaction, Result, RollupStorage};
 use solana_sdk::transaction::{SanitizedTransaction, VersionedTransaction};
 use solana_svm::{
     transaction_processor::LoadAndExecuteSanitizedTransactionsOutput,
     transaction_results::TransactionResults,
 };
+use std::slice;
 
 #[cfg(test)]
 mod tests;
@@ -13,7 +14,38 @@ pub struct TransactionsResultWrapper {
     pub output: LoadAndExecuteSanitizedTransactionsOutput,
 }
 
+/// Batches of the working bank committed by [`RollupStorage::commit_ahead`],
+/// not written to the blockstore yet.
+#[derive(Default)]
+pub(crate) struct CommittedAhead {
+    pub(crate) transactions: Vec<Vec<VersionedTransaction>>,
+    pub(crate) events: Vec<CommittedTransaction>,
+}
+
 impl RollupStorage {
+    /// Commits a batch to the working bank before the rest of the block is
+    /// executed, e.g. deposits spent by the block. Its transactions are written
+    /// first in the entries of the block by the next `commit`.
+    pub fn commit_ahead(
+        &mut self,
+        result: TransactionsResultWrapper,
+        origin: CommitBatch,
+    ) -> Result<TransactionResults> {
+        let transactions = result.success_txs(origin.transactions());
+        if self.has_commit_subscribers() {
+            let events =
+                CommittedTransaction::collect(slice::from_ref(&result), slice::from_ref(&origin));
+            self.committed_ahead.events.extend(events);
+        }
+        let result = if self.enable_history() {
+            self.single_batch_commit_with_history(result, origin)?
+        } else {
+            self.single_batch_commit(result, &origin)?
+        };
+        self.committed_ahead.transactions.push(transactions);
+        Ok(result)
+    }
+
     pub(crate) fn commit_block(
         &mut self,
         result: Vec<TransactionsResultWrapper>,
@@ -27,8 +59,12 @@ impl RollupStorage {
 
         let mut data_entries = vec![];
         let mut start_hash = None;
-        for (result, origin) in result.iter().zip(origin.iter()) {
-            let executed_txs = result.success_txs(origin.transactions());
+        let executed_ahead = std::mem::take(&mut self.committed_ahead.transactions);
+        let executed = result
+            .iter()
+            .zip(origin.iter())
+            .map(|(result, origin)| result.success_txs(origin.transactions()));
+        for executed_txs in executed_ahead.into_iter().chain(executed) {
             let entry = self.transactions_to_entry(executed_txs, start_hash)?;
             start_hash = Some(entry.hash);
             data_entries.push(entry);
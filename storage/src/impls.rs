@@ -1,6 +1,12 @@
 use crate::{
-    background::StorageBackground, blockstore::txs::CommitBatch, config::GlobalConfig,
-    error::BankError, execution::TransactionsResultWrapper, history::StorageHistoryServices,
+    background::StorageBackground,
+    blockstore::txs::CommitBatch,
+    config::GlobalConfig,
+    error::BankError,
+    events::{CommitEvent, CommittedTransaction, EventsHub},
+    execution::{CommittedAhead, TransactionsResultWrapper},
+    history::StorageHistoryServices,
+    state::StateCommitment,
     BankInfo, Error, Result,
 };
 use solana_gossip::cluster_info::ClusterInfo;
@@ -11,17 +17,30 @@ use solana_ledger::{
 use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::{account::AccountSharedData, clock::Slot, pubkey::Pubkey, signer::Signer};
 use solana_svm::transaction_processing_callback::TransactionProcessingCallback;
-use std::sync::{atomic::AtomicBool, Arc, RwLock};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
 
 pub struct RollupStorage {
     pub(crate) bank: Arc<Bank>,
     pub(crate) bank_forks: Arc<RwLock<BankForks>>,
+    /// Highest slot passed to [`RollupStorage::confirm`], lowered again on reorg.
+    pub(crate) confirmed_slot: Arc<AtomicU64>,
+    /// Account state commitment of the retained slots.
+    pub(crate) state: Arc<Mutex<StateCommitment>>,
+    /// Batches committed to the working bank ahead of its block.
+    pub(crate) committed_ahead: CommittedAhead,
 
     pub(crate) cluster_info: Arc<ClusterInfo>,
     pub(crate) config: GlobalConfig,
     pub(crate) blockstore: Arc<Blockstore>,
     pub(crate) background_service: StorageBackground,
     pub(crate) history_services: StorageHistoryServices,
+    pub(crate) events: EventsHub,
     pub(crate) leader_schedule_cache: Arc<LeaderScheduleCache>,
     pub(crate) process_options: ProcessOptions,
     pub(crate) exit: Arc<AtomicBool>,
@@ -104,6 +123,20 @@ impl RollupStorage {
         self.blockstore.clone()
     }
 
+    pub fn confirmed_slot(&self) -> Slot {
+        self.confirmed_slot.load(Ordering::Relaxed)
+    }
+
+    /// Shared handle of the confirmed slot, for services that follow it (e.g. RPC).
+    pub fn confirmed_slot_tracker(&self) -> Arc<AtomicU64> {
+        self.confirmed_slot.clone()
+    }
+
+    /// Shared handle of the state commitment, for services proving accounts (e.g. RPC).
+    pub fn state_commitment(&self) -> Arc<Mutex<StateCommitment>> {
+        self.state.clone()
+    }
+
     pub fn history_services(&self) -> &StorageHistoryServices {
         &self.history_services
     }
@@ -112,12 +145,32 @@ impl RollupStorage {
         self.cluster_info.clone()
     }
 
+    /// Address the broadcast service listens on, if started.
+    pub fn broadcast_addr(&self) -> Option<SocketAddr> {
+        self.background_service
+            .broadcast_service
+            .as_ref()
+            .map(|service| service.local_addr())
+    }
+
     pub async fn commit<'a>(
         &mut self,
         result: Vec<TransactionsResultWrapper>,
         origin: Vec<CommitBatch<'a>>,
     ) -> Result<()> {
+        let ahead = std::mem::take(&mut self.committed_ahead.events);
+        let transactions = self.has_commit_subscribers().then(|| {
+            let mut transactions = ahead;
+            transactions.extend(CommittedTransaction::collect(&result, &origin));
+            transactions
+        });
         self.commit_block(result, origin)?;
+        if let Some(transactions) = transactions {
+            self.send_commit_event(CommitEvent::Block {
+                bank: self.bank.clone(),
+                transactions: Arc::new(transactions),
+            });
+        }
         Ok(())
     }
 
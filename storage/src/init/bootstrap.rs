@@ -0,0 +1,150 @@
+use crate::{config::GlobalConfig, error::BootstrapError, Result, RollupStorage};
+use solana_accounts_db::hardened_unpack::open_genesis_config;
+use solana_runtime::snapshot_archive_info::{
+    FullSnapshotArchiveInfo, IncrementalSnapshotArchiveInfo, SnapshotArchiveInfoGetter,
+};
+use solana_sdk::{
+    genesis_config::{
+        DEFAULT_GENESIS_ARCHIVE, DEFAULT_GENESIS_DOWNLOAD_PATH, DEFAULT_GENESIS_FILE,
+    },
+    hash::Hash,
+};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Redirects to the latest full snapshot archive of the peer.
+pub const FULL_SNAPSHOT_DOWNLOAD_PATH: &str = "/snapshot.tar.bz2";
+/// Redirects to the latest incremental snapshot archive of the peer.
+pub const INCREMENTAL_SNAPSHOT_DOWNLOAD_PATH: &str = "/incremental-snapshot.tar.bz2";
+
+impl RollupStorage {
+    /// Bootstraps a fresh node from the genesis and the latest snapshot
+    /// archives served by the RPC of the peer at `peer_url`, e.g.
+    /// `http://127.0.0.1:8899`.
+    ///
+    /// The genesis is checked against `StorageConfig::expected_genesis_hash`,
+    /// which must be set, and the snapshots against their archive hash when
+    /// loaded. `init` is still to be called.
+    pub async fn bootstrap_from_peer(config: GlobalConfig, peer_url: &str) -> Result<Self> {
+        let expected_genesis_hash = config
+            .storage
+            .expected_genesis_hash
+            .ok_or(BootstrapError::MissingExpectedGenesisHash)?;
+        let peer_url = peer_url.trim_end_matches('/');
+        let client = reqwest::Client::new();
+        fs::create_dir_all(&config.ledger_path).map_err(BootstrapError::from)?;
+        let download_dir =
+            tempfile::tempdir_in(&config.ledger_path).map_err(BootstrapError::from)?;
+
+        if config.ledger_path.join(DEFAULT_GENESIS_FILE).exists() {
+            check_genesis(&config.ledger_path, &config, expected_genesis_hash)?;
+        } else {
+            download_genesis(
+                &client,
+                peer_url,
+                &config,
+                expected_genesis_hash,
+                download_dir.path(),
+            )
+            .await?;
+        }
+
+        let full = download(
+            &client,
+            &format!("{peer_url}{FULL_SNAPSHOT_DOWNLOAD_PATH}"),
+            download_dir.path(),
+        )
+        .await?
+        .ok_or_else(|| BootstrapError::NotFound("a full snapshot".to_string()))?;
+        let full_slot = FullSnapshotArchiveInfo::new_from_path(full.clone())
+            .map_err(|e| BootstrapError::NotFound(format!("a valid full snapshot: {e}")))?
+            .slot();
+        let incremental = download(
+            &client,
+            &format!("{peer_url}{INCREMENTAL_SNAPSHOT_DOWNLOAD_PATH}"),
+            download_dir.path(),
+        )
+        .await?
+        // the peer may have archived a newer full snapshot in between
+        .filter(|incremental| {
+            IncrementalSnapshotArchiveInfo::new_from_path(incremental.clone())
+                .is_ok_and(|info| info.base_slot() == full_slot)
+        });
+
+        info!("bootstrapping from {peer_url} with snapshots {full:?} and {incremental:?}");
+        Self::new_from_snapshot(config, &full, incremental.as_deref())
+    }
+}
+
+async fn download_genesis(
+    client: &reqwest::Client,
+    peer_url: &str,
+    config: &GlobalConfig,
+    expected_genesis_hash: Hash,
+    download_dir: &Path,
+) -> Result<()> {
+    let archive = download(
+        client,
+        &format!("{peer_url}{DEFAULT_GENESIS_DOWNLOAD_PATH}"),
+        download_dir,
+    )
+    .await?
+    .ok_or_else(|| BootstrapError::NotFound("the genesis".to_string()))?;
+    check_genesis(download_dir, config, expected_genesis_hash)?;
+
+    let ledger_path = &config.ledger_path;
+    fs::rename(
+        download_dir.join(DEFAULT_GENESIS_FILE),
+        ledger_path.join(DEFAULT_GENESIS_FILE),
+    )
+    .map_err(BootstrapError::from)?;
+    fs::rename(archive, ledger_path.join(DEFAULT_GENESIS_ARCHIVE)).map_err(BootstrapError::from)?;
+    Ok(())
+}
+
+/// Checks the hash of the genesis in `dir`, unpacked from its archive if need be.
+fn check_genesis(dir: &Path, config: &GlobalConfig, expected_genesis_hash: Hash) -> Result<()> {
+    let genesis_config = open_genesis_config(dir, config.storage.max_genesis_archive_unpacked_size)
+        .map_err(|e| BootstrapError::InvalidGenesis(e.to_string()))?;
+    let hash = genesis_config.hash();
+    if hash != expected_genesis_hash {
+        return Err(BootstrapError::GenesisHashMismatch {
+            hash,
+            expected: expected_genesis_hash,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Downloads `url` into `dir`, under the file name it redirects to. Returns
+/// `None` if the peer has nothing there.
+async fn download(client: &reqwest::Client, url: &str, dir: &Path) -> Result<Option<PathBuf>> {
+    let response = client.get(url).send().await.map_err(request_failed)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let mut response = response.error_for_status().map_err(request_failed)?;
+    let file_name = response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|file_name| !file_name.is_empty())
+        .ok_or_else(|| BootstrapError::NotFound(format!("a file at {}", response.url())))?
+        .to_string();
+
+    let path = dir.join(file_name);
+    let mut file = File::create(&path).map_err(BootstrapError::from)?;
+    while let Some(chunk) = response.chunk().await.map_err(request_failed)? {
+        file.write_all(&chunk).map_err(BootstrapError::from)?;
+    }
+    debug!("downloaded {url} to {path:?}");
+    Ok(Some(path))
+}
+
+fn request_failed(e: reqwest::Error) -> BootstrapError {
+    BootstrapError::RequestFailed(e.to_string())
+}
@@ -3,6 +3,7 @@ use crate::{
     config::{GlobalConfig, KeypairsConfig, StorageConfig},
     history::StorageHistoryServices,
     sig_hub::SignalHub,
+    state::{StateCommitment, STATE_DIR},
     Error, Result, RollupStorage,
 };
 use default::default_genesis_config;
@@ -24,9 +25,13 @@ use solana_sdk::{signature::Keypair, signer::Signer, timing::timestamp};
 use solana_streamer::socket::SocketAddrSpace;
 use std::{
     path::{Path, PathBuf},
-    sync::{atomic::AtomicBool, Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc, Mutex, RwLock,
+    },
 };
 
+pub mod bootstrap;
 pub mod default;
 
 pub const MAX_REPLAY_WAKE_UP_SIGNALS: usize = 1;
@@ -55,6 +60,7 @@ impl RollupStorage {
 
         let background_service = StorageBackground::new(
             bank_forks.clone(),
+            blockstore.clone(),
             &config.storage,
             &mut hub,
             exit.clone(),
@@ -63,10 +69,19 @@ impl RollupStorage {
         )?;
 
         let bank = bank_forks.read().unwrap().working_bank();
+        let confirmed_slot = Arc::new(AtomicU64::new(blockstore.max_root().min(bank.slot())));
+        let state = StateCommitment::open(
+            &config.ledger_path.join(STATE_DIR),
+            &bank_forks.read().unwrap().root_bank(),
+            config.storage.state_history_slots,
+        )?;
         Ok(Self {
             exit,
             config,
             bank_forks,
+            confirmed_slot,
+            state: Arc::new(Mutex::new(state)),
+            committed_ahead: Default::default(),
             bank,
             blockstore,
             background_service,
@@ -74,6 +89,7 @@ impl RollupStorage {
             cluster_info,
             process_options,
             history_services,
+            events: Default::default(),
         })
     }
 
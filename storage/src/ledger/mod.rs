@@ -1,5 +1,6 @@
 use crate::{
     error::{BankError, StorageError},
+    events::CommitEvent,
     Result, RollupStorage,
 };
 use solana_runtime::{bank_forks::BankForks, installed_scheduler_pool::BankWithScheduler};
@@ -11,7 +12,7 @@ use solana_sdk::{
 };
 use std::{
     collections::HashSet,
-    sync::{Arc, RwLock},
+    sync::{atomic::Ordering, Arc, RwLock},
 };
 
 #[cfg(test)]
@@ -124,15 +125,29 @@ impl RollupStorage {
 
         let bank_forks = self.bank_forks.read().unwrap();
         self.bank = bank_forks.working_bank();
+        self.committed_ahead = Default::default();
+        self.confirmed_slot.fetch_min(slot, Ordering::Relaxed);
 
         Ok(removed)
     }
 
     pub fn confirm(&mut self, slot: u64) -> Result<()> {
         self.bank.freeze();
+        let bank = self.bank_forks.read().unwrap().get(slot);
+        let bank = bank.unwrap_or_else(|| self.bank.clone());
+        // fed to the followers along with the slot, once it is rooted
+        if bank.is_frozen() {
+            self.blockstore.insert_bank_hash(slot, bank.hash(), false);
+        }
         self.blockstore
             .set_roots(std::iter::once(&slot))
             .map_err(|e| StorageError::SetRootFailed(e.to_string()))?;
+        self.confirmed_slot.fetch_max(slot, Ordering::Relaxed);
+        // fees and sysvars are only settled once the bank is frozen
+        self.sync_state(&bank)?;
+        if self.has_commit_subscribers() {
+            self.send_commit_event(CommitEvent::Confirmed(bank));
+        }
         Ok(())
     }
 
@@ -144,6 +159,9 @@ impl RollupStorage {
         self.blockstore
             .set_roots(std::iter::once(&slot))
             .map_err(|e| StorageError::SetRootFailed(e.to_string()))?;
+        // recorded before bank forks squashes the root and drops its parent
+        let root_bank = self.get_bank(slot)?;
+        self.state.lock().unwrap().set_root(&root_bank)?;
         let removed_banks = self
             .bank_forks
             .write()
@@ -154,6 +172,9 @@ impl RollupStorage {
                 finalized,
             )
             .map_err(|e| BankError::SetRootFailed(e.to_string()))?;
+        if self.has_commit_subscribers() {
+            self.send_commit_event(CommitEvent::Rooted(root_bank));
+        }
         Ok(removed_banks)
     }
 }
@@ -1,6 +1,8 @@
 pub mod accounts;
 pub mod background;
 pub mod blockstore;
+pub mod broadcast;
+pub mod cleanup;
 pub mod config;
 pub mod error;
 pub mod events;
@@ -9,7 +11,10 @@ pub mod history;
 pub mod impls;
 pub mod init;
 pub mod ledger;
+pub mod replica;
 pub mod sig_hub;
+pub mod snapshot;
+pub mod state;
 #[cfg(test)]
 mod tests;
 
@@ -0,0 +1,444 @@
+use crate::{error::ReplicaError, Error, Result, RollupStorage};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use solana_entry::entry::{Entry, VerifyRecyclers};
+use solana_ledger::{
+    blockstore_processor::{self, ConfirmationProgress, ConfirmationTiming},
+    shred::{ProcessShredsStats, ReedSolomonCache, Shred, Shredder},
+};
+use solana_runtime::{
+    bank::Bank, installed_scheduler_pool::BankWithScheduler,
+    prioritization_fee_cache::PrioritizationFeeCache,
+};
+use solana_sdk::{clock::Slot, hash::Hash};
+use std::{
+    io::{self, Read, Write},
+    sync::{atomic::Ordering, Arc, OnceLock},
+};
+
+#[cfg(test)]
+mod tests;
+
+const SHREDS_SLOT: u8 = 0;
+const ENTRIES_SLOT: u8 = 1;
+const PURGED_SLOTS: u8 = 2;
+
+/// Well above the data and coding shreds of a full slot.
+pub const MAX_FRAME_LEN: usize = 256 << 20;
+
+/// A slot completed by the leader, as fed to its followers, with the hash of
+/// the bank the leader froze for it and the root of the leader when it was
+/// fed, i.e. the latest slot the leader finalized.
+#[derive(Debug, Clone)]
+pub enum LeaderSlot {
+    /// Data and coding shreds of the slot, as stored by the leader.
+    Shreds {
+        slot: Slot,
+        bank_hash: Hash,
+        root: Slot,
+        shreds: Vec<Shred>,
+    },
+    /// Entries of the slot, shredded again by the follower.
+    Entries {
+        slot: Slot,
+        parent: Slot,
+        bank_hash: Hash,
+        root: Slot,
+        entries: Vec<Entry>,
+    },
+}
+
+impl LeaderSlot {
+    pub fn slot(&self) -> Slot {
+        match self {
+            Self::Shreds { slot, .. } | Self::Entries { slot, .. } => *slot,
+        }
+    }
+
+    pub fn bank_hash(&self) -> Hash {
+        match self {
+            Self::Shreds { bank_hash, .. } | Self::Entries { bank_hash, .. } => *bank_hash,
+        }
+    }
+
+    pub fn root(&self) -> Slot {
+        match self {
+            Self::Shreds { root, .. } | Self::Entries { root, .. } => *root,
+        }
+    }
+
+    /// Writes the slot as a length prefixed frame, e.g. to a file or a stream.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut buf = vec![];
+        match self {
+            Self::Shreds { shreds, .. } => {
+                buf.push(SHREDS_SLOT);
+                self.write_header(&mut buf);
+                buf.extend_from_slice(&(shreds.len() as u64).to_le_bytes());
+                for shred in shreds {
+                    buf.extend_from_slice(&(shred.payload().len() as u64).to_le_bytes());
+                    buf.extend_from_slice(shred.payload());
+                }
+            }
+            Self::Entries {
+                parent, entries, ..
+            } => {
+                buf.push(ENTRIES_SLOT);
+                self.write_header(&mut buf);
+                buf.extend_from_slice(&parent.to_le_bytes());
+                buf.extend(bincode::serialize(entries).map_err(invalid_frame)?);
+            }
+        }
+        writer.write_all(&(buf.len() as u64).to_le_bytes())?;
+        writer.write_all(&buf)
+    }
+
+    /// Reads a frame written by `write_to`, `None` at the end of the feed.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut len = [0; 8];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let len = u64::from_le_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(invalid_frame(format!("frame of {len} bytes")));
+        }
+        let mut buf = vec![0; len];
+        reader.read_exact(&mut buf)?;
+        Self::decode(&buf).map(Some)
+    }
+
+    /// Decodes a frame read without its length prefix.
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        if let Some((&PURGED_SLOTS, first_retained_slot)) = buf.split_first() {
+            let first_retained_slot = <[u8; 8]>::try_from(first_retained_slot)
+                .map_err(|_| invalid_frame("undecodable frame"))?;
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "slots before {} are purged by the leader",
+                    Slot::from_le_bytes(first_retained_slot)
+                ),
+            ));
+        }
+        decode_frame(buf).ok_or_else(|| invalid_frame("undecodable frame"))
+    }
+
+    /// Writes the frame answering a follower that subscribed from a slot
+    /// purged by the leader, read back as a `NotFound` error.
+    pub fn write_purged_to<W: Write>(writer: &mut W, first_retained_slot: Slot) -> io::Result<()> {
+        let mut buf = vec![PURGED_SLOTS];
+        buf.extend_from_slice(&first_retained_slot.to_le_bytes());
+        writer.write_all(&(buf.len() as u64).to_le_bytes())?;
+        writer.write_all(&buf)
+    }
+
+    fn write_header(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.slot().to_le_bytes());
+        buf.extend_from_slice(self.bank_hash().as_ref());
+        buf.extend_from_slice(&self.root().to_le_bytes());
+    }
+}
+
+impl RollupStorage {
+    /// Stores a slot of the leader into the blockstore, returns whether the
+    /// slot is full, i.e. ready to be replayed. The bank hash of the leader is
+    /// stored along, the replayed bank has to match it.
+    ///
+    /// Shreds must be signed by the leader of their slot, entries are
+    /// shredded with the validator keypair of this node.
+    pub fn insert_leader_slot(&self, leader_slot: LeaderSlot) -> Result<bool> {
+        let slot = leader_slot.slot();
+        let bank_hash = leader_slot.bank_hash();
+        match leader_slot {
+            LeaderSlot::Shreds { shreds, .. } => {
+                let leader = self
+                    .leader_schedule_cache
+                    .slot_leader_at(slot, Some(self.bank.as_ref()))
+                    .ok_or(ReplicaError::UnknownLeader(slot))?;
+                if let Some(shred) = shreds
+                    .iter()
+                    .find(|shred| shred.slot() != slot || !shred.verify(&leader))
+                {
+                    return Err(ReplicaError::InvalidShred {
+                        slot,
+                        index: shred.index(),
+                    }
+                    .into());
+                }
+                self.blockstore
+                    .insert_shreds(shreds, Some(&self.leader_schedule_cache), false)?;
+            }
+            LeaderSlot::Entries {
+                parent, entries, ..
+            } => {
+                let (data_shreds, code_shreds) = self.entries_to_shreds(slot, parent, &entries)?;
+                self.blockstore.insert_shreds(
+                    data_shreds,
+                    Some(&self.leader_schedule_cache),
+                    true,
+                )?;
+                self.blockstore.insert_shreds(
+                    code_shreds,
+                    Some(&self.leader_schedule_cache),
+                    true,
+                )?;
+            }
+        }
+        self.blockstore.insert_bank_hash(slot, bank_hash, false);
+        Ok(self.blockstore.is_full(slot))
+    }
+
+    /// Lowest full slot not replayed yet whose parent is replayed, if any.
+    pub fn next_replay_slot(&self) -> Result<Option<Slot>> {
+        let mut replayed = self
+            .bank_forks
+            .read()
+            .unwrap()
+            .frozen_banks()
+            .into_keys()
+            .collect::<Vec<_>>();
+        replayed.sort_unstable();
+        let mut next = None;
+        for parent in replayed.iter() {
+            let Some(meta) = self.blockstore.meta(*parent)? else {
+                continue;
+            };
+            next = meta
+                .next_slots
+                .into_iter()
+                .filter(|slot| {
+                    replayed.binary_search(slot).is_err()
+                        && self.blockstore.is_full(*slot)
+                        && !self.blockstore.is_dead(*slot)
+                })
+                .chain(next)
+                .min();
+        }
+        Ok(next)
+    }
+
+    /// Replays the full slot `slot` on top of its parent with the entries
+    /// verified, then confirms it. The slot is dead if its bank hash does not
+    /// match the one of the leader.
+    ///
+    /// Replayed slots are left unrooted until the leader finalizes them, see
+    /// `set_leader_root`. A slot on top of an earlier one than the current
+    /// bank means the leader reorged: the slots replayed above its parent are
+    /// dropped and dead.
+    pub fn replay_slot(&mut self, slot: Slot) -> Result<Arc<Bank>> {
+        let meta = self
+            .blockstore
+            .meta(slot)?
+            .ok_or(ReplicaError::SlotNotFull(slot))?;
+        if !meta.is_full() {
+            return Err(ReplicaError::SlotNotFull(slot).into());
+        }
+        if self.bank_forks.read().unwrap().get(slot).is_some() {
+            return Err(ReplicaError::AlreadyReplayed(slot).into());
+        }
+        let parent = self.replay_parent(slot)?;
+        if parent.slot() != self.bank.slot() {
+            self.drop_slots_above(parent.slot())?;
+        }
+
+        let leader = self
+            .leader_schedule_cache
+            .slot_leader_at(slot, Some(&parent))
+            .ok_or(ReplicaError::UnknownLeader(slot))?;
+        let last_entry = parent.last_blockhash();
+        let bank = self
+            .bank_forks
+            .write()
+            .unwrap()
+            .insert(Bank::new_from_parent(parent, &leader, slot));
+        if let Err(e) = self.replay_entries(&bank, last_entry) {
+            self.drop_slot(slot)?;
+            return Err(e);
+        }
+        bank.freeze();
+        let expected = self.blockstore.get_bank_hash(slot).unwrap_or_default();
+        if bank.hash() != expected {
+            self.drop_slot(slot)?;
+            return Err(ReplicaError::BankHashMismatch {
+                slot,
+                expected,
+                actual: bank.hash(),
+            }
+            .into());
+        }
+
+        self.bank = bank.clone_without_scheduler();
+        self.committed_ahead = Default::default();
+        self.notify_block_complete();
+        self.confirm(slot)?;
+        debug!("replayed slot {slot} with bank hash {}", bank.hash());
+        Ok(self.bank.clone())
+    }
+
+    /// Roots the replayed slot `root` finalized by the leader, returns whether
+    /// the root moved. Roots the leader finalized before the follower replayed
+    /// them are set by a later call.
+    pub fn set_leader_root(&mut self, root: Slot) -> Result<bool> {
+        if root <= self.get_root() || self.bank_forks.read().unwrap().get(root).is_none() {
+            return Ok(false);
+        }
+        self.set_root(root, None)?;
+        Ok(true)
+    }
+
+    /// Replayed bank the slot `slot` is built on.
+    pub fn replay_parent(&self, slot: Slot) -> Result<Arc<Bank>> {
+        let parent = self
+            .blockstore
+            .meta(slot)?
+            .and_then(|meta| meta.parent_slot);
+        parent
+            .and_then(|parent| self.bank_forks.read().unwrap().get(parent))
+            .ok_or_else(|| ReplicaError::UnknownParent { slot, parent }.into())
+    }
+
+    fn replay_entries(&self, bank: &BankWithScheduler, last_entry: Hash) -> Result<()> {
+        static REPLAY_THREAD_POOL: OnceLock<ThreadPool> = OnceLock::new();
+        let thread_pool = REPLAY_THREAD_POOL.get_or_init(|| {
+            ThreadPoolBuilder::new()
+                .thread_name(|i| format!("iglooReplay{i:02}"))
+                .build()
+                .expect("new rayon thread pool")
+        });
+        blockstore_processor::confirm_slot(
+            &self.blockstore,
+            bank,
+            thread_pool,
+            &mut ConfirmationTiming::default(),
+            &mut ConfirmationProgress::new(last_entry),
+            false, // skip_verification
+            self.history_services.transaction_status_sender.as_ref(),
+            None,
+            None,
+            &VerifyRecyclers::default(),
+            false, // allow_dead_slots
+            None,
+            &PrioritizationFeeCache::default(),
+        )
+        .map_err(|e| ReplicaError::ReplayFailed(bank.slot(), e.to_string()))?;
+        Ok(())
+    }
+
+    /// Drops the replayed slots above `slot`, the leader abandoned them.
+    fn drop_slots_above(&mut self, slot: Slot) -> Result<()> {
+        let abandoned = self
+            .bank_forks
+            .read()
+            .unwrap()
+            .frozen_banks()
+            .into_keys()
+            .filter(|abandoned| *abandoned > slot)
+            .collect::<Vec<_>>();
+        for abandoned in abandoned {
+            warn!("leader abandoned replayed slot {abandoned}");
+            self.drop_slot(abandoned)?;
+        }
+        self.bank = self
+            .bank_forks
+            .read()
+            .unwrap()
+            .get(slot)
+            .ok_or(ReplicaError::DeadSlot(slot))?;
+        self.confirmed_slot.fetch_min(slot, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn drop_slot(&mut self, slot: Slot) -> Result<()> {
+        self.bank_forks.write().unwrap().remove(slot);
+        self.blockstore.set_dead_slot(slot)?;
+        Ok(())
+    }
+
+    fn entries_to_shreds(
+        &self,
+        slot: Slot,
+        parent: Slot,
+        entries: &[Entry],
+    ) -> Result<(Vec<Shred>, Vec<Shred>)> {
+        let keypair = self
+            .config
+            .keypairs
+            .validator_keypair
+            .as_ref()
+            .ok_or(Error::KeypairsConfigMissingValidatorKeypair)?
+            .as_ref();
+        let shredder = Shredder::new(slot, parent, 0, 0)
+            .map_err(|_| ReplicaError::InvalidParent { slot, parent })?;
+        Ok(shredder.entries_to_shreds(
+            keypair,
+            entries,
+            true, // is_last_in_slot
+            None,
+            0,
+            0,
+            true, // merkle_variant
+            &ReedSolomonCache::default(),
+            &mut ProcessShredsStats::default(),
+        ))
+    }
+}
+
+fn invalid_frame(e: impl ToString) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid leader slot: {}", e.to_string()),
+    )
+}
+
+fn take<const N: usize>(cursor: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, tail) = cursor.split_first_chunk::<N>()?;
+    *cursor = tail;
+    Some(*head)
+}
+
+fn take_u64(cursor: &mut &[u8]) -> Option<u64> {
+    take(cursor).map(u64::from_le_bytes)
+}
+
+fn decode_frame(mut cursor: &[u8]) -> Option<LeaderSlot> {
+    let cursor = &mut cursor;
+    let [tag] = take(cursor)?;
+    let slot = take_u64(cursor)?;
+    let bank_hash = Hash::new_from_array(take(cursor)?);
+    let root = take_u64(cursor)?;
+    match tag {
+        SHREDS_SLOT => {
+            let len = take_u64(cursor)?;
+            let mut shreds = vec![];
+            for _ in 0..len {
+                let payload_len = take_u64(cursor)? as usize;
+                if payload_len > cursor.len() {
+                    return None;
+                }
+                let (payload, tail) = cursor.split_at(payload_len);
+                *cursor = tail;
+                shreds.push(Shred::new_from_serialized_shred(payload.to_vec()).ok()?);
+            }
+            cursor.is_empty().then_some(LeaderSlot::Shreds {
+                slot,
+                bank_hash,
+                root,
+                shreds,
+            })
+        }
+        ENTRIES_SLOT => {
+            let parent = take_u64(cursor)?;
+            let entries = bincode::deserialize(cursor).ok()?;
+            Some(LeaderSlot::Entries {
+                slot,
+                parent,
+                bank_hash,
+                root,
+                entries,
+            })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,239 @@
+use super::LeaderSlot;
+use crate::{
+    blockstore::txs::CommitBatch,
+    broadcast::{read_leader_slot, FeedFormat},
+    config::GlobalConfig,
+    error::ReplicaError,
+    execution::TransactionsResultWrapper,
+    tests::mock::{commit_empty_block, processor::process_transfers_ex},
+    Error, RollupStorage,
+};
+use anyhow::Result;
+use solana_sdk::{
+    clock::Slot, hash::Hash, signature::Keypair, signer::Signer, system_transaction,
+    transaction::SanitizedTransaction,
+};
+use std::{fs, io::Cursor, path::Path};
+
+fn new_follower(leader: &RollupStorage, ledger_path: &Path) -> Result<RollupStorage> {
+    fs::copy(
+        leader.config.ledger_path.join("genesis.bin"),
+        ledger_path.join("genesis.bin"),
+    )?;
+    let mut config = GlobalConfig::new(ledger_path)?;
+    config.keypairs = leader.config.keypairs.clone();
+    let mut store = RollupStorage::new(config)?;
+    store.init()?;
+    Ok(store)
+}
+
+fn shreds_of(store: &RollupStorage, slot: Slot) -> Result<LeaderSlot> {
+    Ok(read_leader_slot(
+        &store.blockstore,
+        slot,
+        store.get_root(),
+        FeedFormat::Shreds,
+    )?)
+}
+
+#[tokio::test]
+async fn leader_slot_frames_work() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    store.init()?;
+    store.bump()?;
+    commit_empty_block(&mut store).await?;
+    store.confirm(1)?;
+
+    let bank_hash = store.get_bank(1)?.hash();
+    let LeaderSlot::Shreds { shreds, .. } = shreds_of(&store, 1)? else {
+        unreachable!()
+    };
+    let entries = store.blockstore.get_slot_entries(1, 0)?;
+    let mut feed = vec![];
+    LeaderSlot::Shreds {
+        slot: 1,
+        bank_hash,
+        root: 0,
+        shreds: shreds.clone(),
+    }
+    .write_to(&mut feed)?;
+    LeaderSlot::Entries {
+        slot: 1,
+        parent: 0,
+        bank_hash,
+        root: 1,
+        entries: entries.clone(),
+    }
+    .write_to(&mut feed)?;
+
+    let mut reader = Cursor::new(feed);
+    match LeaderSlot::read_from(&mut reader)? {
+        Some(LeaderSlot::Shreds {
+            slot,
+            bank_hash: read_hash,
+            root,
+            shreds: read,
+        }) => {
+            assert_eq!((slot, read_hash, root), (1, bank_hash, 0));
+            assert_eq!(read, shreds);
+        }
+        other => panic!("unexpected frame {other:?}"),
+    }
+    match LeaderSlot::read_from(&mut reader)? {
+        Some(LeaderSlot::Entries {
+            slot,
+            parent,
+            bank_hash: read_hash,
+            root,
+            entries: read,
+        }) => {
+            assert_eq!((slot, parent, read_hash, root), (1, 0, bank_hash, 1));
+            assert_eq!(read, entries);
+        }
+        other => panic!("unexpected frame {other:?}"),
+    }
+    assert!(LeaderSlot::read_from(&mut reader)?.is_none());
+
+    // a frame cut short is an error, not the end of the feed
+    let mut feed = vec![];
+    LeaderSlot::Shreds {
+        slot: 1,
+        bank_hash,
+        root: 0,
+        shreds,
+    }
+    .write_to(&mut feed)?;
+    feed.truncate(feed.len() - 1);
+    assert!(LeaderSlot::read_from(&mut Cursor::new(feed)).is_err());
+
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn follower_replays_leader_slots() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut leader = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    leader.init()?;
+    let alice = leader.config.keypairs.mint_keypair.clone().unwrap();
+    let bob = Keypair::new().pubkey();
+
+    leader.bump()?;
+    let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+        &alice,
+        &bob,
+        1000000,
+        leader.bank.last_blockhash(),
+    ));
+    let results = process_transfers_ex(&leader, vec![tx.clone()]);
+    leader
+        .commit(
+            vec![TransactionsResultWrapper { output: results }],
+            vec![CommitBatch::new(vec![tx].into())],
+        )
+        .await?;
+    leader.confirm(1)?;
+    leader.bump()?;
+    commit_empty_block(&mut leader).await?;
+    leader.confirm(2)?;
+
+    let follower_path = tempfile::tempdir()?.into_path();
+    let mut follower = new_follower(&leader, &follower_path)?;
+    assert_eq!(follower.next_replay_slot()?, None);
+    assert!(matches!(
+        follower.replay_slot(1),
+        Err(Error::ReplicaError(ReplicaError::SlotNotFull(1)))
+    ));
+
+    // shreds of another slot are rejected
+    let LeaderSlot::Shreds { shreds, .. } = shreds_of(&leader, 2)? else {
+        unreachable!()
+    };
+    assert!(matches!(
+        follower.insert_leader_slot(LeaderSlot::Shreds {
+            slot: 1,
+            bank_hash: leader.get_bank(1)?.hash(),
+            root: 0,
+            shreds
+        }),
+        Err(Error::ReplicaError(ReplicaError::InvalidShred {
+            slot: 1,
+            ..
+        }))
+    ));
+
+    assert!(follower.insert_leader_slot(shreds_of(&leader, 1)?)?);
+    assert_eq!(follower.next_replay_slot()?, Some(1));
+    let bank = follower.replay_slot(1)?;
+    assert_eq!(bank.slot(), 1);
+    assert_eq!(bank.hash(), leader.get_bank(1)?.hash());
+    assert!(matches!(
+        follower.replay_slot(1),
+        Err(Error::ReplicaError(ReplicaError::AlreadyReplayed(1)))
+    ));
+    // not finalized by the leader yet
+    assert_eq!(follower.get_root(), 0);
+    assert!(!follower.set_leader_root(0)?);
+    assert_eq!(follower.confirmed_slot(), 1);
+    assert_eq!(follower.current_height(), 1);
+    assert_eq!(follower.balance(&bob), 1000000);
+    assert_eq!(follower.next_replay_slot()?, None);
+
+    // entries are shredded again by the follower
+    let entries = LeaderSlot::Entries {
+        slot: 2,
+        parent: 1,
+        bank_hash: leader.get_bank(2)?.hash(),
+        root: 0,
+        entries: leader.blockstore.get_slot_entries(2, 0)?,
+    };
+    assert!(follower.insert_leader_slot(entries.clone())?);
+    assert_eq!(follower.next_replay_slot()?, Some(2));
+    follower.replay_slot(2)?;
+    assert_eq!(follower.current_bank().hash(), leader.get_bank(2)?.hash());
+
+    // rooted once the leader finalizes it
+    leader.set_root(1, None)?;
+    assert!(follower.set_leader_root(leader.get_root())?);
+    assert_eq!(follower.get_root(), 1);
+    assert_eq!(follower.current_height(), 2);
+
+    // a slot whose bank hash differs from the leader's is dead
+    let other_path = tempfile::tempdir()?.into_path();
+    let mut other = new_follower(&leader, &other_path)?;
+    other.insert_leader_slot(shreds_of(&leader, 1)?)?;
+    other.replay_slot(1)?;
+    let LeaderSlot::Entries {
+        slot,
+        parent,
+        root,
+        entries,
+        ..
+    } = entries
+    else {
+        unreachable!()
+    };
+    other.insert_leader_slot(LeaderSlot::Entries {
+        slot,
+        parent,
+        bank_hash: Hash::new_unique(),
+        root,
+        entries,
+    })?;
+    assert!(matches!(
+        other.replay_slot(2),
+        Err(Error::ReplicaError(ReplicaError::BankHashMismatch {
+            slot: 2,
+            ..
+        }))
+    ));
+    assert!(other.blockstore.is_dead(2));
+    assert_eq!(other.current_height(), 1);
+    assert_eq!(other.next_replay_slot()?, None);
+
+    other.close().await?;
+    follower.close().await?;
+    leader.close().await?;
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use crate::{config::GlobalConfig, error::SnapshotError, Result, RollupStorage};
+use solana_ledger::use_snapshot_archives_at_startup::UseSnapshotArchivesAtStartup;
+use solana_runtime::{
+    snapshot_archive_info::{
+        FullSnapshotArchiveInfo, IncrementalSnapshotArchiveInfo, SnapshotArchiveInfoGetter,
+    },
+    snapshot_bank_utils,
+    snapshot_utils::{self, ArchiveFormat},
+};
+use solana_sdk::{clock::Slot, hash::Hash};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// A snapshot archive found in the archive dirs of `SnapshotConfig`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotArchive {
+    pub path: PathBuf,
+    pub slot: Slot,
+    pub hash: Hash,
+    /// Slot of the full snapshot an incremental snapshot builds on, `None`
+    /// for a full snapshot.
+    pub base_slot: Option<Slot>,
+    pub archive_format: ArchiveFormat,
+}
+
+impl From<FullSnapshotArchiveInfo> for SnapshotArchive {
+    fn from(info: FullSnapshotArchiveInfo) -> Self {
+        Self {
+            path: info.path().clone(),
+            slot: info.slot(),
+            hash: info.hash().0,
+            base_slot: None,
+            archive_format: info.archive_format(),
+        }
+    }
+}
+
+impl From<IncrementalSnapshotArchiveInfo> for SnapshotArchive {
+    fn from(info: IncrementalSnapshotArchiveInfo) -> Self {
+        Self {
+            path: info.path().clone(),
+            slot: info.slot(),
+            hash: info.hash().0,
+            base_slot: Some(info.base_slot()),
+            archive_format: info.archive_format(),
+        }
+    }
+}
+
+impl RollupStorage {
+    /// Bootstraps a node from a full snapshot archive, and optionally an
+    /// incremental one on top, without replaying the ledger.
+    ///
+    /// The archives are copied into the archive dirs of the snapshot config,
+    /// which must not hold newer ones. The ledger path must hold the genesis
+    /// the snapshot was taken from, `init` is still to be called.
+    pub fn new_from_snapshot(
+        mut config: GlobalConfig,
+        full_archive: &Path,
+        incremental_archive: Option<&Path>,
+    ) -> Result<Self> {
+        let full = FullSnapshotArchiveInfo::new_from_path(full_archive.to_path_buf())
+            .map_err(|e| SnapshotError::InvalidArchive(e.to_string()))?;
+        let incremental = incremental_archive
+            .map(|path| IncrementalSnapshotArchiveInfo::new_from_path(path.to_path_buf()))
+            .transpose()
+            .map_err(|e| SnapshotError::InvalidArchive(e.to_string()))?;
+        if let Some(incremental) = &incremental {
+            if incremental.base_slot() != full.slot() {
+                return Err(SnapshotError::BaseSlotMismatch {
+                    base_slot: incremental.base_slot(),
+                    full_slot: full.slot(),
+                }
+                .into());
+            }
+        }
+
+        let snapshot_config = &config.storage.snapshot_config;
+        copy_archive(full.path(), &snapshot_config.full_snapshot_archives_dir)?;
+        if let Some(incremental) = &incremental {
+            copy_archive(
+                incremental.path(),
+                &snapshot_config.incremental_snapshot_archives_dir,
+            )?;
+        }
+        let slot = incremental
+            .as_ref()
+            .map_or(full.slot(), |incremental| incremental.slot());
+        let latest = snapshot_utils::get_highest_full_snapshot_archive_slot(
+            &snapshot_config.full_snapshot_archives_dir,
+        )
+        .map(|full_slot| {
+            snapshot_utils::get_highest_incremental_snapshot_archive_slot(
+                &snapshot_config.incremental_snapshot_archives_dir,
+                full_slot,
+            )
+            .unwrap_or(full_slot)
+        });
+        if latest != Some(slot) {
+            return Err(SnapshotError::InvalidArchive(format!(
+                "snapshot at slot {slot} is not the latest archived one"
+            ))
+            .into());
+        }
+
+        // load the bank from the archives, and stop there
+        let halt_at_slot = config.storage.halt_at_slot.replace(slot);
+        let use_snapshot_archives_at_startup = std::mem::replace(
+            &mut config.storage.use_snapshot_archives_at_startup,
+            UseSnapshotArchivesAtStartup::Always,
+        );
+        let mut storage = Self::new(config)?;
+        storage.config.storage.halt_at_slot = halt_at_slot;
+        storage.config.storage.use_snapshot_archives_at_startup = use_snapshot_archives_at_startup;
+        Ok(storage)
+    }
+
+    /// Archives a full snapshot of the bank at `slot`, or of the current bank.
+    pub fn snapshot(&self, slot: Option<Slot>) -> Result<SnapshotArchive> {
+        let bank = self.get_bank(slot.unwrap_or_else(|| self.current_height()))?;
+        let snapshot_config = &self.config.storage.snapshot_config;
+        let info = snapshot_bank_utils::bank_to_full_snapshot_archive(
+            &snapshot_config.bank_snapshots_dir,
+            &bank,
+            Some(snapshot_config.snapshot_version),
+            &snapshot_config.full_snapshot_archives_dir,
+            &snapshot_config.incremental_snapshot_archives_dir,
+            snapshot_config.archive_format,
+        )
+        .map_err(|e| SnapshotError::ArchiveFailed(e.to_string()))?;
+        self.purge_old_snapshot_archives();
+        Ok(info.into())
+    }
+
+    /// Archives an incremental snapshot of the bank at `slot`, or of the
+    /// current bank, on top of the latest full snapshot archive.
+    pub fn incremental_snapshot(&self, slot: Option<Slot>) -> Result<SnapshotArchive> {
+        let slot = slot.unwrap_or_else(|| self.current_height());
+        let snapshot_config = &self.config.storage.snapshot_config;
+        let full_slot = snapshot_utils::get_highest_full_snapshot_archive_slot(
+            &snapshot_config.full_snapshot_archives_dir,
+        )
+        .ok_or(SnapshotError::NoFullSnapshot)?;
+        if slot <= full_slot {
+            return Err(SnapshotError::NotAfterFullSnapshot { slot, full_slot }.into());
+        }
+
+        let bank = self.get_bank(slot)?;
+        let info = snapshot_bank_utils::bank_to_incremental_snapshot_archive(
+            &snapshot_config.bank_snapshots_dir,
+            &bank,
+            full_slot,
+            Some(snapshot_config.snapshot_version),
+            &snapshot_config.full_snapshot_archives_dir,
+            &snapshot_config.incremental_snapshot_archives_dir,
+            snapshot_config.archive_format,
+        )
+        .map_err(|e| SnapshotError::ArchiveFailed(e.to_string()))?;
+        self.purge_old_snapshot_archives();
+        Ok(info.into())
+    }
+
+    /// Full snapshot archives, by ascending slot.
+    pub fn full_snapshot_archives(&self) -> Vec<SnapshotArchive> {
+        let mut archives: Vec<SnapshotArchive> = snapshot_utils::get_full_snapshot_archives(
+            &self
+                .config
+                .storage
+                .snapshot_config
+                .full_snapshot_archives_dir,
+        )
+        .into_iter()
+        .map(Into::into)
+        .collect();
+        archives.sort_by_key(|archive| archive.slot);
+        archives
+    }
+
+    /// Incremental snapshot archives, by ascending base slot then slot.
+    pub fn incremental_snapshot_archives(&self) -> Vec<SnapshotArchive> {
+        let mut archives: Vec<SnapshotArchive> = snapshot_utils::get_incremental_snapshot_archives(
+            &self
+                .config
+                .storage
+                .snapshot_config
+                .incremental_snapshot_archives_dir,
+        )
+        .into_iter()
+        .map(Into::into)
+        .collect();
+        archives.sort_by_key(|archive| (archive.base_slot, archive.slot));
+        archives
+    }
+
+    /// Removes the archives beyond the retention limits of the snapshot
+    /// config, oldest first.
+    pub fn purge_old_snapshot_archives(&self) {
+        let snapshot_config = &self.config.storage.snapshot_config;
+        snapshot_utils::purge_old_snapshot_archives(
+            &snapshot_config.full_snapshot_archives_dir,
+            &snapshot_config.incremental_snapshot_archives_dir,
+            snapshot_config.maximum_full_snapshot_archives_to_retain,
+            snapshot_config.maximum_incremental_snapshot_archives_to_retain,
+        );
+    }
+}
+
+fn copy_archive(archive: &Path, dir: &Path) -> Result<()> {
+    let file_name = archive.file_name().ok_or_else(|| {
+        SnapshotError::InvalidArchive(format!("{} is not a file", archive.display()))
+    })?;
+    let target = dir.join(file_name);
+    if target != archive {
+        fs::create_dir_all(dir).map_err(SnapshotError::from)?;
+        fs::copy(archive, target).map_err(SnapshotError::from)?;
+    }
+    Ok(())
+}
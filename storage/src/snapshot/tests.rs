@@ -0,0 +1,124 @@
+use crate::{
+    config::GlobalConfig, error::SnapshotError, tests::mock::commit_empty_block, Error,
+    RollupStorage,
+};
+use anyhow::Result;
+use solana_runtime::snapshot_utils::ArchiveFormat;
+use std::{fs, num::NonZeroUsize, path::Path};
+
+async fn new_rooted_slot(store: &mut RollupStorage) -> Result<()> {
+    store.bump()?;
+    commit_empty_block(store).await?;
+    store.force_save().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshots_on_demand_work() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut config = GlobalConfig::new_temp(&ledger_path)?;
+    let snapshot_config = &mut config.storage.snapshot_config;
+    snapshot_config.archive_format = ArchiveFormat::Tar;
+    snapshot_config.maximum_full_snapshot_archives_to_retain = NonZeroUsize::new(1).unwrap();
+    let full_dir = snapshot_config.full_snapshot_archives_dir.clone();
+    let mut store = RollupStorage::new(config)?;
+    store.init()?;
+
+    assert!(matches!(
+        store.incremental_snapshot(None),
+        Err(Error::SnapshotError(SnapshotError::NoFullSnapshot))
+    ));
+    new_rooted_slot(&mut store).await?;
+    let full = store.snapshot(None)?;
+    assert_eq!(full.slot, 1);
+    assert_eq!(full.base_slot, None);
+    assert_eq!(full.archive_format, ArchiveFormat::Tar);
+    assert!(full.path.starts_with(&full_dir));
+    assert_eq!(store.full_snapshot_archives(), vec![full.clone()]);
+    assert!(matches!(
+        store.incremental_snapshot(Some(1)),
+        Err(Error::SnapshotError(SnapshotError::NotAfterFullSnapshot {
+            slot: 1,
+            full_slot: 1
+        }))
+    ));
+
+    new_rooted_slot(&mut store).await?;
+    let incremental = store.incremental_snapshot(None)?;
+    assert_eq!(incremental.slot, 2);
+    assert_eq!(incremental.base_slot, Some(1));
+    assert_eq!(store.incremental_snapshot_archives(), vec![incremental]);
+
+    // only the latest full snapshot is retained, with its incremental ones
+    new_rooted_slot(&mut store).await?;
+    let full = store.snapshot(None)?;
+    assert_eq!(full.slot, 3);
+    assert_eq!(store.full_snapshot_archives(), vec![full]);
+    assert!(store.incremental_snapshot_archives().is_empty());
+
+    store.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_from_snapshot_works() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    store.init()?;
+    let keypairs = store.config.keypairs.clone();
+    new_rooted_slot(&mut store).await?;
+    let full = store.snapshot(None)?;
+    new_rooted_slot(&mut store).await?;
+    let incremental = store.incremental_snapshot(None)?;
+    let hash = store.current_bank().hash();
+    store.close().await?;
+
+    let bootstrap = |new_ledger_path: &Path| -> Result<GlobalConfig> {
+        fs::copy(
+            ledger_path.join("genesis.bin"),
+            new_ledger_path.join("genesis.bin"),
+        )?;
+        let mut config = GlobalConfig::new(new_ledger_path)?;
+        config.keypairs = keypairs.clone();
+        Ok(config)
+    };
+
+    // the incremental snapshot must build on the full one
+    let other_ledger_path = tempfile::tempdir()?.into_path();
+    let other_full = other_ledger_path.join(
+        full.path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .replace("snapshot-1-", "snapshot-0-"),
+    );
+    fs::copy(&full.path, &other_full)?;
+    assert!(matches!(
+        RollupStorage::new_from_snapshot(
+            bootstrap(&other_ledger_path)?,
+            &other_full,
+            Some(&incremental.path),
+        ),
+        Err(Error::SnapshotError(SnapshotError::BaseSlotMismatch { .. }))
+    ));
+
+    let new_ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new_from_snapshot(
+        bootstrap(&new_ledger_path)?,
+        &full.path,
+        Some(&incremental.path),
+    )?;
+    store.init()?;
+    assert_eq!(store.get_root(), 2);
+    assert_eq!(store.current_bank().hash(), hash);
+    assert_eq!(store.incremental_snapshot_archives().len(), 1);
+    assert_eq!(store.config.storage.halt_at_slot, None);
+
+    // the node goes on from the snapshot slot
+    store.bump()?;
+    commit_empty_block(&mut store).await?;
+    store.confirm(3)?;
+    assert_eq!(store.confirmed_slot(), 3);
+    store.close().await?;
+    Ok(())
+}
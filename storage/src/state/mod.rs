@@ -0,0 +1,384 @@
+//! Sparse Merkle commitment of the account state, kept per slot and across forks.
+//!
+//! The tree holds the state of a single slot at a time. Every slot keeps the account hashes it
+//! changed, before and after, so the tree moves between slots by undoing and replaying those
+//! changes. Changes are logged under the ledger and folded into a checkpoint as slots age out.
+
+use crate::{
+    error::{AccountDbError, StateError, StorageError},
+    Result, RollupStorage,
+};
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    clock::Slot,
+    keccak::Hash,
+    pubkey::Pubkey,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+use store::{Checkpoint, StateLog, StateRecord};
+
+pub mod smt;
+mod store;
+#[cfg(test)]
+mod tests;
+
+pub use smt::{hash_account, AccountProof, SparseMerkleTree};
+
+pub const STATE_DIR: &str = "state";
+pub const DEFAULT_STATE_HISTORY_SLOTS: u64 = 4096;
+/// Log records after which the log is folded into a new checkpoint.
+const COMPACT_LOG_RECORDS: usize = 8192;
+
+struct SlotState {
+    parent: Slot,
+    /// Account hashes before and after the slot.
+    changes: HashMap<Pubkey, (Option<Hash>, Option<Hash>)>,
+    root: Hash,
+}
+
+pub struct StateCommitment {
+    tree: SparseMerkleTree,
+    /// Slot whose state the tree holds.
+    tip: Slot,
+    /// Oldest retained slot, the others lead back to it through their parents.
+    base: Slot,
+    base_root: Hash,
+    slots: BTreeMap<Slot, SlotState>,
+    history_slots: u64,
+    log: StateLog,
+}
+
+impl StateCommitment {
+    /// Restores the commitment persisted in `dir`, or rebuilds it from `root_bank` if nothing
+    /// usable was persisted.
+    pub fn open(dir: &Path, root_bank: &Bank, history_slots: u64) -> Result<Self> {
+        match Self::load(dir, history_slots) {
+            Ok(Some(state)) if state.has_slot(root_bank.slot()) => return Ok(state),
+            Ok(Some(_)) => warn!(
+                "persisted state tree does not reach root slot {}, rebuilding",
+                root_bank.slot()
+            ),
+            Ok(None) => {}
+            Err(e) => warn!("failed to load persisted state tree, rebuilding: {}", e),
+        }
+        Self::rebuild(dir, root_bank, history_slots)
+    }
+
+    fn load(dir: &Path, history_slots: u64) -> Result<Option<Self>> {
+        let Some((checkpoint, records, log)) = StateLog::load(dir).map_err(persist_error)? else {
+            return Ok(None);
+        };
+        let mut state = Self::from_checkpoint(checkpoint, history_slots, log);
+        for record in records {
+            match record {
+                StateRecord::Slot {
+                    slot,
+                    parent,
+                    updates,
+                    root,
+                } => {
+                    state.record(slot, parent, updates, false)?;
+                    if state.slots[&slot].root != root {
+                        return Err(
+                            StateError::Corrupted(format!("root mismatch at slot {slot}")).into(),
+                        );
+                    }
+                }
+                StateRecord::Root(root) => state.prune(root, false)?,
+            }
+        }
+        Ok(Some(state))
+    }
+
+    fn rebuild(dir: &Path, root_bank: &Bank, history_slots: u64) -> Result<Self> {
+        let mut leaves = vec![];
+        root_bank
+            .scan_all_accounts(
+                |account: Option<(&Pubkey, AccountSharedData, Slot)>| {
+                    if let Some((pubkey, account, _)) =
+                        account.filter(|(_, account, _)| account.lamports() > 0)
+                    {
+                        leaves.push((*pubkey, hash_account(&account)));
+                    }
+                },
+                true,
+            )
+            .map_err(|e| AccountDbError::FailedToScanAccounts(e.to_string()))?;
+        let checkpoint = Checkpoint {
+            slot: root_bank.slot(),
+            leaves,
+        };
+        let log = StateLog::create(dir, &checkpoint, &[]).map_err(persist_error)?;
+        Ok(Self::from_checkpoint(checkpoint, history_slots, log))
+    }
+
+    fn from_checkpoint(checkpoint: Checkpoint, history_slots: u64, log: StateLog) -> Self {
+        let mut tree = SparseMerkleTree::default();
+        for (pubkey, hash) in checkpoint.leaves {
+            tree.update(pubkey, Some(hash));
+        }
+        Self {
+            base_root: tree.root(),
+            tree,
+            tip: checkpoint.slot,
+            base: checkpoint.slot,
+            slots: BTreeMap::new(),
+            history_slots,
+            log,
+        }
+    }
+
+    pub fn has_slot(&self, slot: Slot) -> bool {
+        slot == self.base || self.slots.contains_key(&slot)
+    }
+
+    pub fn state_root(&self, slot: Slot) -> Option<Hash> {
+        if slot == self.base {
+            Some(self.base_root)
+        } else {
+            self.slots.get(&slot).map(|state| state.root)
+        }
+    }
+
+    /// Proves the account against [`Self::state_root`] of `slot`, `None` if the account does not
+    /// exist at that slot.
+    pub fn prove(&mut self, pubkey: &Pubkey, slot: Slot) -> Result<Option<AccountProof>> {
+        let tip = self.tip;
+        self.checkout(slot)?;
+        let proof = self.tree.proof(pubkey);
+        self.checkout(tip)?;
+        Ok(proof)
+    }
+
+    /// Records the accounts written by `bank` since its parent, along with any ancestor that
+    /// has not been recorded yet. Recording a slot again replaces its previous changes.
+    pub fn sync(&mut self, bank: &Bank) -> Result<()> {
+        if bank.slot() <= self.base {
+            return Ok(());
+        }
+        let mut missing = vec![];
+        let mut parent = bank.parent();
+        while let Some(ancestor) = parent {
+            if self.has_slot(ancestor.slot()) {
+                break;
+            }
+            if ancestor.slot() < self.base {
+                return Err(StateError::SlotNotAvailable(ancestor.slot()).into());
+            }
+            parent = ancestor.parent();
+            missing.push(ancestor);
+        }
+        for ancestor in missing.iter().rev() {
+            self.record_bank(ancestor)?;
+        }
+        self.record_bank(bank)
+    }
+
+    fn record_bank(&mut self, bank: &Bank) -> Result<()> {
+        let updates = bank
+            .get_all_accounts_modified_since_parent()
+            .into_iter()
+            .map(|(pubkey, account)| {
+                let hash = (account.lamports() > 0).then(|| hash_account(&account));
+                (pubkey, hash)
+            })
+            .collect();
+        self.record(bank.slot(), bank.parent_slot(), updates, true)
+    }
+
+    fn record(
+        &mut self,
+        slot: Slot,
+        parent: Slot,
+        updates: Vec<(Pubkey, Option<Hash>)>,
+        persist: bool,
+    ) -> Result<()> {
+        self.checkout(parent)?;
+        // descendants were built on the changes being replaced
+        let stale = self.descendants(slot);
+        self.slots.retain(|s, _| !stale.contains(s));
+
+        let mut changes = HashMap::new();
+        for (pubkey, hash) in &updates {
+            let previous = self.tree.get(pubkey).copied();
+            if previous != *hash {
+                changes.insert(*pubkey, (previous, *hash));
+                self.tree.update(*pubkey, *hash);
+            }
+        }
+        let root = self.tree.root();
+        self.slots.insert(
+            slot,
+            SlotState {
+                parent,
+                changes,
+                root,
+            },
+        );
+        self.tip = slot;
+
+        if persist {
+            self.append(&StateRecord::Slot {
+                slot,
+                parent,
+                updates,
+                root,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Drops the slots off the fork of `root` and the ancestors older than the retained history.
+    pub(crate) fn set_root(&mut self, root_bank: &Bank) -> Result<()> {
+        self.sync_missing(root_bank)?;
+        self.prune(root_bank.slot(), true)?;
+        if self.log.len() >= COMPACT_LOG_RECORDS {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn sync_missing(&mut self, bank: &Bank) -> Result<()> {
+        if self.has_slot(bank.slot()) || bank.slot() < self.base {
+            Ok(())
+        } else {
+            self.sync(bank)
+        }
+    }
+
+    fn prune(&mut self, root: Slot, persist: bool) -> Result<()> {
+        let Some(ancestors) = self.ancestors(root) else {
+            return Ok(());
+        };
+        self.checkout(root)?;
+
+        let ancestors = ancestors.into_iter().collect::<HashSet<_>>();
+        let mut descendants = self.descendants(root);
+        descendants.insert(root);
+        self.slots
+            .retain(|slot, _| ancestors.contains(slot) || descendants.contains(slot));
+
+        let oldest = root.saturating_sub(self.history_slots);
+        while let Some((&slot, _)) = self.slots.first_key_value() {
+            if slot > oldest || slot >= root {
+                break;
+            }
+            let state = self.slots.remove(&slot).unwrap();
+            self.base = slot;
+            self.base_root = state.root;
+        }
+
+        if persist {
+            self.append(&StateRecord::Root(root))?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the log as a checkpoint of the base slot followed by the retained slots.
+    fn compact(&mut self) -> Result<()> {
+        let tip = self.tip;
+        self.checkout(self.base)?;
+        let checkpoint = Checkpoint {
+            slot: self.base,
+            leaves: self.tree.iter().map(|(k, v)| (*k, *v)).collect(),
+        };
+        self.checkout(tip)?;
+
+        let records = self
+            .slots
+            .iter()
+            .map(|(slot, state)| StateRecord::Slot {
+                slot: *slot,
+                parent: state.parent,
+                updates: state.changes.iter().map(|(k, (_, v))| (*k, *v)).collect(),
+                root: state.root,
+            })
+            .collect::<Vec<_>>();
+        self.log =
+            StateLog::create(self.log.dir(), &checkpoint, &records).map_err(persist_error)?;
+        Ok(())
+    }
+
+    /// Moves the tree to the state of `slot`.
+    fn checkout(&mut self, slot: Slot) -> Result<()> {
+        if slot == self.tip {
+            return Ok(());
+        }
+        let path = self
+            .ancestors(slot)
+            .ok_or(StateError::SlotNotAvailable(slot))?;
+
+        while !path.contains(&self.tip) {
+            let state = &self.slots[&self.tip];
+            for (pubkey, (previous, _)) in &state.changes {
+                self.tree.update(*pubkey, *previous);
+            }
+            self.tip = state.parent;
+        }
+        let fork = path.iter().position(|s| *s == self.tip).unwrap();
+        for slot in path[..fork].iter().rev() {
+            for (pubkey, (_, hash)) in &self.slots[slot].changes {
+                self.tree.update(*pubkey, *hash);
+            }
+            self.tip = *slot;
+        }
+        Ok(())
+    }
+
+    /// `slot` followed by its ancestors down to the base slot.
+    fn ancestors(&self, mut slot: Slot) -> Option<Vec<Slot>> {
+        let mut path = vec![slot];
+        while slot != self.base {
+            slot = self.slots.get(&slot)?.parent;
+            path.push(slot);
+        }
+        Some(path)
+    }
+
+    fn descendants(&self, slot: Slot) -> HashSet<Slot> {
+        let mut descendants = HashSet::new();
+        // parents always precede their children
+        for (child, state) in self.slots.range(slot + 1..) {
+            if state.parent == slot || descendants.contains(&state.parent) {
+                descendants.insert(*child);
+            }
+        }
+        descendants
+    }
+
+    fn append(&mut self, record: &StateRecord) -> Result<()> {
+        self.log.append(record).map_err(persist_error)
+    }
+}
+
+fn persist_error(e: std::io::Error) -> crate::Error {
+    StateError::PersistFailed(e.to_string()).into()
+}
+
+impl RollupStorage {
+    /// Root of the account state tree at `slot`.
+    pub fn state_root(&self, slot: Slot) -> Result<Hash> {
+        self.state
+            .lock()
+            .unwrap()
+            .state_root(slot)
+            .ok_or(StateError::SlotNotAvailable(slot).into())
+    }
+
+    /// Proves the account at `slot` against [`RollupStorage::state_root`] of that slot.
+    pub fn prove_account(&self, pubkey: &Pubkey, slot: Slot) -> Result<AccountProof> {
+        self.state
+            .lock()
+            .unwrap()
+            .prove(pubkey, slot)?
+            .ok_or(StorageError::AccountNotFound.into())
+    }
+
+    pub(crate) fn sync_state(&self, bank: &Bank) -> Result<()> {
+        self.state.lock().unwrap().sync(bank)
+    }
+}
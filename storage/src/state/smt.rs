@@ -0,0 +1,191 @@
+use solana_sdk::{
+    account::ReadableAccount,
+    keccak::{hashv, Hash},
+    pubkey::Pubkey,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+};
+
+const TREE_DEPTH: usize = 256;
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hash of the account fields committed by the state tree, the rent epoch is left out.
+pub fn hash_account(account: &impl ReadableAccount) -> Hash {
+    hashv(&[
+        &account.lamports().to_le_bytes(),
+        account.owner().as_ref(),
+        &[account.executable() as u8],
+        account.data(),
+    ])
+}
+
+pub fn hash_leaf(pubkey: &Pubkey, account_hash: &Hash) -> Hash {
+    hashv(&[LEAF_PREFIX, pubkey.as_ref(), account_hash.as_ref()])
+}
+
+pub fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    hashv(&[NODE_PREFIX, left.as_ref(), right.as_ref()])
+}
+
+fn bit(key: &Pubkey, index: usize) -> bool {
+    key.as_ref()[index / 8] & (0x80 >> (index % 8)) != 0
+}
+
+fn flip_bit(key: &Pubkey, index: usize) -> Pubkey {
+    let mut bytes = key.to_bytes();
+    bytes[index / 8] ^= 0x80 >> (index % 8);
+    Pubkey::new_from_array(bytes)
+}
+
+/// Lowest and highest key sharing the first `depth` bits of `key`.
+fn subtree_range(key: &Pubkey, depth: usize) -> (Pubkey, Pubkey) {
+    let mut low = key.to_bytes();
+    let mut high = low;
+    let (byte, bits) = (depth / 8, depth % 8);
+    if byte < low.len() {
+        let mask = 0xff >> bits;
+        low[byte] &= !mask;
+        high[byte] |= mask;
+        low[byte + 1..].fill(0);
+        high[byte + 1..].fill(0xff);
+    }
+    (Pubkey::new_from_array(low), Pubkey::new_from_array(high))
+}
+
+fn common_prefix_len(a: &Pubkey, b: &Pubkey) -> usize {
+    a.as_ref()
+        .iter()
+        .zip(b.as_ref())
+        .enumerate()
+        .find(|(_, (x, y))| x != y)
+        .map(|(i, (x, y))| i * 8 + (x ^ y).leading_zeros() as usize)
+        .unwrap_or(TREE_DEPTH)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountProof {
+    pub pubkey: Pubkey,
+    /// [`hash_account`] of the proven account.
+    pub account_hash: Hash,
+    /// Sibling hashes from the root down to the leaf of the account.
+    pub siblings: Vec<Hash>,
+}
+
+impl AccountProof {
+    pub fn root(&self) -> Hash {
+        self.siblings.iter().enumerate().rev().fold(
+            hash_leaf(&self.pubkey, &self.account_hash),
+            |node, (depth, sibling)| {
+                if bit(&self.pubkey, depth) {
+                    hash_node(sibling, &node)
+                } else {
+                    hash_node(&node, sibling)
+                }
+            },
+        )
+    }
+
+    pub fn verify(&self, root: &Hash) -> bool {
+        self.root() == *root
+    }
+}
+
+/// Sparse Merkle tree keyed by the bits of the pubkey. Empty subtrees hash to zero and a subtree
+/// holding a single account hashes to that account's leaf, so the tree is only as deep as
+/// needed to tell the accounts apart.
+#[derive(Default)]
+pub struct SparseMerkleTree {
+    leaves: BTreeMap<Pubkey, Hash>,
+    /// Hashes of the subtrees holding two or more leaves, keyed by depth and lowest key.
+    nodes: HashMap<(usize, Pubkey), Hash>,
+}
+
+impl SparseMerkleTree {
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&Hash> {
+        self.leaves.get(pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Pubkey, &Hash)> {
+        self.leaves.iter()
+    }
+
+    /// Sets the account hash of `pubkey`, `None` removes the account.
+    pub fn update(&mut self, pubkey: Pubkey, account_hash: Option<Hash>) {
+        let previous = match account_hash {
+            Some(account_hash) => self.leaves.insert(pubkey, account_hash),
+            None => self.leaves.remove(&pubkey),
+        };
+        if previous == account_hash {
+            return;
+        }
+
+        // only subtrees shared with a neighbouring key hold internal nodes on this path
+        let predecessor = self.leaves.range(..pubkey).next_back();
+        let successor = self
+            .leaves
+            .range((Bound::Excluded(pubkey), Bound::Unbounded))
+            .next();
+        let Some(deepest) = predecessor
+            .into_iter()
+            .chain(successor)
+            .map(|(key, _)| common_prefix_len(&pubkey, key))
+            .max()
+        else {
+            self.nodes.clear();
+            return;
+        };
+
+        for depth in (0..=deepest).rev() {
+            let (low, high) = subtree_range(&pubkey, depth);
+            if self.leaves.range(low..=high).nth(1).is_some() {
+                let left = self.subtree_hash(depth + 1, &low);
+                let right = self.subtree_hash(depth + 1, &flip_bit(&low, depth));
+                self.nodes.insert((depth, low), hash_node(&left, &right));
+            } else {
+                self.nodes.remove(&(depth, low));
+            }
+        }
+    }
+
+    pub fn root(&self) -> Hash {
+        self.subtree_hash(0, &Pubkey::default())
+    }
+
+    pub fn proof(&self, pubkey: &Pubkey) -> Option<AccountProof> {
+        let account_hash = *self.leaves.get(pubkey)?;
+        let mut siblings = vec![];
+        for depth in 0..TREE_DEPTH {
+            let (low, high) = subtree_range(pubkey, depth);
+            if self.leaves.range(low..=high).nth(1).is_none() {
+                break;
+            }
+            siblings.push(self.subtree_hash(depth + 1, &flip_bit(pubkey, depth)));
+        }
+        Some(AccountProof {
+            pubkey: *pubkey,
+            account_hash,
+            siblings,
+        })
+    }
+
+    fn subtree_hash(&self, depth: usize, key: &Pubkey) -> Hash {
+        let (low, high) = subtree_range(key, depth);
+        let mut leaves = self.leaves.range(low..=high);
+        match (leaves.next(), leaves.next()) {
+            (None, _) => Hash::default(),
+            (Some((pubkey, account_hash)), None) => hash_leaf(pubkey, account_hash),
+            _ => self.nodes[&(depth, low)],
+        }
+    }
+}
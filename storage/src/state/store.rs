@@ -0,0 +1,238 @@
+use solana_sdk::{clock::Slot, keccak::Hash, pubkey::Pubkey};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+const CHECKPOINT_FILE: &str = "checkpoint";
+const LOG_FILE: &str = "log";
+
+const SLOT_RECORD: u8 = 0;
+const ROOT_RECORD: u8 = 1;
+
+/// State of the oldest retained slot, the log is replayed on top of it.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Checkpoint {
+    pub slot: Slot,
+    pub leaves: Vec<(Pubkey, Hash)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum StateRecord {
+    /// Account hashes written by `slot`, `None` for removed accounts.
+    Slot {
+        slot: Slot,
+        parent: Slot,
+        updates: Vec<(Pubkey, Option<Hash>)>,
+        root: Hash,
+    },
+    Root(Slot),
+}
+
+/// Append-only log of the state tree changes next to its checkpoint.
+pub(crate) struct StateLog {
+    dir: PathBuf,
+    writer: BufWriter<File>,
+    records: usize,
+}
+
+impl StateLog {
+    /// Replaces the checkpoint and log in `dir`, the new log holds `records`.
+    pub fn create(
+        dir: &Path,
+        checkpoint: &Checkpoint,
+        records: &[StateRecord],
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let mut buf = vec![];
+        buf.extend_from_slice(&checkpoint.slot.to_le_bytes());
+        buf.extend_from_slice(&(checkpoint.leaves.len() as u64).to_le_bytes());
+        for (pubkey, hash) in &checkpoint.leaves {
+            buf.extend_from_slice(pubkey.as_ref());
+            buf.extend_from_slice(hash.as_ref());
+        }
+        write_atomic(&dir.join(CHECKPOINT_FILE), &buf)?;
+
+        buf.clear();
+        for record in records {
+            encode_record(record, &mut buf);
+        }
+        write_atomic(&dir.join(LOG_FILE), &buf)?;
+
+        Self::open_writer(dir, records.len())
+    }
+
+    /// Reads the checkpoint and the log in `dir`, `None` if there is no checkpoint. A record cut
+    /// short by a crash is dropped from the log.
+    pub fn load(dir: &Path) -> io::Result<Option<(Checkpoint, Vec<StateRecord>, Self)>> {
+        let checkpoint = match fs::read(dir.join(CHECKPOINT_FILE)) {
+            Ok(bytes) => decode_checkpoint(&bytes).ok_or_else(|| corrupted(CHECKPOINT_FILE))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let log_path = dir.join(LOG_FILE);
+        let bytes = match fs::read(&log_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+        let mut records = vec![];
+        let mut cursor = bytes.as_slice();
+        while !cursor.is_empty() {
+            let mut next = cursor;
+            match decode_record(&mut next) {
+                Some(record) => {
+                    records.push(record);
+                    cursor = next;
+                }
+                None => {
+                    warn!("dropping truncated state log record in {:?}", log_path);
+                    break;
+                }
+            }
+        }
+        let valid_len = bytes.len() - cursor.len();
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&log_path)?
+            .set_len(valid_len as u64)?;
+
+        let log = Self::open_writer(dir, records.len())?;
+        Ok(Some((checkpoint, records, log)))
+    }
+
+    pub fn append(&mut self, record: &StateRecord) -> io::Result<()> {
+        let mut buf = vec![];
+        encode_record(record, &mut buf);
+        self.writer.write_all(&buf)?;
+        self.writer.flush()?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Records appended since the checkpoint.
+    pub fn len(&self) -> usize {
+        self.records
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn open_writer(dir: &Path, records: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            writer: BufWriter::new(file),
+            records,
+        })
+    }
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+fn corrupted(file: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupted state {file}"),
+    )
+}
+
+fn encode_record(record: &StateRecord, buf: &mut Vec<u8>) {
+    match record {
+        StateRecord::Slot {
+            slot,
+            parent,
+            updates,
+            root,
+        } => {
+            buf.push(SLOT_RECORD);
+            buf.extend_from_slice(&slot.to_le_bytes());
+            buf.extend_from_slice(&parent.to_le_bytes());
+            buf.extend_from_slice(&(updates.len() as u64).to_le_bytes());
+            for (pubkey, hash) in updates {
+                buf.extend_from_slice(pubkey.as_ref());
+                match hash {
+                    Some(hash) => {
+                        buf.push(1);
+                        buf.extend_from_slice(hash.as_ref());
+                    }
+                    None => buf.push(0),
+                }
+            }
+            buf.extend_from_slice(root.as_ref());
+        }
+        StateRecord::Root(slot) => {
+            buf.push(ROOT_RECORD);
+            buf.extend_from_slice(&slot.to_le_bytes());
+        }
+    }
+}
+
+fn take<const N: usize>(cursor: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, tail) = cursor.split_first_chunk::<N>()?;
+    *cursor = tail;
+    Some(*head)
+}
+
+fn take_u64(cursor: &mut &[u8]) -> Option<u64> {
+    take(cursor).map(u64::from_le_bytes)
+}
+
+fn decode_record(cursor: &mut &[u8]) -> Option<StateRecord> {
+    let [tag] = take(cursor)?;
+    match tag {
+        SLOT_RECORD => {
+            let slot = take_u64(cursor)?;
+            let parent = take_u64(cursor)?;
+            let len = take_u64(cursor)?;
+            let mut updates = vec![];
+            for _ in 0..len {
+                let pubkey = Pubkey::new_from_array(take(cursor)?);
+                let hash = match take(cursor)? {
+                    [0] => None,
+                    [1] => Some(Hash::new_from_array(take(cursor)?)),
+                    _ => return None,
+                };
+                updates.push((pubkey, hash));
+            }
+            let root = Hash::new_from_array(take(cursor)?);
+            Some(StateRecord::Slot {
+                slot,
+                parent,
+                updates,
+                root,
+            })
+        }
+        ROOT_RECORD => Some(StateRecord::Root(take_u64(cursor)?)),
+        _ => None,
+    }
+}
+
+fn decode_checkpoint(mut cursor: &[u8]) -> Option<Checkpoint> {
+    let cursor = &mut cursor;
+    let slot = take_u64(cursor)?;
+    let len = take_u64(cursor)?;
+    let mut leaves = vec![];
+    for _ in 0..len {
+        leaves.push((
+            Pubkey::new_from_array(take(cursor)?),
+            Hash::new_from_array(take(cursor)?),
+        ));
+    }
+    cursor.is_empty().then_some(Checkpoint { slot, leaves })
+}
@@ -0,0 +1,211 @@
+use anyhow::Result;
+use solana_runtime::{bank::Bank, genesis_utils::create_genesis_config};
+use solana_sdk::{
+    account::{AccountSharedData, ReadableAccount},
+    clock::Slot,
+    keccak::{hashv, Hash},
+    pubkey::Pubkey,
+};
+use std::{fs::OpenOptions, io::Write, sync::Arc};
+
+use super::{
+    hash_account,
+    smt::{hash_leaf, hash_node},
+    store::{Checkpoint, StateLog, StateRecord},
+    SparseMerkleTree, StateCommitment,
+};
+
+/// Root computed from scratch, splitting the leaves bit by bit.
+fn naive_root(leaves: &[(Pubkey, Hash)], depth: usize) -> Hash {
+    match leaves {
+        [] => Hash::default(),
+        [(pubkey, hash)] => hash_leaf(pubkey, hash),
+        _ => {
+            let split = leaves
+                .iter()
+                .position(|(pubkey, _)| pubkey.as_ref()[depth / 8] & (0x80 >> (depth % 8)) != 0)
+                .unwrap_or(leaves.len());
+            hash_node(
+                &naive_root(&leaves[..split], depth + 1),
+                &naive_root(&leaves[split..], depth + 1),
+            )
+        }
+    }
+}
+
+fn scan_root(bank: &Bank) -> Hash {
+    let mut tree = SparseMerkleTree::default();
+    bank.scan_all_accounts(
+        |account: Option<(&Pubkey, AccountSharedData, Slot)>| {
+            if let Some((pubkey, account, _)) = account.filter(|(_, a, _)| a.lamports() > 0) {
+                tree.update(*pubkey, Some(hash_account(&account)));
+            }
+        },
+        true,
+    )
+    .unwrap();
+    tree.root()
+}
+
+#[test]
+fn sparse_merkle_tree_works() {
+    let mut tree = SparseMerkleTree::default();
+    assert_eq!(tree.root(), Hash::default());
+
+    // unique pubkeys share long prefixes, random ones do not
+    let keys = (0..32)
+        .map(|_| Pubkey::new_unique())
+        .chain((0..32).map(|_| solana_sdk::pubkey::new_rand()))
+        .collect::<Vec<_>>();
+    for (i, key) in keys.iter().enumerate() {
+        tree.update(*key, Some(hashv(&[&[i as u8]])));
+    }
+    let mut leaves = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    assert_eq!(tree.root(), naive_root(&leaves, 0));
+
+    for key in keys.iter().step_by(2) {
+        tree.update(*key, None);
+    }
+    leaves.retain(|(key, _)| tree.get(key).is_some());
+    assert_eq!(leaves.len(), 32);
+    assert_eq!(tree.root(), naive_root(&leaves, 0));
+
+    let root = tree.root();
+    for (key, hash) in &leaves {
+        let mut proof = tree.proof(key).unwrap();
+        assert_eq!(proof.account_hash, *hash);
+        assert!(proof.verify(&root));
+        proof.account_hash = Hash::default();
+        assert!(!proof.verify(&root));
+    }
+    assert!(tree.proof(&keys[0]).is_none());
+
+    for (key, _) in &leaves {
+        tree.update(*key, None);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.root(), Hash::default());
+}
+
+#[test]
+fn state_log_works() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let checkpoint = Checkpoint {
+        slot: 3,
+        leaves: vec![(Pubkey::new_unique(), hashv(&[b"a"]))],
+    };
+    let records = vec![
+        StateRecord::Slot {
+            slot: 4,
+            parent: 3,
+            updates: vec![
+                (Pubkey::new_unique(), Some(hashv(&[b"b"]))),
+                (checkpoint.leaves[0].0, None),
+            ],
+            root: hashv(&[b"root"]),
+        },
+        StateRecord::Root(4),
+    ];
+    let mut log = StateLog::create(dir.path(), &checkpoint, &records[..1])?;
+    log.append(&records[1])?;
+    assert_eq!(log.len(), 2);
+    drop(log);
+
+    // a record cut short is dropped, later appends follow the last complete one
+    OpenOptions::new()
+        .append(true)
+        .open(dir.path().join("log"))?
+        .write_all(&[0, 5, 0])?;
+    let (loaded, loaded_records, mut log) = StateLog::load(dir.path())?.unwrap();
+    assert_eq!(loaded, checkpoint);
+    assert_eq!(loaded_records, records);
+    log.append(&StateRecord::Root(5))?;
+    drop(log);
+    let (_, loaded_records, _) = StateLog::load(dir.path())?.unwrap();
+    assert_eq!(loaded_records.len(), 3);
+    assert_eq!(loaded_records[2], StateRecord::Root(5));
+
+    assert!(StateLog::load(&dir.path().join("missing"))?.is_none());
+    Ok(())
+}
+
+#[test]
+fn state_commitment_follows_forks() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let genesis = create_genesis_config(1_000_000_000);
+    let bank0 = Arc::new(Bank::new_for_tests(&genesis.genesis_config));
+    bank0.freeze();
+    let mut state = StateCommitment::open(dir.path(), &bank0, 16)?;
+    assert_eq!(state.state_root(0), Some(scan_root(&bank0)));
+
+    //  slot 4  * (E)
+    //          |
+    //  slot 3  * (D)
+    //          |
+    //  slot 2  |   * (C)
+    //          |  /
+    //  slot 1  * (B)
+    //          |
+    //  slot 0  * (A)
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let bank1 = Arc::new(Bank::new_from_parent(bank0, &Pubkey::default(), 1));
+    bank1.store_account(&alice, &AccountSharedData::new(100, 0, &Pubkey::default()));
+    state.sync(&bank1)?;
+    assert_eq!(state.state_root(1), Some(scan_root(&bank1)));
+    // syncing a slot again picks up the writes of freezing it
+    bank1.freeze();
+    state.sync(&bank1)?;
+
+    let bank2 = Arc::new(Bank::new_from_parent(bank1.clone(), &Pubkey::default(), 2));
+    bank2.store_account(&alice, &AccountSharedData::new(200, 0, &Pubkey::default()));
+    bank2.store_account(&bob, &AccountSharedData::new(100, 0, &Pubkey::default()));
+    bank2.freeze();
+    state.sync(&bank2)?;
+
+    // the empty slot 3 is recorded through its child
+    let bank3 = Arc::new(Bank::new_from_parent(bank1.clone(), &Pubkey::default(), 3));
+    let bank4 = Arc::new(Bank::new_from_parent(bank3.clone(), &Pubkey::default(), 4));
+    bank4.store_account(&alice, &AccountSharedData::new(0, 0, &Pubkey::default()));
+    state.sync(&bank4)?;
+
+    for bank in [&bank1, &bank2, &bank3, &bank4] {
+        assert_eq!(state.state_root(bank.slot()), Some(scan_root(bank)));
+    }
+    let proof = state.prove(&alice, 2)?.unwrap();
+    assert_eq!(
+        proof.account_hash,
+        hash_account(&bank2.get_account(&alice).unwrap())
+    );
+    assert!(proof.verify(&state.state_root(2).unwrap()));
+    assert!(state.prove(&alice, 4)?.is_none());
+    assert!(state.prove(&bob, 1)?.is_none());
+
+    bank4.store_account(&bob, &AccountSharedData::new(300, 0, &Pubkey::default()));
+    state.sync(&bank4)?;
+    assert_eq!(state.state_root(4), Some(scan_root(&bank4)));
+
+    state.set_root(&bank3)?;
+    assert!(state.state_root(2).is_none());
+    let root1 = state.state_root(1).unwrap();
+    let root4 = state.state_root(4).unwrap();
+    drop(state);
+
+    // restarts replay the log
+    let mut state = StateCommitment::open(dir.path(), &bank3, 16)?;
+    assert_eq!(state.state_root(1), Some(root1));
+    assert_eq!(state.state_root(4), Some(root4));
+    assert!(state.state_root(2).is_none());
+    assert!(state.prove(&bob, 4)?.unwrap().verify(&root4));
+
+    // history is bounded, the oldest retained slot becomes the base
+    state.history_slots = 0;
+    state.set_root(&bank4)?;
+    assert!(state.state_root(1).is_none());
+    assert_eq!(state.state_root(4), Some(root4));
+    state.compact()?;
+    drop(state);
+    let state = StateCommitment::open(dir.path(), &bank4, 16)?;
+    assert_eq!(state.state_root(4), Some(root4));
+    Ok(())
+}
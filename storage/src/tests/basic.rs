@@ -1,6 +1,7 @@
 use crate::{
     blockstore::txs::CommitBatch,
     config::GlobalConfig,
+    events::CommitEvent,
     execution::TransactionsResultWrapper,
     init::default::{DEFAULT_MINT_LAMPORTS, DEFAULT_STAKE_LAMPORTS, DEFAULT_VALIDATOR_LAMPORTS},
     tests::mock::{assert_result_balance, processor::process_transfers_ex},
@@ -95,6 +96,49 @@ async fn storage_basic_process_works() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn commit_events_works() -> Result<()> {
+    let ledger_path = tempfile::tempdir()?.into_path();
+    let mut store = RollupStorage::new(GlobalConfig::new_temp(&ledger_path)?)?;
+    store.init()?;
+    let events = store.subscribe_commit_events();
+    let alice = store.config.keypairs.mint_keypair.clone().unwrap();
+    let bob = Keypair::new().pubkey();
+
+    store.bump()?;
+    let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+        &alice,
+        &bob,
+        1000000,
+        store.bank.last_blockhash(),
+    ));
+    let results = process_transfers_ex(&store, vec![tx.clone()]);
+    store
+        .commit(
+            vec![TransactionsResultWrapper { output: results }],
+            vec![CommitBatch::new(vec![tx.clone()].into())],
+        )
+        .await?;
+    store.confirm(1)?;
+    store.force_save().await?;
+
+    match events.try_recv()? {
+        CommitEvent::Block { bank, transactions } => {
+            assert_eq!(bank.slot(), 1);
+            assert_eq!(transactions.len(), 1);
+            assert_eq!(transactions[0].signature, *tx.signature());
+            assert!(transactions[0].err.is_none());
+            assert!(transactions[0].writable_accounts.contains(&bob));
+        }
+        event => panic!("unexpected event {event:?}"),
+    }
+    assert!(matches!(events.try_recv()?, CommitEvent::Confirmed(bank) if bank.slot() == 1));
+    assert!(matches!(events.try_recv()?, CommitEvent::Rooted(bank) if bank.slot() == 1));
+    assert!(events.try_recv().is_err());
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn basic_process_tests(
     mut store: RollupStorage,
@@ -192,7 +236,9 @@ async fn basic_process_tests(
     assert_eq!(store.balance(&dave), TO_DAVE);
 
     // 3. save and close
+    assert_eq!(store.confirmed_slot(), 0);
     store.confirm(store.current_height())?;
+    assert_eq!(store.confirmed_slot(), 1);
     store.close().await?;
 
     // 4. open again
@@ -203,6 +249,7 @@ async fn basic_process_tests(
     let (bank_height, store_height) = store.get_mixed_heights()?;
     assert_eq!(bank_height, 1);
     assert_eq!(store_height, Some(1));
+    assert_eq!(store.confirmed_slot(), 1);
     // TODO: check why bob balance is not `alice_init_balance - TO_CHARLIE` ?
     assert_eq!(
         store.balance(&alice.pubkey()),
@@ -1,3 +1,4 @@
+use crate::{blockstore::txs::CommitBatch, execution::TransactionsResultWrapper, RollupStorage};
 use solana_sdk::{
     account::{AccountSharedData, ReadableAccount},
     pubkey::Pubkey,
@@ -18,3 +19,14 @@ pub fn assert_result_balance(account: Pubkey, lamports: Option<u64>, tx: &Loaded
         None => assert!(lamports.is_none()),
     }
 }
+
+/// Commits a block holding a single empty batch into the working bank.
+pub async fn commit_empty_block(store: &mut RollupStorage) -> crate::Result<()> {
+    let results = processor::process_transfers_ex(store, vec![]);
+    store
+        .commit(
+            vec![TransactionsResultWrapper { output: results }],
+            vec![CommitBatch::new(vec![].into())],
+        )
+        .await
+}
@@ -12,6 +12,7 @@ use crate::{
     config::GlobalConfig,
     execution::TransactionsResultWrapper,
     init::default::{DEFAULT_MINT_LAMPORTS, DEFAULT_VALIDATOR_LAMPORTS},
+    state::hash_account,
     tests::mock::processor::process_transfers_ex,
     RollupStorage,
 };
@@ -207,6 +208,18 @@ async fn reorg_works() -> Result<()> {
     assert_eq!(store.balance(&charlie), SLOT1_TO_CHARLIE + SLOT3_TO_CHARLIE);
     assert_eq!(store.balance(&dave), SLOT1_TO_DAVE + SLOT3_TO_DAVE);
 
+    // the state tree follows the reorg, the abandoned fork is no longer provable
+    let state_root = store.state_root(3)?;
+    let proof = store.prove_account(&charlie, 3)?;
+    assert_eq!(
+        proof.account_hash,
+        hash_account(&store.get_account(&charlie)?)
+    );
+    assert!(proof.verify(&state_root));
+    assert!(store.state_root(1).is_ok());
+    assert!(store.state_root(2).is_err());
+    assert!(store.state_root(4).is_err());
+
     let bank_forks = store.bank_forks.read().unwrap();
     assert!(bank_forks.get(0).is_none());
     assert!(bank_forks.get(1).is_some());
@@ -10,6 +10,9 @@ pub enum Error {
 
     #[error(transparent)]
     TransactionError(#[from] TransactionError),
+
+    #[error(transparent)]
+    RollupError(#[from] RollupError),
 }
 
 #[derive(Error, Debug)]
@@ -38,3 +41,14 @@ pub enum TicksError {
     #[error("trailing entry")]
     TrailingEntry,
 }
+
+#[derive(Error, Debug)]
+pub enum RollupError {
+    /// L2 blocks can not be further ahead of their L1 origin than the max sequencer drift
+    #[error("sequencer drift exceeded at sequence number {0}")]
+    SequencerDriftExceeded(u64),
+
+    /// L2 blocks have to be confirmed within the sequencing window of their L1 origin
+    #[error("epoch {0} outside the sequencing window")]
+    OutsideSequencingWindow(u64),
+}
@@ -10,6 +10,7 @@ use solana_svm::{
 use std::{borrow::Cow, sync::Arc};
 
 pub mod error;
+pub mod rollup;
 pub mod settings;
 
 pub use error::{Error, Result};
@@ -0,0 +1,48 @@
+use crate::{error::RollupError, Result};
+
+pub const DEFAULT_BLOCK_TIME: u64 = 2;
+pub const DEFAULT_MAX_SEQUENCER_DRIFT: u64 = 600;
+pub const DEFAULT_SEQ_WINDOW_SIZE: u64 = 3600;
+
+/// Parameters bounding the L2 blocks produced on top of each L1 block (epoch).
+#[derive(Debug, Clone)]
+pub struct RollupParams {
+    /// Seconds between two consecutive L2 blocks.
+    pub block_time: u64,
+    /// Seconds an L2 block may be ahead of its L1 origin.
+    pub max_sequencer_drift: u64,
+    /// L1 blocks after its L1 origin an L2 block has to be confirmed by DA in.
+    pub seq_window_size: u64,
+}
+
+impl Default for RollupParams {
+    fn default() -> Self {
+        Self {
+            block_time: DEFAULT_BLOCK_TIME,
+            max_sequencer_drift: DEFAULT_MAX_SEQUENCER_DRIFT,
+            seq_window_size: DEFAULT_SEQ_WINDOW_SIZE,
+        }
+    }
+}
+
+impl RollupParams {
+    /// Timestamp of the L2 block at `sequence_number` of the epoch at `epoch_timestamp`.
+    pub fn l2_timestamp(&self, epoch_timestamp: u64, sequence_number: u64) -> u64 {
+        epoch_timestamp.saturating_add(sequence_number.saturating_mul(self.block_time))
+    }
+
+    pub fn check_drift(&self, epoch_timestamp: u64, sequence_number: u64) -> Result<()> {
+        let drift = self.l2_timestamp(epoch_timestamp, sequence_number) - epoch_timestamp;
+        if drift > self.max_sequencer_drift {
+            return Err(RollupError::SequencerDriftExceeded(sequence_number).into());
+        }
+        Ok(())
+    }
+
+    pub fn check_window(&self, epoch_height: u64, l1_height: u64) -> Result<()> {
+        if l1_height > epoch_height.saturating_add(self.seq_window_size) {
+            return Err(RollupError::OutsideSequencingWindow(epoch_height).into());
+        }
+        Ok(())
+    }
+}